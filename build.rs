@@ -3,6 +3,27 @@ extern crate autocfg;
 fn main() {
     let ac = autocfg::new();
 
+    // Declare every cfg we might emit, so that rustc>=1.80 does not warn about them.
+    // Older versions of cargo simply ignore this directive.
+    for cfg in &[
+        "tuple_ty",
+        "allow_clippy",
+        "maybe_uninit",
+        "type_name",
+        "doctests",
+        "offset_from",
+        "raw_ref_macros",
+        "stable_const",
+        "stable_offset_of",
+        "inline_const",
+        "nested_offset_of",
+        "offset_of_enum",
+        // Set by Kani, and used by the harnesses of `verification_harnesses!`.
+        "kani",
+    ] {
+        println!("cargo:rustc-check-cfg=cfg({})", cfg);
+    }
+
     // Check for a minimum version for a few features
     if ac.probe_rustc_version(1, 20) {
         println!("cargo:rustc-cfg=tuple_ty");
@@ -13,9 +34,15 @@ fn main() {
     if ac.probe_rustc_version(1, 36) {
         println!("cargo:rustc-cfg=maybe_uninit");
     }
+    if ac.probe_rustc_version(1, 38) {
+        println!("cargo:rustc-cfg=type_name");
+    }
     if ac.probe_rustc_version(1, 40) {
         println!("cargo:rustc-cfg=doctests");
     }
+    if ac.probe_rustc_version(1, 47) {
+        println!("cargo:rustc-cfg=offset_from");
+    }
     if ac.probe_rustc_version(1, 51) {
         println!("cargo:rustc-cfg=raw_ref_macros");
     }
@@ -25,4 +52,14 @@ fn main() {
     if ac.probe_rustc_version(1, 77) {
         println!("cargo:rustc-cfg=stable_offset_of");
     }
+    if ac.probe_rustc_version(1, 79) {
+        println!("cargo:rustc-cfg=inline_const");
+    }
+    if ac.probe_rustc_version(1, 82) {
+        println!("cargo:rustc-cfg=nested_offset_of");
+    }
+    // Only used to test enum variant fields, which need a nightly feature in the calling crate.
+    if ac.probe_raw("#![feature(offset_of_enum)]").is_ok() {
+        println!("cargo:rustc-cfg=offset_of_enum");
+    }
 }
@@ -0,0 +1,615 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tables of layout hashes, for checking that separately compiled binaries, such as a host and
+//! its dynamically loaded plugins, agree on the layout of the types they share.
+//!
+//! Each side builds an `AbiTable` with `abi_table!` or exports one under a well-known symbol
+//! with `export_abi_table!`. The host then compares the tables with `check_abi`, or with
+//! `check_plugin_abi` given the function it loaded from the plugin.
+//!
+//! Only sizes, alignments, and the names, offsets and sizes of fields are hashed, not the types
+//! of the fields: `abi_table!` hashes at compile time, where `core::any::type_name` cannot be
+//! called. So a field that changes to another type of the same size, such as from `u32` to
+//! `f32`, goes undetected.
+
+use core::{slice, str};
+use layout::TypeLayout;
+
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(feature = "std")]
+use std::fmt;
+#[cfg(feature = "std")]
+use std::string::{String, ToString};
+
+/// The name of the function that `export_abi_table!` exports, e.g. for looking it up with
+/// `dlsym`.
+pub const ABI_TABLE_SYMBOL: &str = "memoffset_abi_table";
+
+/// The version of the `AbiTable` format, which is bumped whenever the format or the way layouts
+/// are hashed changes.
+pub const ABI_TABLE_VERSION: u32 = 1;
+
+/// An incremental 64-bit FNV-1a hash of a layout.
+///
+/// Sizes and offsets are hashed as 64-bit integers, so the same layout hashes the same on every
+/// target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutHasher(u64);
+
+impl LayoutHasher {
+    /// Creates a hasher in its initial state.
+    pub const fn new() -> LayoutHasher {
+        LayoutHasher(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// Feeds bytes into the hash.
+    pub const fn write(self, bytes: &[u8]) -> LayoutHasher {
+        let mut hash = self.0;
+        let mut i = 0;
+        while i < bytes.len() {
+            hash ^= bytes[i] as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            i += 1;
+        }
+        LayoutHasher(hash)
+    }
+
+    /// Feeds a length-prefixed string into the hash.
+    pub const fn write_str(self, s: &str) -> LayoutHasher {
+        self.write_usize(s.len()).write(s.as_bytes())
+    }
+
+    /// Feeds an integer into the hash.
+    pub const fn write_usize(self, n: usize) -> LayoutHasher {
+        self.write(&(n as u64).to_le_bytes())
+    }
+
+    /// Feeds the name, offset and size of one field into the hash.
+    ///
+    /// The type of the field is not hashed.
+    pub const fn write_field(self, name: &str, offset: usize, size: usize) -> LayoutHasher {
+        self.write_str(name).write_usize(offset).write_usize(size)
+    }
+
+    /// Returns the hash.
+    pub const fn finish(self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for LayoutHasher {
+    fn default() -> LayoutHasher {
+        LayoutHasher::new()
+    }
+}
+
+/// Hashes the size and alignment of a type, and the names, offsets and sizes of its described
+/// fields, in order.
+///
+/// This is the hash `abi_table!` computes at compile time for the same type and fields.
+/// The names of the types themselves and of the types of the fields are not part of the hash,
+/// so a field that changes to another type of the same size keeps the same hash.
+pub fn layout_hash(layout: &TypeLayout) -> u64 {
+    let mut hasher = LayoutHasher::new()
+        .write_usize(layout.size)
+        .write_usize(layout.align);
+    for field in layout.fields {
+        hasher = hasher.write_field(field.name, field.offset, field.size);
+    }
+    hasher.finish()
+}
+
+/// An entry of an `AbiTable`: the name of a type, and the hash of its layout.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct AbiEntry {
+    /// A pointer to the UTF-8 name of the type.
+    pub name: *const u8,
+    /// The length of the name, in bytes.
+    pub name_len: usize,
+    /// The hash of the layout of the type, as computed by `layout_hash`.
+    pub hash: u64,
+}
+
+impl AbiEntry {
+    /// Creates an entry.
+    pub const fn new(name: &'static str, hash: u64) -> AbiEntry {
+        AbiEntry {
+            name: name.as_ptr(),
+            name_len: name.len(),
+            hash,
+        }
+    }
+
+    /// Returns the name of the type.
+    ///
+    /// ## Safety
+    /// `name` and `name_len` must describe a valid UTF-8 string that outlives `'a`.
+    pub unsafe fn name<'a>(&self) -> &'a str {
+        str::from_utf8_unchecked(slice::from_raw_parts(self.name, self.name_len))
+    }
+}
+
+/// A table of layout hashes, with a C-compatible representation so that it can be passed
+/// between separately compiled binaries.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct AbiTable {
+    /// The version of the format of the table; `ABI_TABLE_VERSION` for tables built by this
+    /// version of the crate.
+    pub version: u32,
+    /// The number of entries.
+    pub len: usize,
+    /// A pointer to the first of `len` entries.
+    pub entries: *const AbiEntry,
+}
+
+// The pointers of a table built by `abi_table!` point to immutable statics.
+unsafe impl Sync for AbiEntry {}
+unsafe impl Sync for AbiTable {}
+
+impl AbiTable {
+    /// Returns the entries of the table.
+    ///
+    /// ## Safety
+    /// `entries` and `len` must describe a valid array of entries that outlives `'a`.
+    pub unsafe fn entries<'a>(&self) -> &'a [AbiEntry] {
+        if self.len == 0 {
+            &[]
+        } else {
+            slice::from_raw_parts(self.entries, self.len)
+        }
+    }
+}
+
+/// Builds a `&'static AbiTable` with the layout hashes of the given structs, computed at compile
+/// time from their size, alignment, and the names, offsets and sizes of the listed fields.
+///
+/// Each type is given by its name, which must be in scope, and types are matched between tables
+/// by that name, so both sides should name each type the same way.
+///
+/// ## Examples
+/// ```
+/// use memoffset::abi::layout_hash;
+/// use memoffset::{abi_table, type_layout};
+///
+/// #[repr(C)]
+/// struct Request {
+///     id: u32,
+///     len: u16,
+/// }
+///
+/// let table = abi_table!([Request { id, len }]);
+/// let entries = unsafe { table.entries() };
+/// assert_eq!(unsafe { entries[0].name() }, "Request");
+/// assert_eq!(entries[0].hash, layout_hash(&type_layout!(Request { id, len })));
+/// ```
+///
+/// Requires rustc 1.65 or newer.
+#[macro_export]
+macro_rules! abi_table {
+    ([$($parent:ident { $($field:tt),* $(,)* }),* $(,)*] $(,)*) => {{
+        static ENTRIES: [$crate::abi::AbiEntry; <[&str]>::len(&[$(stringify!($parent)),*])] = [$(
+            $crate::abi::AbiEntry::new(
+                stringify!($parent),
+                $crate::abi::LayoutHasher::new()
+                    .write_usize($crate::__priv::mem::size_of::<$parent>())
+                    .write_usize($crate::__priv::mem::align_of::<$parent>())
+                    $(.write_field(
+                        stringify!($field),
+                        $crate::offset_of!($parent, $field),
+                        $crate::_memoffset__field_size!($parent, $field),
+                    ))*
+                    .finish(),
+            )
+        ),*];
+        static TABLE: $crate::abi::AbiTable = $crate::abi::AbiTable {
+            version: $crate::abi::ABI_TABLE_VERSION,
+            len: ENTRIES.len(),
+            entries: &ENTRIES as *const [$crate::abi::AbiEntry] as *const $crate::abi::AbiEntry,
+        };
+        &TABLE
+    }};
+}
+
+/// Exports an `AbiTable` built as with `abi_table!` from an `extern "C"` function named
+/// `memoffset_abi_table` (see `ABI_TABLE_SYMBOL`), for the host of a plugin system to check with
+/// `check_plugin_abi`.
+///
+/// As the name of the function is fixed, this may only be used once per binary.
+///
+/// ## Examples
+/// ```
+/// #[macro_use]
+/// extern crate memoffset;
+///
+/// #[repr(C)]
+/// pub struct Request {
+///     id: u32,
+///     len: u16,
+/// }
+///
+/// export_abi_table!([Request { id, len }]);
+///
+/// fn main() {
+///     let table = unsafe { &*memoffset_abi_table() };
+///     assert_eq!(table.len, 1);
+/// }
+/// ```
+///
+/// Requires rustc 1.65 or newer.
+#[macro_export]
+macro_rules! export_abi_table {
+    ([$($types:tt)*] $(,)*) => {
+        /// Returns the layout hashes of the types this binary shares with others.
+        #[no_mangle]
+        pub extern "C" fn memoffset_abi_table() -> *const $crate::abi::AbiTable {
+            $crate::abi_table!([$($types)*])
+        }
+    };
+}
+
+/// The reason the tables of a host and a plugin do not agree, as reported by `check_abi`.
+///
+/// Requires the `std` cargo feature.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiMismatch {
+    /// The plugin did not provide a table.
+    NoTable,
+    /// The tables have different format versions, so their hashes cannot be compared.
+    Version {
+        /// The version of the table of the host.
+        host: u32,
+        /// The version of the table of the plugin.
+        plugin: u32,
+    },
+    /// The plugin does not describe a type that the host does.
+    Missing {
+        /// The name of the type.
+        type_name: String,
+    },
+    /// A type has a different layout in the plugin than in the host.
+    Layout {
+        /// The name of the type.
+        type_name: String,
+        /// The hash of the layout in the host.
+        host: u64,
+        /// The hash of the layout in the plugin.
+        plugin: u64,
+    },
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for AbiMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AbiMismatch::NoTable => write!(f, "the plugin does not provide a layout table"),
+            AbiMismatch::Version { host, plugin } => write!(
+                f,
+                "the layout table of the plugin has version {}, but the host expects version {}",
+                plugin, host
+            ),
+            AbiMismatch::Missing { ref type_name } => {
+                write!(
+                    f,
+                    "the plugin does not describe the layout of `{}`",
+                    type_name
+                )
+            }
+            AbiMismatch::Layout {
+                ref type_name,
+                host,
+                plugin,
+            } => write!(
+                f,
+                "the layout of `{}` differs between the host and the plugin \
+                 (hash {:016x} in the host, {:016x} in the plugin)",
+                type_name, host, plugin
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for AbiMismatch {}
+
+/// Checks that the plugin table describes every type of the host table, with the same layout.
+/// Types that only the plugin describes are ignored.
+///
+/// Returns the first mismatch, in the order of the host table.
+///
+/// ## Safety
+/// Both tables must be valid, as those built by `abi_table!` are, for the duration of the call.
+///
+/// Requires the `std` cargo feature.
+#[cfg(feature = "std")]
+pub unsafe fn check_abi(host: &AbiTable, plugin: &AbiTable) -> Result<(), AbiMismatch> {
+    if host.version != plugin.version {
+        return Err(AbiMismatch::Version {
+            host: host.version,
+            plugin: plugin.version,
+        });
+    }
+    let plugin_entries = plugin.entries();
+    for entry in host.entries() {
+        let name = entry.name();
+        match plugin_entries.iter().find(|other| other.name() == name) {
+            None => {
+                return Err(AbiMismatch::Missing {
+                    type_name: name.to_string(),
+                })
+            }
+            Some(other) if other.hash != entry.hash => {
+                return Err(AbiMismatch::Layout {
+                    type_name: name.to_string(),
+                    host: entry.hash,
+                    plugin: other.hash,
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Checks the table returned by a plugin's `memoffset_abi_table` function against the table of
+/// the host, as `check_abi` does.
+///
+/// ## Examples
+/// ```
+/// use memoffset::abi::{check_plugin_abi, AbiMismatch, AbiTable};
+/// use memoffset::abi_table;
+///
+/// #[repr(C)]
+/// struct Request {
+///     id: u32,
+///     len: u16,
+/// }
+///
+/// mod plugin {
+///     #[repr(C)]
+///     pub struct Request {
+///         pub id: u64,
+///         pub len: u16,
+///     }
+/// }
+///
+/// // Stands in for the function looked up in the plugin with `dlsym`.
+/// extern "C" fn plugin_table() -> *const AbiTable {
+///     use plugin::Request;
+///     abi_table!([Request { id, len }])
+/// }
+///
+/// fn main() {
+///     let host = abi_table!([Request { id, len }]);
+///     let err = unsafe { check_plugin_abi(host, plugin_table) }.unwrap_err();
+///     assert!(matches!(err, AbiMismatch::Layout { ref type_name, .. } if type_name == "Request"));
+/// }
+/// ```
+///
+/// ## Safety
+/// `get_table` must return either a null pointer, or a pointer to a table that is valid, as
+/// those built by `abi_table!` are, for the duration of the call.
+///
+/// Requires the `std` cargo feature.
+#[cfg(feature = "std")]
+pub unsafe fn check_plugin_abi(
+    host: &AbiTable,
+    get_table: extern "C" fn() -> *const AbiTable,
+) -> Result<(), AbiMismatch> {
+    let plugin = get_table();
+    if plugin.is_null() {
+        return Err(AbiMismatch::NoTable);
+    }
+    check_abi(host, &*plugin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{layout_hash, AbiTable, LayoutHasher};
+
+    #[repr(C)]
+    struct Header {
+        magic: u32,
+        len: u16,
+    }
+
+    #[repr(C)]
+    struct Tuple(u8, u64);
+
+    #[test]
+    fn hash_matches_layout() {
+        let table = abi_table!([Header { magic, len }, Tuple { 0, 1 }]);
+        assert_eq!(table.version, super::ABI_TABLE_VERSION);
+        let entries = unsafe { table.entries() };
+        assert_eq!(entries.len(), 2);
+        assert_eq!(unsafe { entries[0].name() }, "Header");
+        assert_eq!(
+            entries[0].hash,
+            layout_hash(&type_layout!(Header { magic, len }))
+        );
+        assert_eq!(unsafe { entries[1].name() }, "Tuple");
+        assert_eq!(entries[1].hash, layout_hash(&type_layout!(Tuple { 0, 1 })));
+    }
+
+    #[test]
+    fn hash_covers_layout() {
+        let base = LayoutHasher::new().write_usize(8).write_usize(4);
+        let hash = base.write_field("a", 0, 4).finish();
+        assert_ne!(hash, base.finish());
+        assert_ne!(hash, base.write_field("b", 0, 4).finish());
+        assert_ne!(hash, base.write_field("a", 4, 4).finish());
+        assert_ne!(hash, base.write_field("a", 0, 2).finish());
+        assert_ne!(
+            LayoutHasher::new().write_str("ab").write_str("c").finish(),
+            LayoutHasher::new().write_str("a").write_str("bc").finish()
+        );
+    }
+
+    #[test]
+    fn hash_ignores_field_types() {
+        #[repr(C)]
+        struct Float {
+            magic: f32,
+            len: i16,
+        }
+
+        assert_eq!(
+            layout_hash(&type_layout!(Header { magic, len })),
+            layout_hash(&type_layout!(Float { magic, len }))
+        );
+    }
+
+    #[test]
+    fn empty_table() {
+        let table: &AbiTable = abi_table!([]);
+        assert_eq!(unsafe { table.entries() }.len(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    mod plugin {
+        use super::super::{check_abi, check_plugin_abi, AbiMismatch, AbiTable};
+        use std::string::ToString;
+
+        mod host {
+            #[repr(C)]
+            pub struct Request {
+                pub id: u32,
+                pub len: u16,
+            }
+
+            #[repr(C)]
+            pub struct Reply {
+                pub id: u32,
+                pub status: u8,
+            }
+        }
+
+        mod plugin_v1 {
+            #[repr(C)]
+            pub struct Request {
+                pub id: u32,
+                pub len: u16,
+            }
+
+            #[repr(C)]
+            pub struct Reply {
+                pub id: u32,
+                pub status: u8,
+            }
+
+            #[repr(C)]
+            pub struct Extra {
+                pub flags: u8,
+            }
+        }
+
+        mod plugin_v2 {
+            #[repr(C)]
+            pub struct Request {
+                pub id: u32,
+                pub len: u16,
+            }
+
+            #[repr(C)]
+            pub struct Reply {
+                pub id: u64,
+                pub status: u8,
+            }
+        }
+
+        fn host_table() -> &'static AbiTable {
+            use self::host::{Reply, Request};
+            abi_table!([Request { id, len }, Reply { id, status }])
+        }
+
+        extern "C" fn compatible() -> *const AbiTable {
+            use self::plugin_v1::{Extra, Reply, Request};
+            abi_table!([Extra { flags }, Reply { id, status }, Request { id, len }])
+        }
+
+        extern "C" fn incompatible() -> *const AbiTable {
+            use self::plugin_v2::{Reply, Request};
+            abi_table!([Request { id, len }, Reply { id, status }])
+        }
+
+        extern "C" fn partial() -> *const AbiTable {
+            use self::plugin_v2::Request;
+            abi_table!([Request { id, len }])
+        }
+
+        extern "C" fn missing() -> *const AbiTable {
+            core::ptr::null()
+        }
+
+        #[test]
+        fn compatible_plugin() {
+            assert_eq!(
+                unsafe { check_plugin_abi(host_table(), compatible) },
+                Ok(())
+            );
+        }
+
+        #[test]
+        fn incompatible_plugin() {
+            let err = unsafe { check_plugin_abi(host_table(), incompatible) }.unwrap_err();
+            match err {
+                AbiMismatch::Layout {
+                    ref type_name,
+                    host,
+                    plugin,
+                } => {
+                    assert_eq!(type_name, "Reply");
+                    assert_ne!(host, plugin);
+                }
+                _ => panic!("unexpected mismatch: {:?}", err),
+            }
+            assert!(err
+                .to_string()
+                .starts_with("the layout of `Reply` differs between the host and the plugin"));
+
+            assert_eq!(
+                unsafe { check_plugin_abi(host_table(), partial) },
+                Err(AbiMismatch::Missing {
+                    type_name: "Reply".to_string()
+                })
+            );
+            assert_eq!(
+                unsafe { check_plugin_abi(host_table(), missing) },
+                Err(AbiMismatch::NoTable)
+            );
+        }
+
+        #[test]
+        fn version_mismatch() {
+            let old = AbiTable {
+                version: 0,
+                ..*host_table()
+            };
+            assert_eq!(
+                unsafe { check_abi(host_table(), &old) },
+                Err(AbiMismatch::Version { host: 1, plugin: 0 })
+            );
+        }
+    }
+}
@@ -0,0 +1,788 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// Calculates the offset of the specified field of the `n`-th element of an array of structs,
+/// from the start of the array.
+///
+/// This is `n * size_of::<Parent>() + offset_of!(Parent, field)`, with overflow checking:
+/// the result is `None` if it does not fit in a `usize`.
+/// It is a constant expression if `n` is, on rustc 1.77 and newer.
+/// The field may be a nested path, such as `hdr.len`, which requires rustc 1.82 or newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::nth_field_offset;
+///
+/// #[repr(C)]
+/// struct Record {
+///     key: u32,
+///     value: u64,
+/// }
+///
+/// assert_eq!(nth_field_offset!(Record, value, 3), Some(3 * 16 + 8));
+/// assert_eq!(nth_field_offset!(Record, key, !0), None);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! nth_field_offset {
+    ($parent:path, $($field:tt).+, $n:expr $(,)*) => {
+        $crate::__priv::nth_offset(
+            $n,
+            $crate::__priv::mem::size_of::<$parent>(),
+            offset_of!($parent, $($field).+),
+        )
+    };
+}
+
+/// Produces the range of bytes that the specified field of the `n`-th element of an array of
+/// structs spans, counted from the start of the array.
+///
+/// The result is `None` if the end of the range does not fit in a `usize`.
+/// As with `nth_field_offset!`, the field may be a nested path on rustc 1.82 and newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::nth_field_span;
+///
+/// #[repr(C)]
+/// struct Record {
+///     key: u32,
+///     value: u64,
+/// }
+///
+/// assert_eq!(nth_field_span!(Record, value, 3), Some(56..64));
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! nth_field_span {
+    ($parent:path, $($field:tt).+, $n:expr $(,)*) => {{
+        let span = span_of!($parent, $($field).+);
+        match nth_field_offset!($parent, $($field).+, $n) {
+            $crate::__priv::Option::Some(start) => match start.checked_add(span.end - span.start) {
+                $crate::__priv::Option::Some(end) => $crate::__priv::Option::Some(start..end),
+                $crate::__priv::Option::None => $crate::__priv::Option::None,
+            },
+            $crate::__priv::Option::None => $crate::__priv::Option::None,
+        }
+    }};
+}
+
+/// Calculates the offset of the element at a runtime index of an array field of a struct.
+///
+/// This is `offset_of!(Parent, array) + index * size_of::<Element>()`, where the element type
+/// is inferred from the field, and it is the same as `offset_of!(Parent, array[index])`.
+///
+/// ## Examples
+/// ```
+/// use memoffset::element_offset;
+///
+/// #[repr(C)]
+/// struct Ring {
+///     head: u32,
+///     slots: [u64; 8],
+/// }
+///
+/// let i = 3;
+/// assert_eq!(element_offset!(Ring, slots, i), 8 + 3 * 8);
+/// ```
+///
+/// ## Panics
+/// Panics if the index is out of bounds of the array.
+#[macro_export(local_inner_macros)]
+macro_rules! element_offset {
+    ($parent:path, $($field:tt).+, $index:expr $(,)*) => {
+        _memoffset__offset_of_element_impl!($parent, $($field).+, $index)
+    };
+}
+
+/// Computes a const raw pointer to the element at a runtime index of an array field, given a
+/// pointer to the parent struct.
+///
+/// Like `raw_field!`, this never creates a reference to the struct, the array or the element.
+/// The base pointer *must not* be dangling, but it *may* point to uninitialized memory.
+///
+/// ## Examples
+/// ```
+/// use memoffset::element_ptr;
+///
+/// #[repr(C)]
+/// struct Ring {
+///     head: u32,
+///     slots: [u64; 8],
+/// }
+///
+/// let ring = Ring { head: 0, slots: [10, 11, 12, 13, 14, 15, 16, 17] };
+/// let slot = element_ptr!(&ring as *const Ring, Ring, slots, 5);
+/// assert_eq!(unsafe { *slot }, 15);
+/// ```
+///
+/// ## Panics
+/// Panics if the index is out of bounds of the array.
+#[macro_export(local_inner_macros)]
+macro_rules! element_ptr {
+    ($base:expr, $parent:path, $($field:tt).+, $index:expr $(,)*) => {{
+        let array_ptr = raw_field!($base, $parent, $($field).+);
+        let index = $index;
+        let (element, _) = $crate::__priv::element_span(
+            array_ptr as *const [_],
+            index,
+            $crate::__priv::Option::Some($crate::__priv::end_of_element(index)),
+            $crate::__priv::size_of_pointee(array_ptr),
+        );
+        $crate::__priv::element_ptr(array_ptr, element)
+    }};
+}
+
+/// Binary searches a byte buffer holding an array of structs, sorted by the specified field,
+/// for a key.
+///
+/// The structs are expected back to back, `size_of::<Parent>()` bytes apart; trailing bytes
+/// that do not make up a whole struct are ignored.
+/// The buffer does not need to be aligned, as the keys are read unaligned.
+///
+/// Like `slice::binary_search`, the result is `Ok` with the index of a struct with a matching
+/// key, or `Err` with the index where a struct with that key could be inserted to keep the
+/// array sorted.
+/// If several structs match, any one of them may be returned.
+///
+/// ## Examples
+/// ```
+/// use memoffset::search_by_field;
+///
+/// #[repr(C, packed)]
+/// struct Record {
+///     id: u16,
+///     value: u8,
+/// }
+///
+/// let table = [1, 0, 0xAA, 5, 0, 0xBB, 9, 0, 0xCC];
+/// let key: u16 = if cfg!(target_endian = "little") { 5 } else { 5 << 8 };
+/// assert_eq!(unsafe { search_by_field!(&table, Record, id, &key) }, Ok(1));
+/// ```
+///
+/// ## Safety
+/// The bytes of the specified field must be a valid value of its type for every struct in the
+/// buffer.
+#[macro_export(local_inner_macros)]
+macro_rules! search_by_field {
+    ($data:expr, $parent:path, $field:tt, $key:expr $(,)*) => {{
+        let data: &[u8] = $data;
+        let key = $key;
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        let field_ptr = raw_field!(base_ptr, $parent, $field);
+        let offset = _memoffset_offset_from_unsafe!(field_ptr, base_ptr);
+        let stride = $crate::__priv::mem::size_of::<$parent>();
+        let count = data.len().checked_div(stride).unwrap_or(0);
+        $crate::__priv::search_records(data.as_ptr(), count, stride, offset, key, field_ptr)
+    }};
+}
+
+/// Binary searches a slice of structs, sorted by the specified field, for a key.
+///
+/// This is the same as `search_by_field!`, but for a slice of structs instead of raw bytes.
+/// The keys are read unaligned, so this works for fields of packed structs as well.
+///
+/// ## Examples
+/// ```
+/// use memoffset::search_slice_by_field;
+///
+/// #[repr(C)]
+/// struct Record {
+///     id: u32,
+///     value: u64,
+/// }
+///
+/// let records = [
+///     Record { id: 2, value: 20 },
+///     Record { id: 3, value: 30 },
+///     Record { id: 7, value: 70 },
+/// ];
+/// assert_eq!(search_slice_by_field!(&records, Record, id, &7), Ok(2));
+/// assert_eq!(search_slice_by_field!(&records, Record, id, &5), Err(2));
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! search_slice_by_field {
+    ($records:expr, $parent:path, $field:tt, $key:expr $(,)*) => {{
+        let records: &[$parent] = $records;
+        let key = $key;
+        // The offset is taken from a base pointer of its own: the pointer of an empty slice
+        // dangles, so it is only used to read the records.
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        let field_ptr = raw_field!(base_ptr, $parent, $field);
+        let offset = _memoffset_offset_from_unsafe!(field_ptr, base_ptr);
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            $crate::__priv::search_records(
+                records.as_ptr() as *const u8,
+                records.len(),
+                $crate::__priv::mem::size_of::<$parent>(),
+                offset,
+                key,
+                field_ptr,
+            )
+        }
+    }};
+}
+
+/// Sorts a slice of structs by the specified field.
+///
+/// The sort is stable, as `slice::sort_by_key` is: structs with equal keys keep their order.
+/// The keys are read unaligned, so this works for fields of packed structs as well, and the
+/// field must be `Copy` and `Ord`.
+/// The field may be a nested path, such as `hdr.seq`, which requires rustc 1.82 or newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::{is_sorted_by_field, sort_by_field};
+///
+/// #[repr(C, packed)]
+/// struct Record {
+///     flags: u8,
+///     id: u32,
+/// }
+///
+/// let mut records = [
+///     Record { flags: 0, id: 7 },
+///     Record { flags: 1, id: 2 },
+///     Record { flags: 2, id: 3 },
+/// ];
+/// assert!(!is_sorted_by_field!(&records, Record, id));
+/// sort_by_field!(&mut records, Record, id);
+/// assert!(is_sorted_by_field!(&records, Record, id));
+/// assert_eq!(records.iter().map(|r| r.flags).collect::<Vec<_>>(), [1, 2, 0]);
+/// ```
+///
+/// Requires the `std` cargo feature.
+#[cfg(feature = "std")]
+#[macro_export(local_inner_macros)]
+macro_rules! sort_by_field {
+    ($records:expr, $parent:path, $($field:tt).+ $(,)*) => {{
+        let records: &mut [$parent] = $records;
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        let field_ptr = raw_field!(base_ptr, $parent, $($field).+);
+        let offset = _memoffset_offset_from_unsafe!(field_ptr, base_ptr);
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            $crate::__priv::sort_records(records, offset, field_ptr)
+        }
+    }};
+}
+
+/// Checks whether a slice of structs is sorted by the specified field, as `sort_by_field!`
+/// sorts it.
+///
+/// The keys are read unaligned, so this works for fields of packed structs as well, and the
+/// field must be `Copy` and `Ord`.
+/// The field may be a nested path, such as `hdr.seq`, which requires rustc 1.82 or newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::is_sorted_by_field;
+///
+/// #[repr(C)]
+/// struct Record {
+///     id: u32,
+///     value: u64,
+/// }
+///
+/// let records = [Record { id: 1, value: 9 }, Record { id: 4, value: 8 }];
+/// assert!(is_sorted_by_field!(&records, Record, id));
+/// assert!(!is_sorted_by_field!(&records, Record, value));
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! is_sorted_by_field {
+    ($records:expr, $parent:path, $($field:tt).+ $(,)*) => {{
+        let records: &[$parent] = $records;
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        let field_ptr = raw_field!(base_ptr, $parent, $($field).+);
+        let offset = _memoffset_offset_from_unsafe!(field_ptr, base_ptr);
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            $crate::__priv::records_sorted(records, offset, field_ptr)
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use core::mem;
+
+    #[repr(C)]
+    struct Record {
+        key: u32,
+        flags: u8,
+        value: u64,
+    }
+
+    #[test]
+    fn matches_pointer_arithmetic() {
+        let records = [
+            Record {
+                key: 0,
+                flags: 0,
+                value: 0,
+            },
+            Record {
+                key: 1,
+                flags: 1,
+                value: 1,
+            },
+            Record {
+                key: 2,
+                flags: 2,
+                value: 2,
+            },
+        ];
+        let base = records.as_ptr() as usize;
+
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(
+                nth_field_offset!(Record, key, i),
+                Some(&record.key as *const _ as usize - base)
+            );
+            assert_eq!(
+                nth_field_offset!(Record, value, i),
+                Some(&record.value as *const _ as usize - base)
+            );
+            let start = &record.flags as *const _ as usize - base;
+            assert_eq!(nth_field_span!(Record, flags, i), Some(start..start + 1));
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        // The last index at which a record still starts within the address space.
+        let n = !0usize / mem::size_of::<Record>();
+        let start = n * mem::size_of::<Record>();
+        assert_eq!(nth_field_offset!(Record, key, n), Some(start));
+        assert_eq!(nth_field_offset!(Record, value, n), Some(start + 8));
+        // ... but the last field does not end within it.
+        assert_eq!(nth_field_span!(Record, value, n), None);
+        assert_eq!(nth_field_offset!(Record, key, n + 1), None);
+        assert_eq!(nth_field_span!(Record, key, n + 1), None);
+
+        // What a 32-bit target would see for a fabricated index.
+        let n = !0u32 as usize;
+        assert_eq!(
+            nth_field_offset!(Record, key, n),
+            n.checked_mul(mem::size_of::<Record>())
+        );
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn nested_fields() {
+        #[repr(C)]
+        struct Entry {
+            id: u16,
+            record: Record,
+            pair: (u8, u32),
+        }
+
+        let entries = [
+            Entry {
+                id: 0,
+                record: Record {
+                    key: 0,
+                    flags: 0,
+                    value: 0,
+                },
+                pair: (0, 0),
+            },
+            Entry {
+                id: 1,
+                record: Record {
+                    key: 1,
+                    flags: 1,
+                    value: 1,
+                },
+                pair: (1, 1),
+            },
+        ];
+        let base = entries.as_ptr() as usize;
+
+        for (i, entry) in entries.iter().enumerate() {
+            assert_eq!(
+                nth_field_offset!(Entry, record.value, i),
+                Some(&entry.record.value as *const _ as usize - base)
+            );
+            let start = &entry.record.flags as *const _ as usize - base;
+            assert_eq!(
+                nth_field_span!(Entry, record.flags, i),
+                Some(start..start + 1)
+            );
+            let start = &entry.pair.1 as *const _ as usize - base;
+            assert_eq!(nth_field_span!(Entry, pair.1, i,), Some(start..start + 4));
+        }
+
+        assert_eq!(nth_field_offset!(Entry, record.key, !0), None);
+        assert_eq!(nth_field_span!(Entry, pair.1, !0), None);
+
+        const OFFSET: Option<usize> = nth_field_offset!(Entry, record.value, 2);
+        assert_eq!(OFFSET, Some(2 * mem::size_of::<Entry>() + 16));
+    }
+
+    #[cfg(stable_offset_of)]
+    #[test]
+    fn const_nth_offset() {
+        const OFFSET: Option<usize> = nth_field_offset!(Record, value, 2);
+        assert_eq!(OFFSET, Some(2 * 16 + 8));
+    }
+
+    #[repr(C)]
+    struct Ring {
+        head: u8,
+        slots: [Record; 4],
+        bytes: [[u8; 3]; 2],
+    }
+
+    fn ring() -> Ring {
+        Ring {
+            head: 0,
+            slots: [
+                Record {
+                    key: 0,
+                    flags: 0,
+                    value: 10,
+                },
+                Record {
+                    key: 1,
+                    flags: 1,
+                    value: 11,
+                },
+                Record {
+                    key: 2,
+                    flags: 2,
+                    value: 12,
+                },
+                Record {
+                    key: 3,
+                    flags: 3,
+                    value: 13,
+                },
+            ],
+            bytes: [[1, 2, 3], [4, 5, 6]],
+        }
+    }
+
+    #[test]
+    fn runtime_elements() {
+        let ring = ring();
+        let base = &ring as *const Ring;
+
+        for i in 0..4 {
+            let slot = element_ptr!(base, Ring, slots, i);
+            assert_eq!(slot, &ring.slots[i] as *const Record);
+            assert_eq!(unsafe { (*slot).value }, 10 + i as u64);
+            assert_eq!(
+                element_offset!(Ring, slots, i),
+                slot as usize - base as usize
+            );
+            assert_eq!(element_offset!(Ring, slots, i), offset_of!(Ring, slots[i]));
+        }
+        assert_eq!(element_offset!(Ring, bytes, 1), offset_of!(Ring, bytes) + 3);
+        assert_eq!(unsafe { *element_ptr!(base, Ring, bytes, 1) }, [4u8, 5, 6]);
+        assert_eq!(ring.head, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn runtime_element_out_of_bounds() {
+        let i = 4;
+        let _ = element_offset!(Ring, slots, i);
+    }
+
+    #[test]
+    #[should_panic]
+    fn runtime_element_ptr_out_of_bounds() {
+        let ring = ring();
+        element_ptr!(&ring as *const Ring, Ring, bytes, 2);
+    }
+
+    #[test]
+    fn search_slice() {
+        fn record(key: u32, value: u64) -> Record {
+            Record {
+                key,
+                flags: 0,
+                value,
+            }
+        }
+
+        let records = [
+            record(1, 10),
+            record(3, 30),
+            record(3, 31),
+            record(3, 32),
+            record(8, 80),
+        ];
+
+        assert_eq!(search_slice_by_field!(&records, Record, key, &1), Ok(0));
+        assert_eq!(search_slice_by_field!(&records, Record, key, &8), Ok(4));
+        let dup = search_slice_by_field!(&records, Record, key, &3).unwrap();
+        assert!(dup == 1 || dup == 2 || dup == 3);
+        assert_eq!(search_slice_by_field!(&records, Record, key, &0), Err(0));
+        assert_eq!(search_slice_by_field!(&records, Record, key, &4), Err(4));
+        assert_eq!(search_slice_by_field!(&records, Record, key, &9), Err(5));
+        assert_eq!(search_slice_by_field!(&records, Record, value, &31), Ok(2));
+    }
+
+    #[test]
+    fn search_empty() {
+        let records: [Record; 0] = [];
+        assert_eq!(search_slice_by_field!(&records, Record, key, &1), Err(0));
+        assert_eq!(search_slice_by_field!(&[], Record, value, &1), Err(0));
+        assert_eq!(
+            search_slice_by_field!(&records[..], Record, flags, &0),
+            Err(0)
+        );
+        assert_eq!(unsafe { search_by_field!(&[], Record, key, &1) }, Err(0));
+    }
+
+    #[test]
+    fn search_duplicate_keys() {
+        fn record(key: u32, value: u64) -> Record {
+            Record {
+                key,
+                flags: 0,
+                value,
+            }
+        }
+
+        let mut records = [
+            record(1, 0),
+            record(2, 1),
+            record(2, 2),
+            record(2, 3),
+            record(2, 4),
+            record(2, 5),
+            record(2, 6),
+            record(5, 7),
+            record(5, 8),
+            record(9, 9),
+        ];
+        for &key in &[1, 2, 5, 9] {
+            let found = search_slice_by_field!(&records, Record, key, &key).unwrap();
+            assert_eq!(records[found].key, key);
+        }
+        assert_eq!(search_slice_by_field!(&records, Record, key, &3), Err(7));
+
+        for record in records.iter_mut() {
+            record.key = 4;
+        }
+        assert!(search_slice_by_field!(&records, Record, key, &4).unwrap() < records.len());
+        assert_eq!(search_slice_by_field!(&records, Record, key, &3), Err(0));
+        assert_eq!(search_slice_by_field!(&records, Record, key, &5), Err(10));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn search_misaligned_vec() {
+        use std::vec::Vec;
+
+        // Records copied into a heap buffer, three bytes in, as if read from a file or a
+        // mapping at an arbitrary offset.
+        let keys = [3u32, 6, 6, 12, 15];
+        let mut buf = Vec::with_capacity(3 + keys.len() * mem::size_of::<Record>());
+        buf.extend_from_slice(&[0xFF; 3]);
+        for (i, &key) in keys.iter().enumerate() {
+            let record = Record {
+                key,
+                flags: i as u8,
+                value: key as u64 * 10,
+            };
+            let bytes = unsafe {
+                core::slice::from_raw_parts(
+                    &record as *const Record as *const u8,
+                    mem::size_of::<Record>(),
+                )
+            };
+            buf.extend_from_slice(bytes);
+        }
+        let data = &buf[3..];
+        assert_ne!(data.as_ptr() as usize % mem::align_of::<Record>(), 0);
+
+        unsafe {
+            assert_eq!(search_by_field!(data, Record, key, &3), Ok(0));
+            assert_eq!(search_by_field!(data, Record, key, &12), Ok(3));
+            assert_eq!(search_by_field!(data, Record, value, &150), Ok(4));
+            let dup = search_by_field!(data, Record, key, &6).unwrap();
+            assert!(dup == 1 || dup == 2);
+            assert_eq!(search_by_field!(data, Record, key, &7), Err(3));
+            assert_eq!(search_by_field!(data, Record, key, &16), Err(5));
+        }
+    }
+
+    #[test]
+    fn search_misaligned_bytes() {
+        #[repr(C, packed)]
+        struct Entry {
+            tag: u8,
+            key: u32,
+        }
+
+        // Five entries with the keys 10, 20, 20, 30, 40, one byte into the buffer.
+        let mut buf = [0u8; 1 + 5 * 5 + 3];
+        for (i, &key) in [10u32, 20, 20, 30, 40].iter().enumerate() {
+            let entry = Entry { tag: i as u8, key };
+            unsafe {
+                core::ptr::write_unaligned(
+                    buf.as_mut_ptr().offset(1 + 5 * i as isize) as *mut Entry,
+                    entry,
+                );
+            }
+        }
+        // The trailing bytes do not make up an entry.
+        let data = &buf[1..];
+
+        unsafe {
+            assert_eq!(search_by_field!(data, Entry, key, &10), Ok(0));
+            assert_eq!(search_by_field!(data, Entry, key, &40), Ok(4));
+            assert_eq!(search_by_field!(data, Entry, key, &30), Ok(3));
+            let dup = search_by_field!(data, Entry, key, &20).unwrap();
+            assert!(dup == 1 || dup == 2);
+            assert_eq!(search_by_field!(data, Entry, key, &5), Err(0));
+            assert_eq!(search_by_field!(data, Entry, key, &25), Err(3));
+            assert_eq!(search_by_field!(data, Entry, key, &50), Err(5));
+            assert_eq!(search_by_field!(&data[..4], Entry, key, &10), Err(0));
+        }
+    }
+
+    #[test]
+    fn sorted_by_field() {
+        #[repr(C, packed)]
+        struct Packed {
+            tag: u8,
+            key: u16,
+        }
+
+        let records = [
+            Packed { tag: 0, key: 1 },
+            Packed { tag: 1, key: 1 },
+            Packed { tag: 2, key: 5 },
+        ];
+        assert!(is_sorted_by_field!(&records, Packed, key));
+        assert!(is_sorted_by_field!(&records, Packed, tag,));
+        assert!(is_sorted_by_field!(&records[..0], Packed, key));
+        let reversed = [Packed { tag: 1, key: 5 }, Packed { tag: 2, key: 1 }];
+        assert!(!is_sorted_by_field!(&reversed, Packed, key));
+        assert!(is_sorted_by_field!(&reversed, Packed, tag));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn sort_packed() {
+        use std::vec::Vec;
+
+        #[derive(Clone, Copy)]
+        #[repr(C, packed)]
+        struct Packed {
+            tag: u8,
+            key: u64,
+        }
+
+        let keys = [9u64, 3, 7, 3, 0, !0, 7, 1];
+        let mut records: Vec<Packed> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, &key)| Packed { tag: i as u8, key })
+            .collect();
+        let mut expected = records.clone();
+        expected.sort_by_key(|record| record.key);
+
+        sort_by_field!(&mut records, Packed, key);
+        assert!(is_sorted_by_field!(&records, Packed, key));
+        // The sort is stable, so even the order of the equal keys matches.
+        let tags = |records: &[Packed]| records.iter().map(|r| r.tag).collect::<Vec<_>>();
+        assert_eq!(tags(&records), tags(&expected));
+        assert_eq!(tags(&records), [4, 7, 1, 3, 2, 6, 0, 5]);
+
+        let mut empty: [Packed; 0] = [];
+        sort_by_field!(&mut empty, Packed, key);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn sort_aligned() {
+        let mut records = [
+            Record {
+                key: 3,
+                flags: 0,
+                value: 30,
+            },
+            Record {
+                key: 1,
+                flags: 1,
+                value: 40,
+            },
+            Record {
+                key: 2,
+                flags: 2,
+                value: 10,
+            },
+        ];
+
+        sort_by_field!(&mut records[..], Record, value);
+        assert_eq!(
+            records
+                .iter()
+                .map(|r| r.flags)
+                .collect::<std::vec::Vec<_>>(),
+            [2, 0, 1]
+        );
+        assert!(!is_sorted_by_field!(&records, Record, key));
+
+        sort_by_field!(&mut records, Record, key,);
+        assert!(is_sorted_by_field!(&records, Record, key));
+        assert_eq!(search_slice_by_field!(&records, Record, key, &2), Ok(1));
+    }
+
+    #[cfg(all(feature = "std", nested_offset_of))]
+    #[test]
+    fn sort_nested() {
+        use std::vec::Vec;
+
+        #[repr(C)]
+        struct Header {
+            kind: u8,
+            seq: u32,
+        }
+
+        #[repr(C, packed)]
+        struct Message(u8, Header, (u16, i8));
+
+        let message = |id, seq, prio| Message(id, Header { kind: 0, seq }, (0, prio));
+        let mut messages = [message(0, 30, 1), message(1, 10, -1), message(2, 20, 0)];
+        let ids = |messages: &[Message]| messages.iter().map(|m| m.0).collect::<Vec<_>>();
+
+        assert!(!is_sorted_by_field!(&messages, Message, 1.seq));
+        sort_by_field!(&mut messages, Message, 1.seq);
+        assert!(is_sorted_by_field!(&messages, Message, 1.seq));
+        assert_eq!(ids(&messages), [1, 2, 0]);
+
+        sort_by_field!(&mut messages, Message, 2.1);
+        assert!(is_sorted_by_field!(&messages, Message, 2.1));
+        assert!(is_sorted_by_field!(&messages, Message, 1.kind));
+        assert_eq!(ids(&messages), [1, 2, 0]);
+
+        messages[0].2 .1 = 5;
+        assert!(!is_sorted_by_field!(&messages, Message, 2.1));
+        sort_by_field!(&mut messages, Message, 2.1);
+        assert_eq!(ids(&messages), [2, 0, 1]);
+    }
+}
@@ -0,0 +1,773 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// Asserts at compile time that one struct is a layout prefix of another.
+///
+/// Every listed field must be present in both structs, at the same offset and with the same
+/// size, and the prefix struct must not be larger than the full struct.
+/// This is what makes it sound to access a `Full` value through a pointer to `Prefix`,
+/// as is commonly done with versioned structs that only ever append fields.
+///
+/// ## Examples
+/// ```
+/// use memoffset::assert_prefix_layout;
+///
+/// #[repr(C)]
+/// struct V1 {
+///     a: u32,
+///     b: u16,
+/// }
+///
+/// #[repr(C)]
+/// struct V2 {
+///     a: u32,
+///     b: u16,
+///     c: u64,
+/// }
+///
+/// assert_prefix_layout!(V1, V2, { a, b });
+/// ```
+///
+/// A field that moved fails to compile:
+///
+/// ```compile_fail
+/// use memoffset::assert_prefix_layout;
+///
+/// #[repr(C)]
+/// struct V1 {
+///     a: u32,
+///     b: u16,
+/// }
+///
+/// #[repr(C)]
+/// struct V2 {
+///     b: u16,
+///     a: u32,
+///     c: u64,
+/// }
+///
+/// assert_prefix_layout!(V1, V2, { a, b });
+/// ```
+///
+/// So does a field that changed size:
+///
+/// ```compile_fail
+/// use memoffset::assert_prefix_layout;
+///
+/// #[repr(C)]
+/// struct V1 {
+///     a: u32,
+///     b: u16,
+/// }
+///
+/// #[repr(C)]
+/// struct V2 {
+///     a: u32,
+///     b: u32,
+///     c: u64,
+/// }
+///
+/// assert_prefix_layout!(V1, V2, { a, b });
+/// ```
+///
+/// Requires rustc 1.65 or newer, as it relies on constant evaluation of offsets.
+#[cfg(stable_const)]
+#[macro_export]
+macro_rules! assert_prefix_layout {
+    ($prefix:path, $full:path, { $($field:tt),+ $(,)* } $(,)*) => {
+        const _: () = {
+            assert!(
+                $crate::__priv::mem::size_of::<$prefix>() <= $crate::__priv::mem::size_of::<$full>(),
+                concat!(
+                    "`", stringify!($prefix), "` is larger than `",
+                    stringify!($full), "`"
+                )
+            );
+            $(
+                assert!(
+                    $crate::offset_of!($prefix, $field) == $crate::offset_of!($full, $field),
+                    concat!(
+                        "field `", stringify!($field), "` is not at the same offset in `",
+                        stringify!($prefix), "` and `", stringify!($full), "`"
+                    )
+                );
+                assert!(
+                    $crate::_memoffset__field_size!($prefix, $field) == $crate::_memoffset__field_size!($full, $field),
+                    concat!(
+                        "field `", stringify!($field), "` does not have the same size in `",
+                        stringify!($prefix), "` and `", stringify!($full), "`"
+                    )
+                );
+            )+
+        };
+    };
+}
+
+/// Returns whether the byte ranges `a` and `b` of a struct aligned to `align` bytes can never
+/// share a cache line of `line` bytes.
+#[cfg(stable_const)]
+#[doc(hidden)]
+pub const fn cachelines_disjoint(
+    a: (usize, usize),
+    b: (usize, usize),
+    align: usize,
+    line: usize,
+) -> bool {
+    // Order the ranges by position.
+    let (first, second) = if a.0 <= b.0 { (a, b) } else { (b, a) };
+    // A zero-sized field still sits on the line of its offset.
+    let first_last = if first.1 > first.0 {
+        first.1 - 1
+    } else {
+        first.0
+    };
+    if align >= line {
+        // The struct starts on a line boundary, so line indices are meaningful.
+        first_last / line < second.0 / line
+    } else {
+        // Two bytes are on different lines wherever the struct is placed only if they
+        // are at least a full line apart.
+        second.0 >= first_last + line
+    }
+}
+
+/// Returns whether the byte ranges `a` and `b` of a struct aligned to `align` bytes always
+/// share a single cache line of `line` bytes.
+#[cfg(stable_const)]
+#[doc(hidden)]
+pub const fn cachelines_shared(
+    a: (usize, usize),
+    b: (usize, usize),
+    align: usize,
+    line: usize,
+) -> bool {
+    let start = if a.0 <= b.0 { a.0 } else { b.0 };
+    let end = if a.1 >= b.1 { a.1 } else { b.1 };
+    let last = if end > start { end - 1 } else { start };
+    align >= line && start / line == last / line
+}
+
+/// Asserts at compile time that two fields of a struct never share a cache line.
+///
+/// This is the usual way to make sure that fields written by different threads do not suffer
+/// from false sharing.
+/// A field that straddles a line boundary occupies both lines.
+///
+/// The line size defaults to 64 bytes, and can be overridden by a fourth argument, which must be
+/// a power of two.
+///
+/// The check takes the alignment of the struct into account: if the struct is aligned to the
+/// line size, as is typical for such structs, the fields must be on different lines.
+/// Otherwise, the last byte of the first field and the first byte of the second must be at
+/// least a full line apart, so that no placement of the struct puts them on the same line.
+///
+/// ## Examples
+/// ```
+/// use memoffset::assert_different_cachelines;
+///
+/// #[repr(C, align(64))]
+/// struct Queue {
+///     producer_head: usize,
+///     _pad: [u8; 56],
+///     consumer_tail: usize,
+/// }
+///
+/// assert_different_cachelines!(Queue, producer_head, consumer_tail);
+/// ```
+///
+/// Hot fields on the same line fail to compile:
+///
+/// ```compile_fail
+/// use memoffset::assert_different_cachelines;
+///
+/// #[repr(C, align(64))]
+/// struct Queue {
+///     producer_head: usize,
+///     consumer_tail: usize,
+/// }
+///
+/// assert_different_cachelines!(Queue, producer_head, consumer_tail);
+/// ```
+///
+/// Requires rustc 1.65 or newer, as it relies on constant evaluation of offsets.
+#[cfg(stable_const)]
+#[macro_export]
+macro_rules! assert_different_cachelines {
+    ($parent:path, $a:tt, $b:tt $(,)*) => {
+        $crate::assert_different_cachelines!($parent, $a, $b, 64);
+    };
+    ($parent:path, $a:tt, $b:tt, $line:expr $(,)*) => {
+        const _: () = assert!(
+            $crate::__priv::cachelines_disjoint(
+                $crate::_memoffset__field_range!($parent, $a),
+                $crate::_memoffset__field_range!($parent, $b),
+                $crate::__priv::mem::align_of::<$parent>(),
+                $line,
+            ),
+            concat!(
+                "fields `",
+                stringify!($a),
+                "` and `",
+                stringify!($b),
+                "` of `",
+                stringify!($parent),
+                "` may share a cache line"
+            )
+        );
+    };
+}
+
+/// Asserts at compile time that two fields of a struct always share a single cache line.
+///
+/// This is useful for fields that are accessed together and should be loaded together.
+/// Both fields must fit entirely within the same line; a field that straddles a line
+/// boundary never satisfies the check.
+///
+/// The line size defaults to 64 bytes, and can be overridden by a fourth argument, which must be
+/// a power of two.
+/// As the placement of the fields within a line depends on where the struct is placed,
+/// the struct must be aligned to at least the line size.
+///
+/// ## Examples
+/// ```
+/// use memoffset::assert_same_cacheline;
+///
+/// #[repr(C, align(64))]
+/// struct Lock {
+///     state: u32,
+///     owner: u32,
+///     data: [u8; 120],
+/// }
+///
+/// assert_same_cacheline!(Lock, state, owner);
+/// ```
+///
+/// Fields on different lines fail to compile:
+///
+/// ```compile_fail
+/// use memoffset::assert_same_cacheline;
+///
+/// #[repr(C, align(64))]
+/// struct Lock {
+///     state: u32,
+///     data: [u8; 120],
+///     owner: u32,
+/// }
+///
+/// assert_same_cacheline!(Lock, state, owner);
+/// ```
+///
+/// Requires rustc 1.65 or newer, as it relies on constant evaluation of offsets.
+#[cfg(stable_const)]
+#[macro_export]
+macro_rules! assert_same_cacheline {
+    ($parent:path, $a:tt, $b:tt $(,)*) => {
+        $crate::assert_same_cacheline!($parent, $a, $b, 64);
+    };
+    ($parent:path, $a:tt, $b:tt, $line:expr $(,)*) => {
+        const _: () = assert!(
+            $crate::__priv::cachelines_shared(
+                $crate::_memoffset__field_range!($parent, $a),
+                $crate::_memoffset__field_range!($parent, $b),
+                $crate::__priv::mem::align_of::<$parent>(),
+                $line,
+            ),
+            concat!(
+                "fields `",
+                stringify!($a),
+                "` and `",
+                stringify!($b),
+                "` of `",
+                stringify!($parent),
+                "` are not guaranteed to share a cache line"
+            )
+        );
+    };
+}
+
+/// Returns whether `offset` is a multiple of the power of two `align`.
+#[cfg(stable_const)]
+#[doc(hidden)]
+pub const fn is_aligned_to(offset: usize, align: usize) -> bool {
+    offset & (align - 1) == 0
+}
+
+/// Asserts at compile time that a field is aligned to the given number of bytes.
+///
+/// The offset of the field within the struct must be a multiple of `align`.
+/// As the offset only tells part of the story, the struct itself must also be aligned to at
+/// least `align`, so that the field is aligned wherever the struct is placed.
+/// `align` must be a power of two.
+///
+/// The field may be a nested path, such as `ring.entries`, which requires rustc 1.82 or newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::assert_field_aligned_to;
+///
+/// #[repr(C, align(64))]
+/// struct Ring {
+///     head: u32,
+///     tail: u32,
+///     _pad: [u8; 56],
+///     entries: [u64; 16],
+/// }
+///
+/// assert_field_aligned_to!(Ring, entries, 64);
+/// assert_field_aligned_to!(Ring, tail, 4);
+/// ```
+///
+/// A misplaced field fails to compile:
+///
+/// ```compile_fail
+/// use memoffset::assert_field_aligned_to;
+///
+/// #[repr(C, align(64))]
+/// struct Ring {
+///     head: u32,
+///     tail: u32,
+///     entries: [u64; 16],
+/// }
+///
+/// assert_field_aligned_to!(Ring, entries, 64);
+/// ```
+///
+/// As does a struct that is not sufficiently aligned itself:
+///
+/// ```compile_fail
+/// use memoffset::assert_field_aligned_to;
+///
+/// #[repr(C)]
+/// struct Ring {
+///     head: u32,
+///     tail: u32,
+///     _pad: [u8; 56],
+///     entries: [u64; 16],
+/// }
+///
+/// assert_field_aligned_to!(Ring, entries, 64);
+/// ```
+///
+/// Requires rustc 1.65 or newer, as it relies on constant evaluation of offsets.
+#[cfg(stable_const)]
+#[macro_export]
+macro_rules! assert_field_aligned_to {
+    ($parent:path, $($field:tt).+, $align:expr $(,)*) => {
+        const _: () = {
+            assert!(
+                $crate::__priv::mem::align_of::<$parent>() >= $align,
+                concat!(
+                    "`",
+                    stringify!($parent),
+                    "` is not aligned to ",
+                    stringify!($align),
+                    " bytes"
+                )
+            );
+            assert!(
+                $crate::__priv::is_aligned_to($crate::offset_of!($parent, $($field).+), $align),
+                concat!(
+                    "field `",
+                    stringify!($($field).+),
+                    "` of `",
+                    stringify!($parent),
+                    "` is not aligned to ",
+                    stringify!($align),
+                    " bytes"
+                )
+            );
+        };
+    };
+}
+
+/// Asserts at compile time that a field of a struct is at the given offset.
+///
+/// This pins a struct to a documented ABI, such as a C header, without needing a test to run:
+/// a struct that drifts from it fails to compile on every target it is built for.
+///
+/// ## Examples
+/// ```
+/// use memoffset::assert_offset;
+///
+/// #[repr(C)]
+/// struct Header {
+///     magic: u32,
+///     version: u16,
+///     flags: u16,
+///     len: u64,
+/// }
+///
+/// assert_offset!(Header, version, 4);
+/// assert_offset!(Header, len, 8);
+/// ```
+///
+/// A field at another offset fails to compile:
+///
+/// ```compile_fail
+/// use memoffset::assert_offset;
+///
+/// #[repr(C)]
+/// struct Header {
+///     magic: u32,
+///     len: u64,
+/// }
+///
+/// assert_offset!(Header, len, 4);
+/// ```
+///
+/// Requires rustc 1.65 or newer, as it relies on constant evaluation of offsets.
+#[cfg(stable_const)]
+#[macro_export]
+macro_rules! assert_offset {
+    ($parent:path, $($field:tt).+, $offset:expr $(,)*) => {
+        const _: () = assert!(
+            $crate::offset_of!($parent, $($field).+) == $offset,
+            concat!(
+                "field `",
+                stringify!($($field).+),
+                "` of `",
+                stringify!($parent),
+                "` is not at offset ",
+                stringify!($offset)
+            )
+        );
+    };
+}
+
+/// Asserts at compile time the offsets of several fields of a struct, as `assert_offset!` does
+/// for one.
+///
+/// Each field that is not at its expected offset is reported with its own error.
+///
+/// ## Examples
+/// ```
+/// use memoffset::assert_offsets;
+///
+/// #[repr(C)]
+/// struct Header {
+///     magic: u32,
+///     version: u16,
+///     flags: u16,
+///     len: u64,
+/// }
+///
+/// assert_offsets!(Header {
+///     magic: 0,
+///     version: 4,
+///     flags: 6,
+///     len: 8,
+/// });
+/// ```
+///
+/// Requires rustc 1.65 or newer, as it relies on constant evaluation of offsets.
+#[cfg(stable_const)]
+#[macro_export]
+macro_rules! assert_offsets {
+    ($parent:path { $($($field:tt).+ : $offset:expr),+ $(,)* } $(,)*) => {
+        $($crate::assert_offset!($parent, $($field).+, $offset);)+
+    };
+}
+
+/// Asserts at compile time the layout of a struct, with separate expectations per target.
+///
+/// Each entry starts with a `cfg` predicate, such as `target_pointer_width = "64"` or
+/// `all(target_arch = "arm", target_os = "none")`, and lists the expected `size` and `align` of
+/// the struct and the expected offsets of some of its fields. Every entry whose predicate holds
+/// for the current target is checked, and it is an error if none of them does.
+/// Custom cfgs, for example one set from the exact target triple by a build script, can be used
+/// in the predicates as well.
+///
+/// As `size` and `align` refer to the struct itself, fields with those names cannot be checked.
+///
+/// ## Examples
+/// ```
+/// use memoffset::target_layouts;
+///
+/// #[repr(C)]
+/// struct Request {
+///     id: u32,
+///     buf: *const u8,
+///     len: usize,
+/// }
+///
+/// target_layouts!(Request {
+///     target_pointer_width = "64" => { size: 24, align: 8, buf: 8, len: 16 },
+///     target_pointer_width = "32" => { size: 12, align: 4, buf: 4, len: 8 },
+///     target_pointer_width = "16" => { size: 8, buf: 4, len: 6 },
+/// });
+/// ```
+///
+/// A wrong expectation fails to compile:
+///
+/// ```compile_fail
+/// use memoffset::target_layouts;
+///
+/// #[repr(C)]
+/// struct Request {
+///     id: u32,
+///     buf: *const u8,
+/// }
+///
+/// target_layouts!(Request {
+///     target_pointer_width = "64" => { buf: 4 },
+///     target_pointer_width = "32" => { buf: 8 },
+///     target_pointer_width = "16" => { buf: 2 },
+/// });
+/// ```
+///
+/// Requires rustc 1.65 or newer, as it relies on constant evaluation of offsets.
+#[cfg(stable_const)]
+#[macro_export]
+macro_rules! target_layouts {
+    ($parent:path { $($cfg:meta => { $($key:tt : $value:expr),* $(,)* }),+ $(,)* } $(,)*) => {
+        $(
+            #[cfg($cfg)]
+            const _: () = {
+                $($crate::_memoffset__target_layout_entry!($parent, $cfg, $key, $value);)*
+            };
+        )+
+        #[cfg(not(any($($cfg),+)))]
+        compile_error!(concat!(
+            "no entry of `target_layouts!` for `",
+            stringify!($parent),
+            "` matches the current target"
+        ));
+    };
+}
+
+/// Checks one `key: value` expectation of a `target_layouts!` entry.
+#[cfg(stable_const)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__target_layout_entry {
+    ($parent:path, $cfg:meta, size, $value:expr) => {
+        assert!(
+            $crate::__priv::mem::size_of::<$parent>() == $value,
+            concat!(
+                "`",
+                stringify!($parent),
+                "` does not have a size of ",
+                stringify!($value),
+                " on `",
+                stringify!($cfg),
+                "`"
+            )
+        );
+    };
+    ($parent:path, $cfg:meta, align, $value:expr) => {
+        assert!(
+            $crate::__priv::mem::align_of::<$parent>() == $value,
+            concat!(
+                "`",
+                stringify!($parent),
+                "` does not have an alignment of ",
+                stringify!($value),
+                " on `",
+                stringify!($cfg),
+                "`"
+            )
+        );
+    };
+    ($parent:path, $cfg:meta, $field:tt, $value:expr) => {
+        assert!(
+            $crate::offset_of!($parent, $field) == $value,
+            concat!(
+                "field `",
+                stringify!($field),
+                "` of `",
+                stringify!($parent),
+                "` is not at offset ",
+                stringify!($value),
+                " on `",
+                stringify!($cfg),
+                "`"
+            )
+        );
+    };
+}
+
+#[cfg(all(test, stable_const))]
+mod tests {
+    #[repr(C)]
+    struct V1 {
+        a: u32,
+        b: u16,
+        c: [u8; 3],
+    }
+
+    #[repr(C)]
+    struct V2 {
+        a: u32,
+        b: u16,
+        c: [u8; 3],
+        d: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PackedV2 {
+        a: u32,
+        b: u16,
+        c: [u8; 3],
+        d: u64,
+    }
+
+    assert_prefix_layout!(V1, V2, { a, b, c });
+    assert_prefix_layout!(V1, PackedV2, { a, b, c, });
+    assert_prefix_layout!(V1, V1, { a });
+
+    #[test]
+    fn prefix_is_readable() {
+        let v2 = V2 {
+            a: 1,
+            b: 2,
+            c: [3; 3],
+            d: 4,
+        };
+        let v1 = unsafe { &*(&v2 as *const V2 as *const V1) };
+        assert_eq!(v1.a, 1);
+        assert_eq!(v1.b, 2);
+        assert_eq!(v1.c, [3; 3]);
+    }
+
+    #[repr(C, align(64))]
+    struct Shared {
+        head: usize,
+        head_cached: usize,
+        _pad: [u8; 48],
+        tail: usize,
+        tail_cached: usize,
+    }
+
+    #[repr(C)]
+    struct Unaligned {
+        a: u64,
+        _pad: [u8; 56],
+        b: u64,
+        _pad2: [u8; 55],
+        c: u8,
+    }
+
+    assert_different_cachelines!(Shared, head, tail);
+    assert_different_cachelines!(Shared, tail_cached, head_cached);
+    assert_same_cacheline!(Shared, head, head_cached);
+    assert_same_cacheline!(Shared, tail, tail_cached, 64);
+    assert_different_cachelines!(Shared, head, tail, 32);
+    assert_different_cachelines!(Unaligned, a, c);
+
+    #[test]
+    fn cacheline_edge_cases() {
+        use super::{cachelines_disjoint, cachelines_shared};
+
+        // A field straddling a line boundary occupies both lines.
+        assert!(!cachelines_disjoint((60, 68), (64, 72), 64, 64));
+        assert!(!cachelines_disjoint((64, 72), (60, 68), 64, 64));
+        assert!(!cachelines_shared((60, 68), (56, 60), 64, 64));
+        // Adjacent lines.
+        assert!(cachelines_disjoint((0, 64), (64, 72), 64, 64));
+        // Zero-sized fields.
+        assert!(cachelines_disjoint((0, 0), (64, 64), 64, 64));
+        assert!(cachelines_shared((8, 8), (0, 4), 64, 64));
+        // Without sufficient alignment, only a full line of distance helps.
+        assert!(!cachelines_disjoint((0, 8), (64, 72), 8, 64));
+        assert!(cachelines_disjoint((0, 8), (71, 72), 8, 64));
+        assert!(!cachelines_shared((0, 4), (4, 8), 8, 64));
+    }
+
+    #[repr(C, align(64))]
+    struct Descriptor {
+        flags: u32,
+        len: u32,
+        addr: u64,
+        _pad: [u8; 48],
+        next: [u8; 64],
+    }
+
+    #[repr(C, packed)]
+    struct Packed {
+        a: u8,
+        b: u8,
+    }
+
+    assert_field_aligned_to!(Descriptor, flags, 64);
+    assert_field_aligned_to!(Descriptor, addr, 8);
+    assert_field_aligned_to!(Descriptor, next, 64);
+    assert_field_aligned_to!(Packed, b, 1);
+
+    #[cfg(nested_offset_of)]
+    #[repr(C, align(64))]
+    struct Queue {
+        id: u64,
+        _pad: [u8; 56],
+        desc: Descriptor,
+        pair: (u8, Packed),
+    }
+
+    #[cfg(nested_offset_of)]
+    assert_field_aligned_to!(Queue, desc.next, 64);
+    #[cfg(nested_offset_of)]
+    assert_field_aligned_to!(Queue, desc.addr, 8,);
+    #[cfg(nested_offset_of)]
+    assert_field_aligned_to!(Queue, pair.1.b, 1);
+
+    #[repr(C)]
+    struct Ffi {
+        id: u32,
+        ptr: *const u8,
+        len: u16,
+    }
+
+    #[repr(C)]
+    struct Abi {
+        magic: u32,
+        version: u16,
+        flags: u16,
+        len: u64,
+        inner: V1,
+    }
+
+    #[repr(C)]
+    struct Pair(u8, u32);
+
+    assert_offset!(Abi, len, 8);
+    assert_offset!(Pair, 1, 4,);
+    assert_offsets!(Abi {
+        magic: 0,
+        version: 4,
+        flags: 2 * 3,
+        len: 8,
+        inner: 16,
+    });
+    assert_offsets!(Pair { 0: 0, 1: 4 });
+    #[cfg(nested_offset_of)]
+    assert_offsets!(Abi {
+        inner.b: 20,
+        inner.c: 22,
+    });
+
+    target_layouts!(Ffi {
+        target_pointer_width = "64" => { size: 24, align: 8, id: 0, ptr: 8, len: 16 },
+        target_pointer_width = "32" => { size: 12, align: 4, ptr: 4, len: 8 },
+        target_pointer_width = "16" => { size: 8, ptr: 4, len: 6 },
+    });
+    target_layouts!(Descriptor {
+        all(target_arch = "x86_64", target_os = "linux") => { size: 128 },
+        any(not(target_arch = "x86_64"), not(target_os = "linux")) => { align: 64, next: 64 },
+    });
+}
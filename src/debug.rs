@@ -0,0 +1,314 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt;
+use core::mem;
+use core::ptr;
+
+/// Writes the fields of a struct in the form `name @ start..end: value`.
+/// Used by `fmt_with_offsets!`.
+#[doc(hidden)]
+pub struct FieldsWithOffsets<'a, 'f: 'a> {
+    f: &'a mut fmt::Formatter<'f>,
+    base: usize,
+    result: fmt::Result,
+    has_fields: bool,
+}
+
+impl<'a, 'f: 'a> FieldsWithOffsets<'a, 'f> {
+    #[doc(hidden)]
+    pub fn new<T>(f: &'a mut fmt::Formatter<'f>, name: &str, base: *const T) -> Self {
+        let result = f.write_str(name);
+        FieldsWithOffsets {
+            f,
+            base: base as usize,
+            result,
+            has_fields: false,
+        }
+    }
+
+    fn header<T>(&mut self, name: &str, field: *const T) -> fmt::Result {
+        let start = field as usize - self.base;
+        let prefix = if self.has_fields { ", " } else { " { " };
+        self.has_fields = true;
+        write!(
+            self.f,
+            "{}{} @ {}..{}: ",
+            prefix,
+            name,
+            start,
+            start + mem::size_of::<T>()
+        )
+    }
+
+    /// Writes a field using its `Debug` implementation.
+    /// The field must be initialized; it may be unaligned.
+    #[doc(hidden)]
+    pub unsafe fn field<T: fmt::Debug>(&mut self, name: &str, field: *const T) {
+        if self.result.is_err() {
+            return;
+        }
+        self.result = self.header(name, field).and_then(|()| {
+            if field as usize & (mem::align_of::<T>() - 1) == 0 {
+                fmt::Debug::fmt(&*field, self.f)
+            } else {
+                // Never dropped, so this bitwise copy does not duplicate ownership.
+                let mut copy = mem::MaybeUninit::<T>::uninit();
+                ptr::copy_nonoverlapping(
+                    field as *const u8,
+                    copy.as_mut_ptr() as *mut u8,
+                    mem::size_of::<T>(),
+                );
+                fmt::Debug::fmt(&*copy.as_ptr(), self.f)
+            }
+        });
+    }
+
+    /// Writes a field as a hex dump of its bytes. All of the bytes must be initialized.
+    #[doc(hidden)]
+    pub unsafe fn bytes<T>(&mut self, name: &str, field: *const T) {
+        if self.result.is_err() {
+            return;
+        }
+        self.result = self.header(name, field).and_then(|()| {
+            let bytes = field as *const u8;
+            self.f.write_str("[")?;
+            for i in 0..mem::size_of::<T>() {
+                let separator = if i == 0 { "" } else { " " };
+                write!(self.f, "{}{:02x}", separator, *bytes.add(i))?;
+            }
+            self.f.write_str("]")
+        });
+    }
+
+    #[doc(hidden)]
+    pub fn finish(self) -> fmt::Result {
+        self.result?;
+        if self.has_fields {
+            self.f.write_str(" }")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Formats the specified fields of a struct together with their offsets, in the form
+/// `Parent { name @ start..end: value, .. }`.
+///
+/// This is meant to be used in a `Debug` implementation: the printed ranges make padding
+/// between fields, and any other layout surprise, immediately visible.
+/// Fields are read without creating references to them if they are unaligned, so this works
+/// for packed structs as well.
+///
+/// A field marked with `#[bytes]` is printed as a hex dump of its bytes instead, which is
+/// useful for fields that do not implement `Debug`.
+///
+/// ## Examples
+/// ```
+/// use memoffset::fmt_with_offsets;
+/// use std::fmt;
+///
+/// #[repr(C, packed)]
+/// struct Header {
+///     kind: u8,
+///     len: u32,
+///     tag: [u8; 2],
+/// }
+///
+/// impl fmt::Debug for Header {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         unsafe { fmt_with_offsets!(f, self, Header, [kind, len, #[bytes] tag]) }
+///     }
+/// }
+///
+/// let header = Header { kind: 1, len: 300, tag: *b"ok" };
+/// assert_eq!(
+///     format!("{:?}", header),
+///     "Header { kind @ 0..1: 1, len @ 1..5: 300, tag @ 5..7: [6f 6b] }"
+/// );
+/// ```
+///
+/// ## Safety
+/// The macro is only unsafe if a field is marked with `#[bytes]`: all bytes of such a field,
+/// including any padding inside it, must be initialized.
+///
+/// Requires rustc 1.36 or newer.
+#[cfg(maybe_uninit)]
+#[macro_export(local_inner_macros)]
+macro_rules! fmt_with_offsets {
+    ($f:expr, $value:expr, $parent:path, [$($fields:tt)*] $(,)*) => {{
+        let value: &$parent = $value;
+        let base = value as *const $parent;
+        #[allow(unused_mut)] // for when there are no fields
+        let mut out = $crate::__priv::FieldsWithOffsets::new(
+            $f,
+            _memoffset__stringify!($parent),
+            base,
+        );
+        _memoffset__fmt_fields!(out, base, $parent, $($fields)*);
+        out.finish()
+    }};
+}
+
+/// Writes each field of a `fmt_with_offsets!` field list, munching one field at a time.
+#[cfg(maybe_uninit)]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__fmt_fields {
+    ($out:ident, $base:ident, $parent:path,) => {};
+    ($out:ident, $base:ident, $parent:path, # [bytes] $field:tt) => {
+        _memoffset__fmt_fields!($out, $base, $parent, #[bytes] $field,);
+    };
+    ($out:ident, $base:ident, $parent:path, # [bytes] $field:tt, $($rest:tt)*) => {
+        $out.bytes(_memoffset__stringify!($field), raw_field!($base, $parent, $field));
+        _memoffset__fmt_fields!($out, $base, $parent, $($rest)*);
+    };
+    ($out:ident, $base:ident, $parent:path, $field:tt) => {
+        _memoffset__fmt_fields!($out, $base, $parent, $field,);
+    };
+    ($out:ident, $base:ident, $parent:path, $field:tt, $($rest:tt)*) => {
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            $out.field(_memoffset__stringify!($field), raw_field!($base, $parent, $field));
+        }
+        _memoffset__fmt_fields!($out, $base, $parent, $($rest)*);
+    };
+}
+
+#[cfg(all(test, maybe_uninit))]
+mod tests {
+    use core::fmt::{self, Write};
+    use core::str;
+
+    /// A fixed-capacity `fmt::Write` sink, as the tests cannot allocate.
+    struct Buf {
+        bytes: [u8; 256],
+        len: usize,
+    }
+
+    impl Write for Buf {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = self.len + s.len();
+            if end > self.bytes.len() {
+                return Err(fmt::Error);
+            }
+            self.bytes[self.len..end].copy_from_slice(s.as_bytes());
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    fn check<T: fmt::Debug>(value: &T, expected: &str) {
+        let mut buf = Buf {
+            bytes: [0; 256],
+            len: 0,
+        };
+        write!(buf, "{:?}", value).unwrap();
+        assert_eq!(str::from_utf8(&buf.bytes[..buf.len]).unwrap(), expected);
+    }
+
+    #[repr(C, packed)]
+    struct Packed {
+        a: u8,
+        b: u32,
+        c: u16,
+    }
+
+    impl fmt::Debug for Packed {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt_with_offsets!(f, self, Packed, [a, b, c])
+        }
+    }
+
+    #[repr(C)]
+    struct Inner {
+        x: u16,
+        y: u32,
+    }
+
+    impl fmt::Debug for Inner {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt_with_offsets!(f, self, Inner, [x, y])
+        }
+    }
+
+    #[repr(C)]
+    struct Outer {
+        tag: u8,
+        inner: Inner,
+        raw: [u8; 3],
+    }
+
+    impl fmt::Debug for Outer {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            unsafe {
+                fmt_with_offsets!(
+                    f,
+                    self,
+                    Outer,
+                    [
+                        tag,
+                        inner,
+                        #[bytes]
+                        raw,
+                    ]
+                )
+            }
+        }
+    }
+
+    #[test]
+    fn packed() {
+        check(
+            &Packed {
+                a: 1,
+                b: 0xDEAD,
+                c: 7,
+            },
+            "Packed { a @ 0..1: 1, b @ 1..5: 57005, c @ 5..7: 7 }",
+        );
+    }
+
+    #[test]
+    fn nested() {
+        check(
+            &Outer {
+                tag: 9,
+                inner: Inner { x: 2, y: 3 },
+                raw: [0xAB, 0, 0x10],
+            },
+            "Outer { tag @ 0..1: 9, inner @ 4..12: Inner { x @ 0..2: 2, y @ 4..8: 3 }, \
+             raw @ 12..15: [ab 00 10] }",
+        );
+    }
+
+    #[test]
+    fn no_fields() {
+        struct Opaque;
+
+        impl fmt::Debug for Opaque {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt_with_offsets!(f, self, Opaque, [])
+            }
+        }
+
+        check(&Opaque, "Opaque");
+    }
+}
@@ -0,0 +1,353 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// Declares a fieldless enum with one variant per listed field of a struct, along with
+/// `const fn` accessors for the offset, size, span and name of each field.
+///
+/// Unlike a `TypeLayout`, the enum can be matched exhaustively and stored compactly in tables,
+/// so that the compiler points out every place to update when a field is added.
+///
+/// The enum derives `Debug`, `Clone`, `Copy`, `PartialEq`, `Eq` and `Hash`, and gets these
+/// associated items:
+/// - `ALL`, an array of all variants in declaration order,
+/// - `offset(self) -> usize`,
+/// - `size(self) -> usize`,
+/// - `span(self) -> Range<usize>`,
+/// - `name(self) -> &'static str`, the name of the field.
+///
+/// Fields of tuple structs are given by their index.
+///
+/// ## Examples
+/// ```
+/// use memoffset::{field_enum, offset_of};
+///
+/// #[repr(C)]
+/// struct Foo {
+///     a: u32,
+///     b: [u8; 2],
+///     c: u64,
+/// }
+///
+/// field_enum!(pub enum FooField for Foo { A => a, B => b, C => c });
+///
+/// assert_eq!(FooField::B.offset(), offset_of!(Foo, b));
+/// assert_eq!(FooField::B.size(), 2);
+/// assert_eq!(FooField::C.span(), 8..16);
+/// assert_eq!(FooField::A.name(), "a");
+/// assert_eq!(FooField::ALL, [FooField::A, FooField::B, FooField::C]);
+///
+/// const C_OFFSET: usize = FooField::C.offset();
+/// assert_eq!(C_OFFSET, 8);
+/// ```
+///
+/// Requires rustc 1.65 or newer.
+#[macro_export]
+macro_rules! field_enum {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident for $parent:path {
+            $($variant:ident => $field:tt),+ $(,)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $($variant),+
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            /// All fields, in declaration order.
+            pub const ALL: [$name; [$($name::$variant),+].len()] = [$($name::$variant),+];
+
+            /// The offset of the field, in bytes.
+            pub const fn offset(self) -> usize {
+                match self {
+                    $($name::$variant => $crate::offset_of!($parent, $field)),+
+                }
+            }
+
+            /// The size of the field, in bytes.
+            pub const fn size(self) -> usize {
+                match self {
+                    $($name::$variant => $crate::_memoffset__field_size!($parent, $field)),+
+                }
+            }
+
+            /// The byte range of the field.
+            pub const fn span(self) -> $crate::__priv::ops::Range<usize> {
+                let offset = self.offset();
+                offset..offset + self.size()
+            }
+
+            /// The name of the field.
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $($name::$variant => stringify!($field)),+
+                }
+            }
+        }
+    };
+}
+
+/// Declares a module of constants for the offset, size and span of each listed field of a
+/// struct, so that they are computed once, at compile time, instead of at every use.
+///
+/// `define_offsets!(mod frame_offsets for Frame { seq })` declares a module `frame_offsets`,
+/// with a submodule per field that holds the constants:
+/// - `frame_offsets::seq::OFFSET: usize`,
+/// - `frame_offsets::seq::SIZE: usize`,
+/// - `frame_offsets::seq::SPAN: Range<usize>`.
+///
+/// Nested fields, and the fields of tuple structs, have to be named with `as`, as in
+/// `header.len as header_len` or `0 as tag`; any field can be renamed that way.
+/// Naming a field that does not exist, or giving two fields the same name, is a compile error.
+///
+/// The parent is named from inside the declared module, which imports everything from the
+/// module the macro is used in; a struct declared inside a function body is not visible there.
+///
+/// ## Examples
+/// ```
+/// use memoffset::define_offsets;
+///
+/// #[repr(C)]
+/// struct Header {
+///     magic: u16,
+///     len: u16,
+/// }
+///
+/// #[repr(C)]
+/// struct Frame {
+///     header: Header,
+///     seq: u32,
+///     payload: [u8; 8],
+///     crc: u32,
+/// }
+///
+/// define_offsets!(pub mod frame_offsets for Frame { header, seq, payload, crc });
+///
+/// fn main() {
+///     assert_eq!(frame_offsets::seq::OFFSET, 4);
+///     assert_eq!(frame_offsets::payload::SIZE, 8);
+///     assert_eq!(frame_offsets::crc::SPAN, 16..20);
+///
+///     let frame = [0u8; frame_offsets::crc::OFFSET + frame_offsets::crc::SIZE];
+///     assert_eq!(frame.len(), 20);
+/// }
+/// ```
+///
+/// On rustc 1.82 and newer, nested fields can be listed under a name of their own:
+///
+/// ```
+/// # #[cfg(nested_offset_of)]
+/// # mod example {
+/// use memoffset::define_offsets;
+///
+/// #[repr(C)]
+/// struct Header {
+///     magic: u16,
+///     len: u16,
+/// }
+///
+/// #[repr(C)]
+/// struct Frame {
+///     header: Header,
+///     seq: u32,
+/// }
+///
+/// define_offsets!(mod frame_offsets for Frame { header.len as len, seq });
+///
+/// # pub fn main() {
+/// assert_eq!(frame_offsets::len::SPAN, 2..4);
+/// # }
+/// # }
+/// # fn main() {
+/// #     #[cfg(nested_offset_of)]
+/// #     example::main();
+/// # }
+/// ```
+///
+/// Listing a field twice does not compile:
+///
+/// ```compile_fail
+/// # use memoffset::define_offsets;
+/// # #[repr(C)]
+/// # struct Frame {
+/// #     seq: u32,
+/// #     crc: u32,
+/// # }
+/// define_offsets!(mod frame_offsets for Frame { seq, crc as seq });
+/// # fn main() {}
+/// ```
+///
+/// Neither does listing one that does not exist:
+///
+/// ```compile_fail
+/// # use memoffset::define_offsets;
+/// # #[repr(C)]
+/// # struct Frame {
+/// #     seq: u32,
+/// #     crc: u32,
+/// # }
+/// define_offsets!(mod frame_offsets for Frame { seq, len });
+/// # fn main() {}
+/// ```
+///
+/// Requires rustc 1.65 or newer.
+#[macro_export]
+macro_rules! define_offsets {
+    (
+        $(#[$attr:meta])*
+        $vis:vis mod $name:ident for $parent:path {
+            $($field:tt $(. $rest:tt)* $(as $alias:ident)?),+ $(,)*
+        }
+    ) => {
+        $(#[$attr])*
+        $vis mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            $($crate::_memoffset__define_offset!($parent, [$field $(. $rest)*], $($alias)?);)+
+        }
+    };
+}
+
+/// Declares the submodule of one field for `define_offsets!`.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__define_offset {
+    ($parent:path, [$($field:tt).+], $name:ident) => {
+        #[allow(dead_code)]
+        pub mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            /// The byte range of the field.
+            pub const SPAN: $crate::__priv::ops::Range<usize> = $crate::span_of!($parent, $($field).+);
+            /// The offset of the field, in bytes.
+            pub const OFFSET: usize = SPAN.start;
+            /// The size of the field, in bytes.
+            pub const SIZE: usize = SPAN.end - SPAN.start;
+        }
+    };
+    ($parent:path, [$field:ident],) => {
+        $crate::_memoffset__define_offset!($parent, [$field], $field);
+    };
+    ($parent:path, [$($field:tt)+],) => {
+        compile_error!(concat!(
+            "`",
+            stringify!($($field)+),
+            "` needs a name to be listed under, as in `",
+            stringify!($($field)+),
+            " as name`"
+        ));
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[repr(C)]
+    struct Foo {
+        a: u8,
+        b: u32,
+        c: [u16; 3],
+        d: u64,
+    }
+
+    field_enum!(enum FooField for Foo { A => a, B => b, C => c, D => d });
+
+    #[repr(C)]
+    struct Tuple(u16, u8, u32);
+
+    field_enum!(enum TupleField for Tuple { F0 => 0, F1 => 1, F2 => 2 });
+
+    #[test]
+    fn offsets_agree() {
+        let offsets = [
+            offset_of!(Foo, a),
+            offset_of!(Foo, b),
+            offset_of!(Foo, c),
+            offset_of!(Foo, d),
+        ];
+        for (field, offset) in FooField::ALL.iter().zip(offsets.iter()) {
+            assert_eq!(field.offset(), *offset);
+        }
+        assert_eq!(FooField::ALL.len(), 4);
+        assert_eq!(FooField::C.span(), span_of!(Foo, c));
+        assert_eq!(FooField::D.name(), "d");
+    }
+
+    #[test]
+    fn tuple_fields() {
+        assert_eq!(
+            TupleField::ALL,
+            [TupleField::F0, TupleField::F1, TupleField::F2]
+        );
+        assert_eq!(TupleField::F1.offset(), offset_of!(Tuple, 1));
+        assert_eq!(TupleField::F2.span(), 4..8);
+        assert_eq!(TupleField::F0.name(), "0");
+    }
+
+    #[test]
+    fn const_eval() {
+        const SIZES: [usize; 4] = [
+            FooField::A.size(),
+            FooField::B.size(),
+            FooField::C.size(),
+            FooField::D.size(),
+        ];
+        assert_eq!(SIZES, [1, 4, 6, 8]);
+    }
+
+    define_offsets!(mod foo_offsets for Foo { a, b, c as words, d });
+    define_offsets!(mod tuple_offsets for Tuple { 0 as tag, 2 as value });
+
+    #[test]
+    fn offset_consts() {
+        assert_eq!(foo_offsets::a::OFFSET, 0);
+        assert_eq!(foo_offsets::b::SPAN, span_of!(Foo, b));
+        assert_eq!(foo_offsets::words::SIZE, 6);
+        assert_eq!(foo_offsets::d::OFFSET, offset_of!(Foo, d));
+        assert_eq!(tuple_offsets::tag::SPAN, 0..2);
+        assert_eq!(tuple_offsets::value::OFFSET, 4);
+
+        let buf = [0u8; foo_offsets::d::OFFSET + foo_offsets::d::SIZE];
+        assert_eq!(buf.len(), 24);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[repr(C)]
+    struct Outer {
+        x: u8,
+        foo: Foo,
+        tuple: Tuple,
+    }
+
+    #[cfg(nested_offset_of)]
+    define_offsets!(mod outer_offsets for Outer { x, foo.c as foo_c, tuple.2 as value });
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn nested_offset_consts() {
+        assert_eq!(outer_offsets::x::SPAN, 0..1);
+        assert_eq!(outer_offsets::foo_c::SPAN, 16..22);
+        assert_eq!(outer_offsets::value::OFFSET, 36);
+    }
+}
@@ -0,0 +1,926 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// Copies the specified fields of an initialized struct into the corresponding fields of a
+/// possibly uninitialized one.
+///
+/// The fields are copied bytewise, so this works for packed structs as well.
+/// All other fields of the target are left untouched, and hence possibly uninitialized.
+/// On rustc 1.82 and newer, a field may also be a nested path, such as `hdr.len`, to copy only
+/// part of a nested struct.
+///
+/// ## Examples
+/// ```
+/// use memoffset::{partial_init_from, raw_field};
+/// use std::mem::MaybeUninit;
+///
+/// #[repr(C)]
+/// struct Node {
+///     key: u64,
+///     len: u16,
+///     children: [u32; 4],
+/// }
+///
+/// let old = Node { key: 7, len: 2, children: [1, 2, 0, 0] };
+/// let mut new = MaybeUninit::<Node>::uninit();
+/// unsafe { partial_init_from!(&mut new, &old, Node, [key, len]) };
+///
+/// // Initialize the remaining field.
+/// let children = raw_field!(new.as_mut_ptr(), Node, children) as *mut [u32; 4];
+/// unsafe { children.write([0; 4]) };
+///
+/// let new = unsafe { new.assume_init() };
+/// assert_eq!(new.key, 7);
+/// assert_eq!(new.len, 2);
+/// ```
+///
+/// ## Safety
+/// The fields are duplicated bitwise, without cloning.
+/// If a field owns resources (such as a `Box` or a `Vec`), both structs now claim ownership of
+/// them, and it is up to the caller to make sure only one of them is ever dropped or used to
+/// release the resources.
+///
+/// Requires rustc 1.36 or newer.
+#[cfg(maybe_uninit)]
+#[macro_export(local_inner_macros)]
+macro_rules! partial_init_from {
+    ($target:expr, $source:expr, $parent:path, [$($($field:tt).+),+ $(,)*] $(,)*) => {{
+        let target: &mut $crate::__priv::mem::MaybeUninit<$parent> = $target;
+        let source: &$parent = $source;
+        let to_base = target.as_mut_ptr() as *const $parent;
+        let from_base = source as *const $parent;
+        $(
+            $crate::__priv::copy_pointee(
+                raw_field!(from_base, $parent, $($field).+),
+                raw_field!(to_base, $parent, $($field).+),
+            );
+        )+
+    }};
+}
+
+/// Copies one field of a struct into the same field of another struct of the same type.
+///
+/// The field is copied bytewise, so this works for fields of packed structs as well, and it
+/// must be `Copy`; use `clone_field!` for other fields.
+/// The field may be a nested path, such as `hdr.len`, which requires rustc 1.82 or newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::copy_field;
+///
+/// #[repr(C, packed)]
+/// struct Entry {
+///     tag: u8,
+///     key: u64,
+///     value: u32,
+/// }
+///
+/// let src = Entry { tag: 1, key: 7, value: 70 };
+/// let mut dst = Entry { tag: 2, key: 9, value: 90 };
+/// copy_field!(&mut dst, &src, Entry, key);
+///
+/// assert_eq!({ dst.key }, 7);
+/// assert_eq!({ dst.value }, 90);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! copy_field {
+    ($dst:expr, $src:expr, $parent:path, $($field:tt).+ $(,)*) => {{
+        let dst: &mut $parent = $dst;
+        let src: &$parent = $src;
+        let to = raw_field!(dst as *mut $parent as *const $parent, $parent, $($field).+);
+        let from = raw_field!(src as *const $parent, $parent, $($field).+);
+        // The field is `Copy`, and `dst` and `src` can not overlap.
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            $crate::__priv::copy_value(from, to)
+        }
+    }};
+}
+
+/// Replaces one field of a struct with a clone of the same field of another struct of the same
+/// type, dropping the old value.
+///
+/// This is the counterpart of `copy_field!` for fields that are `Clone` but not `Copy`.
+/// The fields are read and written unaligned, so this works for fields of packed structs as
+/// well; the clone is then made from a temporary bitwise copy of the source field.
+///
+/// ## Examples
+/// ```
+/// use memoffset::clone_field;
+///
+/// struct Config {
+///     id: u32,
+///     name: String,
+/// }
+///
+/// let src = Config { id: 1, name: String::from("primary") };
+/// let mut dst = Config { id: 2, name: String::from("backup") };
+/// clone_field!(&mut dst, &src, Config, name);
+///
+/// assert_eq!(dst.name, "primary");
+/// assert_eq!(src.name, "primary");
+/// assert_eq!(dst.id, 2);
+/// ```
+///
+/// Requires rustc 1.36 or newer.
+#[cfg(maybe_uninit)]
+#[macro_export(local_inner_macros)]
+macro_rules! clone_field {
+    ($dst:expr, $src:expr, $parent:path, $($field:tt).+ $(,)*) => {{
+        let dst: &mut $parent = $dst;
+        let src: &$parent = $src;
+        let to = raw_field!(dst as *mut $parent as *const $parent, $parent, $($field).+);
+        let from = raw_field!(src as *const $parent, $parent, $($field).+);
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            $crate::__priv::clone_value(from, to)
+        }
+    }};
+}
+
+/// Produces a `&[MaybeUninit<u8>]` covering exactly the bytes of the specified field of a
+/// possibly uninitialized struct.
+///
+/// The field may be a nested path, such as `hdr.len`, which requires rustc 1.82 or newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::uninit_field_bytes;
+/// use std::mem::MaybeUninit;
+///
+/// #[repr(C)]
+/// struct Packet {
+///     len: u16,
+///     payload: [u8; 6],
+/// }
+///
+/// let packet = MaybeUninit::<Packet>::uninit();
+/// assert_eq!(uninit_field_bytes!(&packet, Packet, payload).len(), 6);
+/// ```
+///
+/// Requires rustc 1.36 or newer.
+#[cfg(maybe_uninit)]
+#[macro_export(local_inner_macros)]
+macro_rules! uninit_field_bytes {
+    ($parent_ref:expr, $parent:path, $($field:tt).+ $(,)*) => {{
+        let parent: &$crate::__priv::mem::MaybeUninit<$parent> = $parent_ref;
+        let range = _memoffset__field_range!($parent, $($field).+);
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            $crate::__priv::uninit_bytes(parent, range)
+        }
+    }};
+}
+
+/// Produces a `&mut [MaybeUninit<u8>]` covering exactly the bytes of the specified field of a
+/// possibly uninitialized struct.
+///
+/// This allows filling in a field incrementally, for example from a reader, without ever
+/// creating a reference to uninitialized memory.
+/// As with `uninit_field_bytes!`, the field may be a nested path on rustc 1.82 and newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::uninit_field_bytes_mut;
+/// use std::mem::MaybeUninit;
+///
+/// #[repr(C)]
+/// struct Packet {
+///     len: u16,
+///     payload: [u8; 6],
+/// }
+///
+/// let mut packet = MaybeUninit::<Packet>::uninit();
+/// for (i, byte) in uninit_field_bytes_mut!(&mut packet, Packet, payload).iter_mut().enumerate() {
+///     *byte = MaybeUninit::new(i as u8);
+/// }
+/// for byte in uninit_field_bytes_mut!(&mut packet, Packet, len) {
+///     *byte = MaybeUninit::new(0);
+/// }
+///
+/// let packet = unsafe { packet.assume_init() };
+/// assert_eq!(packet.payload, [0, 1, 2, 3, 4, 5]);
+/// ```
+///
+/// Requires rustc 1.36 or newer.
+#[cfg(maybe_uninit)]
+#[macro_export(local_inner_macros)]
+macro_rules! uninit_field_bytes_mut {
+    ($parent_ref:expr, $parent:path, $($field:tt).+ $(,)*) => {{
+        let parent: &mut $crate::__priv::mem::MaybeUninit<$parent> = $parent_ref;
+        let range = _memoffset__field_range!($parent, $($field).+);
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            $crate::__priv::uninit_bytes_mut(parent, range)
+        }
+    }};
+}
+
+/// Initializes the specified field of every struct in a slice of possibly uninitialized structs,
+/// with the value that a closure returns for the index of the struct.
+///
+/// The fields are written through raw pointers, without creating references to the structs,
+/// and without dropping any previous value of the fields.
+/// The other fields are left untouched.
+///
+/// ## Examples
+/// ```
+/// use memoffset::{fill_field_slice, init_field_slice};
+/// use std::mem::MaybeUninit;
+///
+/// #[repr(C)]
+/// struct Node {
+///     next: usize,
+///     value: u32,
+/// }
+///
+/// let mut nodes: [MaybeUninit<Node>; 8] = unsafe { MaybeUninit::uninit().assume_init() };
+/// init_field_slice!(&mut nodes, Node, next, |i| i + 1);
+/// fill_field_slice!(&mut nodes, Node, value, 0);
+///
+/// let last = unsafe { &*nodes[7].as_ptr() };
+/// assert_eq!(last.next, 8);
+/// ```
+///
+/// Requires rustc 1.36 or newer.
+#[cfg(maybe_uninit)]
+#[macro_export(local_inner_macros)]
+macro_rules! init_field_slice {
+    ($slice:expr, $parent:path, $field:tt, $init:expr $(,)*) => {{
+        let slice: &mut [$crate::__priv::mem::MaybeUninit<$parent>] = $slice;
+        #[allow(unused_mut)] // for closures that are `Fn`
+        let mut init = $init;
+        for (i, elem) in $crate::__priv::Iterator::enumerate(slice.iter_mut()) {
+            let field = raw_field!(elem.as_mut_ptr() as *const $parent, $parent, $field);
+            let value = init(i);
+            #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+            unsafe {
+                $crate::__priv::write_pointee(field, value);
+            }
+        }
+    }};
+}
+
+/// Initializes the specified field of every struct in a slice of possibly uninitialized structs
+/// with clones of the same value.
+///
+/// This is the constant-value counterpart of `init_field_slice!`.
+///
+/// Requires rustc 1.36 or newer.
+#[cfg(maybe_uninit)]
+#[macro_export(local_inner_macros)]
+macro_rules! fill_field_slice {
+    ($slice:expr, $parent:path, $field:tt, $value:expr $(,)*) => {{
+        let slice: &mut [$crate::__priv::mem::MaybeUninit<$parent>] = $slice;
+        let value = $value;
+        for elem in slice.iter_mut() {
+            let field = raw_field!(elem.as_mut_ptr() as *const $parent, $parent, $field);
+            #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+            unsafe {
+                $crate::__priv::write_pointee(field, $crate::__priv::Clone::clone(&value));
+            }
+        }
+    }};
+}
+
+/// Defines a builder that initializes a struct one field at a time, and only hands it out once
+/// every field has been set.
+///
+/// The invocation declares the builder type, the struct it builds, and a setter for each field
+/// with the type of the field:
+///
+/// ```text
+/// uninit_builder! {
+///     pub struct FooBuilder for Foo {
+///         a: u32 => set_a,
+///         b: String => set_b,
+///     }
+/// }
+/// ```
+///
+/// This adds `Foo::uninit_builder()`, which returns an empty `FooBuilder`.
+/// Each setter takes the builder by value, writes its field in place, and returns the builder.
+/// Setting a field twice drops the old value.
+/// `finish()` returns the struct if every field was set, and `None` otherwise; dropping an
+/// unfinished builder drops the fields that were set.
+///
+/// Every field of the struct must be listed exactly once, which is checked at compile time.
+/// Whether each of them was actually set is tracked at runtime in a bitmask, since tracking it in
+/// the type of the builder would need a type parameter per field. So the struct may have at most
+/// 64 fields.
+/// The fields are written unaligned, so this works for packed structs as well.
+///
+/// ## Examples
+/// ```
+/// use memoffset::uninit_builder;
+///
+/// #[repr(C, packed)]
+/// struct Config {
+///     id: u32,
+///     name: String,
+///     retries: u8,
+/// }
+///
+/// uninit_builder! {
+///     struct ConfigBuilder for Config {
+///         id: u32 => set_id,
+///         name: String => set_name,
+///         retries: u8 => set_retries,
+///     }
+/// }
+///
+/// let config = Config::uninit_builder()
+///     .set_name(String::from("primary"))
+///     .set_retries(3)
+///     .set_id(7)
+///     .finish()
+///     .unwrap();
+/// assert_eq!({ config.id }, 7);
+///
+/// // A missing field means there is no struct.
+/// let partial = Config::uninit_builder().set_id(1).set_retries(0);
+/// assert!(partial.finish().is_none());
+/// ```
+///
+/// Leaving a field out of the declaration is an error:
+/// ```compile_fail
+/// use memoffset::uninit_builder;
+///
+/// struct Config {
+///     id: u32,
+///     retries: u8,
+/// }
+///
+/// uninit_builder! {
+///     struct ConfigBuilder for Config {
+///         id: u32 => set_id,
+///     }
+/// }
+/// ```
+///
+/// Requires rustc 1.36 or newer.
+#[cfg(maybe_uninit)]
+#[macro_export(local_inner_macros)]
+macro_rules! uninit_builder {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $builder:ident for $parent:path {
+            $($field:tt : $ty:ty => $setter:ident),* $(,)*
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $builder {
+            value: $crate::__priv::mem::MaybeUninit<$parent>,
+            set: u64,
+        }
+
+        impl $parent {
+            /// Returns a builder with none of the fields set.
+            #[allow(dead_code)]
+            $vis fn uninit_builder() -> $builder {
+                // Checks that every field is listed exactly once.
+                #[allow(clippy::unneeded_field_pattern)]
+                let _ = |value: $parent| {
+                    let $parent { $($field: _),* } = value;
+                };
+                let fields: &[&str] = &[$(_memoffset__stringify!($field)),*];
+                let _ = $crate::__priv::builder_mask(fields.len());
+                $builder {
+                    value: $crate::__priv::mem::MaybeUninit::uninit(),
+                    set: 0,
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl $builder {
+            /// Returns the struct if every field was set, and `None` otherwise.
+            $vis fn finish(self) -> $crate::__priv::Option<$parent> {
+                let fields: &[&str] = &[$(_memoffset__stringify!($field)),*];
+                if self.set != $crate::__priv::builder_mask(fields.len()) {
+                    return $crate::__priv::Option::None;
+                }
+                let this = $crate::__priv::mem::ManuallyDrop::new(self);
+                // SAFETY: every field was written.
+                $crate::__priv::Option::Some(unsafe { $crate::__priv::ptr::read(this.value.as_ptr()) })
+            }
+        }
+
+        _memoffset__builder_setters!($vis $builder, $parent, (0), $($field : $ty => $setter,)*);
+
+        impl $crate::__priv::ops::Drop for $builder {
+            #[allow(unused_mut, unused_variables)] // for structs without fields
+            fn drop(&mut self) {
+                let base = self.value.as_ptr();
+                let mut bit = 1u64;
+                $(
+                    if self.set & bit != 0 {
+                        // SAFETY: the field was written, and is not dropped anywhere else.
+                        unsafe {
+                            $crate::__priv::drop_pointee(raw_field!(base, $parent, $field));
+                        }
+                    }
+                    bit <<= 1;
+                )*
+            }
+        }
+    };
+}
+
+/// Generates the setters of a builder defined with `uninit_builder!`, numbering the fields.
+#[cfg(maybe_uninit)]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__builder_setters {
+    ($vis:vis $builder:ident, $parent:path, ($($bit:tt)*),) => {};
+    (
+        $vis:vis $builder:ident, $parent:path, ($($bit:tt)*),
+        $field:tt : $ty:ty => $setter:ident, $($rest:tt)*
+    ) => {
+        #[allow(dead_code)]
+        impl $builder {
+            /// Sets the field, dropping its old value if it was already set.
+            $vis fn $setter(mut self, value: $ty) -> Self {
+                let bit = 1u64 << ($($bit)*);
+                let base = self.value.as_mut_ptr() as *const $parent;
+                let field = raw_field!(base, $parent, $field);
+                // Cleared first, so a panicking drop does not leave a dropped field marked set.
+                let was_set = self.set & bit != 0;
+                self.set &= !bit;
+                // SAFETY: the field lies within `self.value`, and was written if it was set.
+                unsafe {
+                    if was_set {
+                        $crate::__priv::drop_pointee(field);
+                    }
+                    $crate::__priv::write_pointee(field, value);
+                }
+                self.set |= bit;
+                self
+            }
+        }
+
+        _memoffset__builder_setters!($vis $builder, $parent, ($($bit)* + 1), $($rest)*);
+    };
+}
+
+#[cfg(maybe_uninit)]
+#[cfg(test)]
+mod tests {
+    use core::mem::MaybeUninit;
+
+    #[repr(C)]
+    struct Node {
+        key: u64,
+        flags: u8,
+        len: u16,
+        children: [u32; 3],
+    }
+
+    #[test]
+    fn complete_initialization() {
+        let old = Node {
+            key: 0xDEAD_BEEF_F00D,
+            flags: 3,
+            len: 2,
+            children: [1, 2, 3],
+        };
+
+        let mut new = MaybeUninit::<Node>::uninit();
+        unsafe { partial_init_from!(&mut new, &old, Node, [key, len]) };
+
+        let base = new.as_mut_ptr() as *const Node;
+        unsafe {
+            (raw_field!(base, Node, flags) as *mut u8).write(9);
+            (raw_field!(base, Node, children) as *mut [u32; 3]).write([4, 5, 6]);
+        }
+        let new = unsafe { new.assume_init() };
+
+        assert_eq!(new.key, old.key);
+        assert_eq!(new.len, old.len);
+        assert_eq!(new.flags, 9);
+        assert_eq!(new.children, [4, 5, 6]);
+        assert_eq!(old.flags, 3);
+        assert_eq!(old.children, [1, 2, 3]);
+    }
+
+    #[test]
+    fn packed() {
+        #[repr(C, packed)]
+        struct Packed {
+            a: u8,
+            b: u64,
+            c: u16,
+        }
+
+        let old = Packed {
+            a: 1,
+            b: 0x0102_0304_0506_0708,
+            c: 3,
+        };
+        let mut new = MaybeUninit::<Packed>::uninit();
+        unsafe { partial_init_from!(&mut new, &old, Packed, [a, b, c]) };
+        let new = unsafe { new.assume_init() };
+
+        assert_eq!({ new.a }, 1);
+        assert_eq!({ new.b }, 0x0102_0304_0506_0708);
+        assert_eq!({ new.c }, 3);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn nested_fields() {
+        #[repr(C)]
+        struct Header {
+            kind: u8,
+            len: u32,
+        }
+
+        #[repr(C)]
+        struct Message(u16, Header, [u8; 4]);
+
+        let old = Message(
+            1,
+            Header {
+                kind: 2,
+                len: 0x0304_0506,
+            },
+            [7; 4],
+        );
+        let mut new = MaybeUninit::<Message>::uninit();
+        unsafe { partial_init_from!(&mut new, &old, Message, [0, 1.len, 2]) };
+
+        let base = new.as_mut_ptr() as *const Message;
+        unsafe { (raw_field!(base, Message, 1.kind) as *mut u8).write(9) };
+        let new = unsafe { new.assume_init() };
+
+        assert_eq!(new.0, 1);
+        assert_eq!(new.1.kind, 9);
+        assert_eq!(new.1.len, 0x0304_0506);
+        assert_eq!(new.2, [7; 4]);
+    }
+
+    #[test]
+    fn copy_fields() {
+        #[repr(C, packed)]
+        struct Packed {
+            a: u8,
+            b: u64,
+            c: (u16, u32),
+        }
+
+        let src = Packed {
+            a: 1,
+            b: 0x0102_0304_0506_0708,
+            c: (3, 4),
+        };
+        let mut dst = Packed {
+            a: 9,
+            b: 9,
+            c: (9, 9),
+        };
+        copy_field!(&mut dst, &src, Packed, b);
+        assert_eq!({ dst.a }, 9);
+        assert_eq!({ dst.b }, 0x0102_0304_0506_0708);
+        assert_eq!({ dst.c }, (9, 9));
+
+        copy_field!(&mut dst, &src, Packed, c,);
+        assert_eq!({ dst.c }, (3, 4));
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn copy_nested_field() {
+        #[derive(Clone, Copy)]
+        #[repr(C)]
+        struct Header {
+            kind: u8,
+            len: u32,
+        }
+
+        #[repr(C)]
+        struct Message(u16, Header);
+
+        let src = Message(1, Header { kind: 2, len: 3 });
+        let mut dst = Message(4, Header { kind: 5, len: 6 });
+        copy_field!(&mut dst, &src, Message, 1.len);
+
+        assert_eq!(dst.0, 4);
+        assert_eq!(dst.1.kind, 5);
+        assert_eq!(dst.1.len, 3);
+    }
+
+    #[test]
+    fn clone_non_copy_field() {
+        use core::cell::Cell;
+
+        // Counts its clones and drops through a shared counter.
+        struct Tracked<'a> {
+            id: u32,
+            clones: &'a Cell<u32>,
+            drops: &'a Cell<u32>,
+        }
+
+        impl<'a> Clone for Tracked<'a> {
+            fn clone(&self) -> Self {
+                self.clones.set(self.clones.get() + 1);
+                Tracked {
+                    id: self.id + 100,
+                    clones: self.clones,
+                    drops: self.drops,
+                }
+            }
+        }
+
+        impl<'a> Drop for Tracked<'a> {
+            fn drop(&mut self) {
+                self.drops.set(self.drops.get() + 1);
+            }
+        }
+
+        #[repr(C, packed)]
+        struct Slot<'a> {
+            tag: u8,
+            value: Tracked<'a>,
+        }
+
+        let clones = Cell::new(0);
+        let drops = Cell::new(0);
+        let tracked = |id| Tracked {
+            id,
+            clones: &clones,
+            drops: &drops,
+        };
+
+        let src = Slot {
+            tag: 1,
+            value: tracked(1),
+        };
+        let mut dst = Slot {
+            tag: 2,
+            value: tracked(2),
+        };
+        clone_field!(&mut dst, &src, Slot, value);
+
+        // The old value of `dst` is dropped, and `src` is left alone.
+        assert_eq!((clones.get(), drops.get()), (1, 1));
+        let value = unsafe { raw_field!(&dst as *const Slot, Slot, value).read_unaligned() };
+        assert_eq!(value.id, 101);
+        core::mem::forget(value);
+        assert_eq!({ dst.tag }, 2);
+
+        drop(src);
+        drop(dst);
+        assert_eq!((clones.get(), drops.get()), (1, 3));
+    }
+
+    #[test]
+    fn fill_field_bytewise() {
+        #[repr(C)]
+        struct Packet {
+            kind: u8,
+            seq: u32,
+            payload: [u16; 3],
+        }
+
+        let mut packet = MaybeUninit::<Packet>::uninit();
+
+        let seq = uninit_field_bytes_mut!(&mut packet, Packet, seq);
+        assert_eq!(seq.len(), 4);
+        for (byte, value) in seq.iter_mut().zip(&0x0A0B_0C0Du32.to_ne_bytes()) {
+            *byte = MaybeUninit::new(*value);
+        }
+        for byte in uninit_field_bytes_mut!(&mut packet, Packet, payload) {
+            *byte = MaybeUninit::new(0xFF);
+        }
+        uninit_field_bytes_mut!(&mut packet, Packet, kind)[0] = MaybeUninit::new(7);
+
+        assert_eq!(uninit_field_bytes!(&packet, Packet, payload).len(), 6);
+        assert_eq!(
+            uninit_field_bytes!(&packet, Packet, seq).as_ptr() as usize,
+            packet.as_ptr() as usize + offset_of!(Packet, seq)
+        );
+
+        let packet = unsafe { packet.assume_init() };
+        assert_eq!(packet.kind, 7);
+        assert_eq!(packet.seq, 0x0A0B_0C0D);
+        assert_eq!(packet.payload, [0xFFFF; 3]);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn fill_nested_field_bytewise() {
+        #[repr(C)]
+        struct Header {
+            kind: u8,
+            len: u32,
+        }
+
+        #[repr(C)]
+        struct Frame {
+            id: u16,
+            hdr: Header,
+        }
+
+        let mut frame = MaybeUninit::<Frame>::uninit();
+        let len = uninit_field_bytes_mut!(&mut frame, Frame, hdr.len);
+        assert_eq!(len.len(), 4);
+        for (byte, value) in len.iter_mut().zip(&0x0A0B_0C0Du32.to_ne_bytes()) {
+            *byte = MaybeUninit::new(*value);
+        }
+
+        assert_eq!(
+            uninit_field_bytes!(&frame, Frame, hdr.len).as_ptr() as usize,
+            frame.as_ptr() as usize + offset_of!(Frame, hdr.len)
+        );
+        assert_eq!(uninit_field_bytes!(&frame, Frame, hdr.kind,).len(), 1);
+
+        let len = raw_field!(frame.as_ptr(), Frame, hdr.len);
+        assert_eq!(unsafe { len.read() }, 0x0A0B_0C0D);
+    }
+
+    #[test]
+    fn column_initialization() {
+        #[repr(C)]
+        struct Link {
+            prev: u16,
+            next: u16,
+            key: u64,
+            tag: u8,
+        }
+
+        const N: usize = 1000;
+        let mut links: [MaybeUninit<Link>; N] = unsafe { MaybeUninit::uninit().assume_init() };
+
+        // One column first, then the rest.
+        init_field_slice!(&mut links, Link, next, |i| ((i + 1) % N) as u16);
+        init_field_slice!(&mut links[..], Link, prev, |i| ((i + N - 1) % N) as u16);
+        fill_field_slice!(&mut links, Link, key, 0xFEED_u64);
+        let mut tag = 0u8;
+        init_field_slice!(&mut links, Link, tag, |_| {
+            tag = tag.wrapping_add(1);
+            tag
+        });
+
+        for (i, link) in links.iter().enumerate() {
+            let link = unsafe { &*link.as_ptr() };
+            assert_eq!(link.next as usize, (i + 1) % N);
+            assert_eq!(link.prev as usize, (i + N - 1) % N);
+            assert_eq!(link.key, 0xFEED);
+            assert_eq!(link.tag, (i + 1) as u8);
+        }
+    }
+
+    #[test]
+    fn column_initialization_packed() {
+        #[repr(C, packed)]
+        struct Packed {
+            a: u8,
+            b: u32,
+        }
+
+        let mut values: [MaybeUninit<Packed>; 5] = unsafe { MaybeUninit::uninit().assume_init() };
+        init_field_slice!(&mut values, Packed, b, |i| i as u32 * 3);
+        fill_field_slice!(&mut values, Packed, a, 9);
+
+        for (i, value) in values.iter().enumerate() {
+            let value = unsafe { value.as_ptr().read() };
+            assert_eq!({ value.a }, 9);
+            assert_eq!({ value.b }, i as u32 * 3);
+        }
+    }
+
+    #[test]
+    fn builder_complete() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Tracked(u32);
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        #[repr(C, packed)]
+        struct Config {
+            id: u8,
+            name: Tracked,
+            retries: u32,
+        }
+
+        uninit_builder! {
+            /// Builds a `Config`.
+            struct ConfigBuilder for Config {
+                id: u8 => set_id,
+                name: Tracked => set_name,
+                retries: u32 => set_retries,
+            }
+        }
+
+        let builder = Config::uninit_builder().set_name(Tracked(1)).set_retries(5);
+        // Setting a field again drops the old value.
+        let builder = builder.set_name(Tracked(2));
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+
+        let config = builder.set_id(3).finish().unwrap();
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+        assert_eq!({ config.id }, 3);
+        assert_eq!({ config.retries }, 5);
+        let name = config.name;
+        assert_eq!(name.0, 2);
+        drop(name);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn builder_missing_field() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        pub(crate) struct Tracked(u32);
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        pub(crate) struct Entry {
+            key: Tracked,
+            value: Tracked,
+            len: usize,
+        }
+
+        uninit_builder! {
+            pub(crate) struct EntryBuilder for Entry {
+                key: Tracked => set_key,
+                value: Tracked => set_value,
+                len: usize => set_len,
+            }
+        }
+
+        // Only the fields that were set are dropped.
+        let builder = Entry::uninit_builder().set_key(Tracked(1)).set_len(2);
+        assert!(builder.finish().is_none());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+
+        assert!(Entry::uninit_builder().finish().is_none());
+        drop(Entry::uninit_builder().set_value(Tracked(3)));
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+
+        let entry = Entry::uninit_builder()
+            .set_len(4)
+            .set_value(Tracked(5))
+            .set_key(Tracked(6))
+            .finish()
+            .unwrap();
+        assert_eq!((entry.key.0, entry.value.0, entry.len), (6, 5, 4));
+        drop(entry);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn builder_tuple_struct() {
+        struct Pair(u16, [u64; 2]);
+        struct Unit {}
+
+        uninit_builder! {
+            struct PairBuilder for Pair {
+                1: [u64; 2] => second,
+                0: u16 => first,
+            }
+        }
+        uninit_builder! {
+            struct UnitBuilder for Unit {}
+        }
+
+        let pair = Pair::uninit_builder()
+            .second([7, 8])
+            .first(9)
+            .finish()
+            .unwrap();
+        assert_eq!((pair.0, pair.1), (9, [7, 8]));
+        assert!(Pair::uninit_builder().second([0; 2]).finish().is_none());
+        assert!(Unit::uninit_builder().finish().is_some());
+    }
+}
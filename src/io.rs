@@ -0,0 +1,753 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Helpers for I/O over the fields of a struct.
+//!
+//! Requires the `std` cargo feature.
+
+use core::marker::PhantomData;
+use core::ops::{Bound, Range, RangeBounds};
+use core::{mem, ptr, slice};
+use layout::{FieldLayout, TypeLayout};
+use span::{expect_span, span_at};
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+
+/// Computes the `(offset, length)` byte region of a field, or of a sub-range of the elements of
+/// an array field, given a base pointer to the parent type.
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__byte_region {
+    ($base:expr, $parent:path, $field:tt) => {{
+        let base = $base;
+        let field = raw_field!(base, $parent, $field);
+        (
+            _memoffset_offset_from_unsafe!(field, base),
+            $crate::__priv::size_of_pointee(field),
+        )
+    }};
+    ($base:expr, $parent:path, $field:tt [$range:expr]) => {{
+        let base = $base;
+        let field = raw_field!(base, $parent, $field);
+        let array_size = $crate::__priv::size_of_pointee(field);
+        let (start, len) = $crate::io::element_range(field as *const [_], array_size, $range);
+        (_memoffset_offset_from_unsafe!(field, base) + start, len)
+    }};
+}
+
+/// Computes the `(start, end)` byte range of a sub-range of the elements of an array field,
+/// where the range is known at compile time.
+#[cfg(inline_const)]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__element_bytes {
+    ($parent:path, $field:tt, $start:expr, $end:expr) => {{
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        let field = raw_field!(base_ptr, $parent, $field);
+        $crate::io::element_span(
+            field as *const [_],
+            _memoffset_offset_from_unsafe!(field, base_ptr),
+            $crate::__priv::size_of_pointee(field),
+            $start,
+            $end,
+        )
+    }};
+}
+
+/// Checks that the regions listed in `io_slices!` or `io_slices_mut!` are in layout order and
+/// do not overlap.
+/// This is a compile-time check if rustc supports inline `const` blocks and every region is a
+/// whole field or a range of elements with literal bounds, and a runtime check otherwise.
+#[cfg(inline_const)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__assert_disjoint_regions {
+    (@spans $regions:ident, $parent:path, [$($span:expr,)*]) => {
+        const {
+            assert!(
+                $crate::__priv::spans_ordered(&[$($span),*]),
+                "regions must be listed in layout order and must not overlap"
+            )
+        };
+    };
+    (@spans $regions:ident, $parent:path, [$($span:expr,)*] $field:tt $([..])*, $($rest:tt)*) => {
+        $crate::_memoffset__assert_disjoint_regions!(@spans $regions, $parent, [
+            $($span,)* $crate::_memoffset__field_range!($parent, $field),
+        ] $($rest)*)
+    };
+    (@spans $regions:ident, $parent:path, [$($span:expr,)*] $field:tt [$start:literal ..], $($rest:tt)*) => {
+        $crate::_memoffset__assert_disjoint_regions!(@spans $regions, $parent, [
+            $($span,)* $crate::_memoffset__element_bytes!($parent, $field, $start, $crate::__priv::Option::None),
+        ] $($rest)*)
+    };
+    (@spans $regions:ident, $parent:path, [$($span:expr,)*] $field:tt [.. $end:literal], $($rest:tt)*) => {
+        $crate::_memoffset__assert_disjoint_regions!(@spans $regions, $parent, [
+            $($span,)* $crate::_memoffset__element_bytes!($parent, $field, 0, $crate::__priv::Option::Some($end)),
+        ] $($rest)*)
+    };
+    (@spans $regions:ident, $parent:path, [$($span:expr,)*] $field:tt [..= $end:literal], $($rest:tt)*) => {
+        $crate::_memoffset__assert_disjoint_regions!(@spans $regions, $parent, [
+            $($span,)* $crate::_memoffset__element_bytes!($parent, $field, 0, $crate::__priv::Option::Some($end + 1)),
+        ] $($rest)*)
+    };
+    (@spans $regions:ident, $parent:path, [$($span:expr,)*] $field:tt [$start:literal .. $end:literal], $($rest:tt)*) => {
+        $crate::_memoffset__assert_disjoint_regions!(@spans $regions, $parent, [
+            $($span,)* $crate::_memoffset__element_bytes!($parent, $field, $start, $crate::__priv::Option::Some($end)),
+        ] $($rest)*)
+    };
+    (@spans $regions:ident, $parent:path, [$($span:expr,)*] $field:tt [$start:literal ..= $end:literal], $($rest:tt)*) => {
+        $crate::_memoffset__assert_disjoint_regions!(@spans $regions, $parent, [
+            $($span,)* $crate::_memoffset__element_bytes!($parent, $field, $start, $crate::__priv::Option::Some($end + 1)),
+        ] $($rest)*)
+    };
+    // A range with bounds that are only known at runtime.
+    (@spans $regions:ident, $parent:path, [$($span:expr,)*] $($rest:tt)*) => {
+        $crate::io::assert_disjoint_regions(&$regions);
+    };
+    ($regions:ident, $parent:path, $($entries:tt)+) => {
+        $crate::_memoffset__assert_disjoint_regions!(@spans $regions, $parent, [] $($entries)+,)
+    };
+}
+/// Checks that the regions listed in `io_slices!` or `io_slices_mut!` are in layout order and
+/// do not overlap.
+/// This is a compile-time check if rustc supports inline `const` blocks and every region is a
+/// whole field or a range of elements with literal bounds, and a runtime check otherwise.
+#[cfg(not(inline_const))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__assert_disjoint_regions {
+    ($regions:ident, $parent:path, $($entries:tt)+) => {
+        $crate::io::assert_disjoint_regions(&$regions);
+    };
+}
+
+/// Swallows its input.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__ignore {
+    ($($tt:tt)*) => {};
+}
+
+/// Resolves a range of elements of an array of `array_size` bytes to a `(offset, length)` byte
+/// region relative to the start of the array, panicking like slice indexing does if it is out of
+/// range.
+#[doc(hidden)]
+pub fn element_range<T, R: RangeBounds<usize>>(
+    _array: *const [T],
+    array_size: usize,
+    range: R,
+) -> (usize, usize) {
+    let size = mem::size_of::<T>();
+    let len = array_size.checked_div(size).unwrap_or(0);
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => match start.checked_add(1) {
+            Some(start) => start,
+            None => panic!("range start {} overflows `usize`", start),
+        },
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => match end.checked_add(1) {
+            Some(end) => end,
+            None => panic!("range end {} overflows `usize`", end),
+        },
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    assert!(
+        start <= end && end <= len,
+        "range {}..{} out of bounds for an array of {} elements",
+        start,
+        end,
+        len
+    );
+    (start * size, (end - start) * size)
+}
+
+/// Resolves a range of elements of an array of `array_size` bytes at `offset` to a
+/// `(start, end)` byte range, failing like `element_range` does if it is out of range.
+/// This is used for ranges with literal bounds, which are resolved at compile time.
+#[cfg(inline_const)]
+#[doc(hidden)]
+pub const fn element_span<T>(
+    _array: *const [T],
+    offset: usize,
+    array_size: usize,
+    start: usize,
+    end: Option<usize>,
+) -> (usize, usize) {
+    let size = mem::size_of::<T>();
+    let len = match array_size.checked_div(size) {
+        Some(len) => len,
+        None => 0,
+    };
+    let end = match end {
+        Some(end) => end,
+        None => len,
+    };
+    assert!(
+        start <= end && end <= len,
+        "range out of bounds for the array"
+    );
+    (offset + start * size, offset + end * size)
+}
+
+/// Panics unless the `(offset, length)` regions are listed in ascending order and do not overlap.
+#[doc(hidden)]
+pub fn assert_disjoint_regions(regions: &[(usize, usize)]) {
+    for pair in regions.windows(2) {
+        let (offset, len) = pair[0];
+        assert!(
+            offset + len <= pair[1].0,
+            "regions must be listed in layout order and must not overlap"
+        );
+    }
+}
+
+/// Creates an `IoSlice` over a region of `value`.
+///
+/// The region must lie inside `value` and consist of initialized bytes.
+#[doc(hidden)]
+pub unsafe fn io_slice<'a, T>(value: &'a T, (offset, len): (usize, usize)) -> IoSlice<'a> {
+    let base = value as *const T as *const u8;
+    IoSlice::new(slice::from_raw_parts(base.add(offset), len))
+}
+
+/// Turns a mutable borrow into a raw pointer, and a marker that keeps the borrow alive.
+#[doc(hidden)]
+pub fn split_borrow<T>(value: &mut T) -> (*mut T, PhantomData<&mut T>) {
+    (value as *mut T, PhantomData)
+}
+
+/// Zeroes a region of `value` and creates an `IoSliceMut` over it.
+///
+/// The region must lie inside `value` and must not overlap any other live slice.
+#[doc(hidden)]
+pub unsafe fn io_slice_mut<'a, T>(
+    value: *mut T,
+    _borrow: PhantomData<&'a mut T>,
+    (offset, len): (usize, usize),
+) -> IoSliceMut<'a> {
+    let ptr = (value as *mut u8).add(offset);
+    ptr.write_bytes(0, len);
+    IoSliceMut::new(slice::from_raw_parts_mut(ptr, len))
+}
+
+/// Creates an array of `IoSlice`s, one per listed field of a struct, for use with
+/// `Write::write_vectored`.
+///
+/// Fields of array type can be narrowed to a sub-range of their elements, as in `payload[..len]`.
+/// The range may depend on runtime values, and is bounds-checked like slice indexing.
+///
+/// ## Examples
+/// ```
+/// use memoffset::io_slices;
+/// use std::io::Write;
+///
+/// #[repr(C)]
+/// struct Frame {
+///     len: u16,
+///     kind: u16,
+///     payload: [u8; 64],
+///     crc: u32,
+/// }
+///
+/// let frame = Frame { len: 3, kind: 1, payload: [7; 64], crc: 0xAABBCCDD };
+/// let slices = unsafe { io_slices!(&frame, Frame, [len, payload[..frame.len as usize], crc]) };
+///
+/// let mut out = Vec::new();
+/// out.write_vectored(&slices).unwrap();
+/// assert_eq!(out.len(), 2 + 3 + 4);
+/// ```
+///
+/// The regions must be listed in layout order and must not overlap. On rustc 1.79 and newer,
+/// this is checked at compile time unless a range has bounds that are not literals:
+///
+/// ```compile_fail
+/// use memoffset::io_slices;
+///
+/// #[repr(C)]
+/// struct Frame {
+///     len: u16,
+///     payload: [u8; 64],
+/// }
+///
+/// let frame = Frame { len: 3, payload: [7; 64] };
+/// let slices = unsafe { io_slices!(&frame, Frame, [payload[..8], payload[4..]]) };
+/// ```
+///
+/// ## Safety
+/// The listed regions must not contain any padding bytes, as padding is uninitialized.
+///
+/// ## Panics
+/// Panics if a range is out of bounds, or if the regions are not listed in layout order or
+/// overlap each other, where that is not detected at compile time.
+///
+/// Requires the `std` cargo feature.
+#[macro_export(local_inner_macros)]
+macro_rules! io_slices {
+    ($value:expr, $parent:path, [$($field:tt $([$($range:tt)*])*),+ $(,)*] $(,)*) => {{
+        let value: &$parent = $value;
+        let base = value as *const $parent;
+        let regions = [$(_memoffset__byte_region!(base, $parent, $field $([$($range)*])*)),+];
+        _memoffset__assert_disjoint_regions!(regions, $parent, $($field $([$($range)*])*),+);
+        let mut regions = regions.iter();
+        [$({
+            _memoffset__ignore!($field);
+            $crate::io::io_slice(value, *$crate::__priv::Iterator::next(&mut regions).unwrap())
+        }),+]
+    }};
+}
+
+/// Creates an array of `IoSliceMut`s, one per listed field of a possibly uninitialized struct,
+/// for use with `Read::read_vectored`.
+///
+/// The listed regions are zeroed first, so the slices never expose uninitialized memory.
+/// Everything else in the value is left untouched.
+/// Fields of array type can be narrowed to a sub-range of their elements, and the regions are
+/// checked as in `io_slices!`.
+///
+/// ## Examples
+/// ```
+/// use memoffset::io_slices_mut;
+/// use std::io::Read;
+/// use std::mem::MaybeUninit;
+///
+/// #[repr(C)]
+/// struct Frame {
+///     len: u16,
+///     kind: u16,
+///     crc: u32,
+/// }
+///
+/// let mut frame = MaybeUninit::<Frame>::uninit();
+/// let mut input: &[u8] = &[1, 0, 2, 0, 3, 0, 0, 0];
+/// let mut slices = io_slices_mut!(&mut frame, Frame, [len, kind, crc]);
+/// input.read_vectored(&mut slices).unwrap();
+///
+/// let frame = unsafe { frame.assume_init() };
+/// assert_eq!(frame.crc, u32::from_le_bytes([3, 0, 0, 0]));
+/// ```
+///
+/// ## Panics
+/// Panics if a range is out of bounds, or if the regions are not listed in layout order or
+/// overlap each other, where that is not detected at compile time.
+///
+/// Requires the `std` cargo feature.
+#[macro_export(local_inner_macros)]
+macro_rules! io_slices_mut {
+    ($value:expr, $parent:path, [$($field:tt $([$($range:tt)*])*),+ $(,)*] $(,)*) => {{
+        let value: &mut $crate::__priv::mem::MaybeUninit<$parent> = $value;
+        let (ptr, borrow) = $crate::io::split_borrow(value);
+        let base = ptr as *const $parent;
+        let regions = [$(_memoffset__byte_region!(base, $parent, $field $([$($range)*])*)),+];
+        _memoffset__assert_disjoint_regions!(regions, $parent, $($field $([$($range)*])*),+);
+        let mut regions = regions.iter();
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            [$({
+                _memoffset__ignore!($field);
+                $crate::io::io_slice_mut(ptr, borrow, *$crate::__priv::Iterator::next(&mut regions).unwrap())
+            }),+]
+        }
+    }};
+}
+
+/// Resolves the byte range of a field, panicking if it does not lie within the type.
+fn field_span(layout: &TypeLayout, field: &FieldLayout) -> Range<usize> {
+    match span_at(field.offset, field.size) {
+        Ok(span) => expect_span(span, layout.size),
+        Err(err) => panic!("{}", err),
+    }
+}
+
+fn assert_describes<T>(layout: &TypeLayout) {
+    assert_eq!(
+        layout.size,
+        mem::size_of::<T>(),
+        "the layout does not describe the type"
+    );
+}
+
+/// Writes values field by field to an `io::Write`, in the order the fields are listed in a
+/// `TypeLayout`, skipping any padding.
+///
+/// Each field is written with its own `write_all` call, in native byte order.
+/// Fields of struct type are written as opaque blobs.
+///
+/// ## Examples
+/// ```
+/// use memoffset::io::{FieldReader, FieldWriter};
+/// use memoffset::type_layout;
+///
+/// #[repr(C)]
+/// struct Sample {
+///     id: u8,
+///     value: u32,
+/// }
+///
+/// let layout = type_layout!(Sample { id, value });
+/// let mut writer = FieldWriter::new(layout, Vec::new());
+/// unsafe { writer.write(&Sample { id: 1, value: 2 }) }.unwrap();
+/// let bytes = writer.into_inner();
+/// assert_eq!(bytes.len(), 1 + 4);
+///
+/// let mut reader = FieldReader::new(layout, &bytes[..]);
+/// let sample: Sample = unsafe { reader.read() }.unwrap();
+/// assert_eq!((sample.id, sample.value), (1, 2));
+/// ```
+///
+/// Requires the `std` cargo feature.
+#[derive(Debug)]
+pub struct FieldWriter<'l, W> {
+    layout: TypeLayout<'l>,
+    inner: W,
+}
+
+impl<'l, W: Write> FieldWriter<'l, W> {
+    /// Creates a writer for values of the type described by `layout`.
+    pub fn new(layout: TypeLayout<'l>, inner: W) -> Self {
+        FieldWriter { layout, inner }
+    }
+
+    /// Writes the described fields of `value`.
+    ///
+    /// If an error occurs, some of the fields may already have been written.
+    ///
+    /// ## Safety
+    /// `layout` must describe `T`, and the described fields must not contain padding bytes,
+    /// for example from a nested struct.
+    ///
+    /// ## Panics
+    /// Panics if `layout` has a different size than `T`, or if a field does not lie within it.
+    pub unsafe fn write<T>(&mut self, value: &T) -> io::Result<()> {
+        assert_describes::<T>(&self.layout);
+        let base = value as *const T as *const u8;
+        for field in self.layout.fields {
+            let span = field_span(&self.layout, field);
+            // Only the bytes of the field are borrowed, so padding around it is never read.
+            let bytes = slice::from_raw_parts(base.add(span.start), span.end - span.start);
+            self.inner.write_all(bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwraps the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads values field by field from an `io::Read`, in the order the fields are listed in a
+/// `TypeLayout`.
+///
+/// This is the counterpart of `FieldWriter`. Each field is read with its own `read_exact` call,
+/// straight into a `MaybeUninit` of the value, which is only assumed to be initialized once the
+/// last field has been read.
+///
+/// Requires the `std` cargo feature.
+#[derive(Debug)]
+pub struct FieldReader<'l, R> {
+    layout: TypeLayout<'l>,
+    inner: R,
+}
+
+impl<'l, R: Read> FieldReader<'l, R> {
+    /// Creates a reader for values of the type described by `layout`.
+    pub fn new(layout: TypeLayout<'l>, inner: R) -> Self {
+        FieldReader { layout, inner }
+    }
+
+    /// Reads the described fields into a new value.
+    ///
+    /// If an error occurs, including an `UnexpectedEof` if the input ends in the middle of the
+    /// value, the partially read value is discarded without being dropped.
+    /// Some of its bytes may have been consumed from the underlying reader.
+    ///
+    /// ## Safety
+    /// `layout` must describe `T`, every field of `T` must be described, and the bytes read
+    /// must form a valid value for each field.
+    ///
+    /// ## Panics
+    /// Panics if `layout` has a different size than `T`, or if a field does not lie within it.
+    pub unsafe fn read<T>(&mut self) -> io::Result<T> {
+        assert_describes::<T>(&self.layout);
+        let mut value = mem::MaybeUninit::<T>::uninit();
+        let base = value.as_mut_ptr() as *mut u8;
+        for field in self.layout.fields {
+            let span = field_span(&self.layout, field);
+            let len = span.end - span.start;
+            let dst = base.add(span.start);
+            // `read_exact` needs initialized memory to read into.
+            ptr::write_bytes(dst, 0, len);
+            self.inner.read_exact(slice::from_raw_parts_mut(dst, len))?;
+        }
+        Ok(value.assume_init())
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Write};
+    use std::mem::MaybeUninit;
+    use std::vec::Vec;
+
+    #[repr(C)]
+    struct Packet {
+        hdr: u32,
+        payload: [u16; 8],
+        pad: u16,
+        crc: u32,
+    }
+
+    fn packet() -> Packet {
+        Packet {
+            hdr: 0x0102_0304,
+            payload: [
+                0x1111, 0x2222, 0x3333, 0x4444, 0x5555, 0x6666, 0x7777, 0x8888,
+            ],
+            pad: 0,
+            crc: 0xDEAD_BEEF,
+        }
+    }
+
+    #[test]
+    fn write_fields() {
+        let p = packet();
+        let len = 3;
+        let slices = unsafe { io_slices!(&p, Packet, [hdr, payload[..len], crc]) };
+
+        let mut cursor = Cursor::new(Vec::new());
+        let written = cursor.write_vectored(&slices).unwrap();
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&p.hdr.to_ne_bytes());
+        for x in &p.payload[..len] {
+            expected.extend_from_slice(&x.to_ne_bytes());
+        }
+        expected.extend_from_slice(&p.crc.to_ne_bytes());
+        assert_eq!(written, expected.len());
+        assert_eq!(cursor.into_inner(), expected);
+    }
+
+    #[test]
+    fn write_sub_ranges() {
+        let p = packet();
+        let slices = unsafe { io_slices!(&p, Packet, [payload[1..=2], payload[6..], crc,]) };
+
+        let lens: Vec<usize> = slices.iter().map(|s| s.len()).collect();
+        assert_eq!(lens, [4, 4, 4]);
+        assert_eq!(&slices[1][..], &[0x77, 0x77, 0x88, 0x88][..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_bounds_range() {
+        let p = packet();
+        let end = 9;
+        let _ = unsafe { io_slices!(&p, Packet, [payload[4..end]]) };
+    }
+
+    #[test]
+    #[should_panic(expected = "overflows `usize`")]
+    fn overflowing_range() {
+        let p = packet();
+        let end = !0;
+        let _ = unsafe { io_slices!(&p, Packet, [payload[4..=end]]) };
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_order() {
+        let p = packet();
+        let len = 2;
+        let _ = unsafe { io_slices!(&p, Packet, [crc, payload[..len]]) };
+    }
+
+    #[test]
+    #[should_panic]
+    fn overlapping() {
+        let mut p = MaybeUninit::<Packet>::uninit();
+        let start = 3;
+        let _ = io_slices_mut!(&mut p, Packet, [payload[..4], payload[start..]]);
+    }
+
+    #[cfg(inline_const)]
+    #[test]
+    fn literal_ranges() {
+        let p = packet();
+        let slices = unsafe {
+            io_slices!(
+                &p,
+                Packet,
+                [
+                    hdr,
+                    payload[..1],
+                    payload[1..=2],
+                    payload[3..3],
+                    payload[3..],
+                    crc
+                ]
+            )
+        };
+        let lens: Vec<usize> = slices.iter().map(|s| s.len()).collect();
+        assert_eq!(lens, [4, 2, 4, 0, 10, 4]);
+
+        let mut p = MaybeUninit::<Packet>::uninit();
+        let slices = io_slices_mut!(&mut p, Packet, [payload[..], crc]);
+        assert_eq!(slices[0].len(), 16);
+    }
+
+    #[test]
+    fn field_round_trip() {
+        use super::{FieldReader, FieldWriter};
+
+        let layout = type_layout!(Packet {
+            hdr,
+            payload,
+            pad,
+            crc
+        });
+        let mut writer = FieldWriter::new(layout, Cursor::new(Vec::new()));
+        unsafe {
+            writer.write(&packet()).unwrap();
+            writer.write(&packet()).unwrap();
+        }
+        let bytes = writer.into_inner().into_inner();
+        // The padding after `pad` is skipped.
+        assert_eq!(bytes.len(), 2 * (4 + 16 + 2 + 4));
+
+        let mut reader = FieldReader::new(layout, Cursor::new(bytes));
+        for _ in 0..2 {
+            let read: Packet = unsafe { reader.read() }.unwrap();
+            assert_eq!(read.hdr, 0x0102_0304);
+            assert_eq!(read.payload, packet().payload);
+            assert_eq!(read.crc, 0xDEAD_BEEF);
+        }
+        let eof: std::io::Result<Packet> = unsafe { reader.read() };
+        assert!(eof.is_err());
+    }
+
+    #[test]
+    fn write_padded() {
+        use super::FieldWriter;
+
+        #[repr(C)]
+        struct Padded {
+            a: u8,
+            b: u32,
+            c: u16,
+        }
+
+        // The padding after `a` and `c` is uninitialized, which Miri flags if it is ever read.
+        let value = Padded { a: 1, b: 2, c: 3 };
+        let layout = type_layout!(Padded { a, b, c });
+        let mut writer = FieldWriter::new(layout, Vec::new());
+        unsafe { writer.write(&value) }.unwrap();
+
+        let mut expected = std::vec![1];
+        expected.extend_from_slice(&2u32.to_ne_bytes());
+        expected.extend_from_slice(&3u16.to_ne_bytes());
+        assert_eq!(writer.into_inner(), expected);
+
+        // A layout may list only some of the fields, in any order.
+        let layout = type_layout!(Padded { c, a });
+        let mut writer = FieldWriter::new(layout, Vec::new());
+        unsafe { writer.write(&value) }.unwrap();
+        let mut expected = 3u16.to_ne_bytes().to_vec();
+        expected.push(1);
+        assert_eq!(writer.into_inner(), expected);
+    }
+
+    #[test]
+    fn short_read() {
+        use super::FieldReader;
+        use std::io::ErrorKind;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Guard;
+
+        impl Drop for Guard {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        #[repr(C)]
+        struct Record {
+            a: u32,
+            guard: Guard,
+            b: u64,
+        }
+
+        // `a` is complete, but the input ends in the middle of `b`.
+        let bytes = [1u8; 4 + 5];
+        let layout = type_layout!(Record { a, guard, b });
+        let mut reader = FieldReader::new(layout, &bytes[..]);
+        let read: std::io::Result<Record> = unsafe { reader.read() };
+        assert_eq!(read.err().unwrap().kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn read_fields() {
+        let p = packet();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&p.hdr.to_ne_bytes());
+        for x in &p.payload {
+            bytes.extend_from_slice(&x.to_ne_bytes());
+        }
+
+        let mut read = MaybeUninit::<Packet>::uninit();
+        {
+            let mut slices = io_slices_mut!(&mut read, Packet, [hdr, payload]);
+            let n = Cursor::new(bytes).read_vectored(&mut slices).unwrap();
+            assert_eq!(n, 4 + 16);
+        }
+        let base = read.as_mut_ptr();
+        let crc = raw_field!(base, Packet, crc) as *mut u32;
+        let pad = raw_field!(base, Packet, pad) as *mut u16;
+        unsafe {
+            crc.write(p.crc);
+            pad.write(0);
+        }
+        let read = unsafe { read.assume_init() };
+        assert_eq!(read.hdr, p.hdr);
+        assert_eq!(read.payload, p.payload);
+        assert_eq!(read.crc, p.crc);
+    }
+}
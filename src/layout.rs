@@ -0,0 +1,1242 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Plain-data descriptions of the layout of a type, and ways to export them.
+//!
+//! ## Field descriptors for FFI
+//! There is no derive in this crate, so it can not attach a table of fields to a struct, or
+//! tell which FFI type (an integer, a pointer, an array or a nested struct) a field has:
+//! `macro_rules!` macros only see the field names they are given, not the declaration of the
+//! struct.
+//! An element list for a runtime call interface, such as one built with libffi, can instead be
+//! put together from `type_layout!`, which lists the offset, size and type name of each
+//! named field:
+//!
+//! ```
+//! use memoffset::type_layout;
+//!
+//! #[repr(C)]
+//! struct Sample {
+//!     id: u32,
+//!     data: *const u8,
+//!     len: u16,
+//! }
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Tag {
+//!     U16,
+//!     U32,
+//!     Pointer,
+//! }
+//!
+//! let layout = type_layout!(Sample { id, data, len });
+//! let tags: Vec<(usize, Tag)> = layout
+//!     .fields
+//!     .iter()
+//!     .map(|field| match field.name {
+//!         "data" => (field.offset, Tag::Pointer),
+//!         _ if field.size == 2 => (field.offset, Tag::U16),
+//!         _ => (field.offset, Tag::U32),
+//!     })
+//!     .collect();
+//! assert_eq!(tags[1], (layout.fields[1].offset, Tag::Pointer));
+//! assert_eq!(tags[2].1, Tag::U16);
+//! ```
+
+use core::{fmt, mem, slice};
+use span::expect_span;
+
+#[cfg(feature = "std")]
+use std::fs::{self, File};
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+#[cfg(feature = "std")]
+use std::path::Path;
+#[cfg(feature = "std")]
+use std::string::{String, ToString};
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+/// The layout of a single field of a type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldLayout {
+    /// The name of the field, as written in the source.
+    pub name: &'static str,
+    /// The offset of the field from the start of the type.
+    pub offset: usize,
+    /// The size of the field.
+    pub size: usize,
+    /// The name of the type of the field, as given by `core::any::type_name`.
+    ///
+    /// The exact text is not guaranteed to be stable across compiler versions.
+    /// It is empty on rustc older than 1.38.
+    pub type_name: &'static str,
+}
+
+/// The layout of a type: its size, its alignment, and a selection of its fields.
+///
+/// Values of this type are usually built with `type_layout!`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TypeLayout<'a> {
+    /// The name of the type, as written in the source.
+    pub name: &'static str,
+    /// The size of the type.
+    pub size: usize,
+    /// The alignment of the type.
+    pub align: usize,
+    /// The described fields, in the order they were listed.
+    pub fields: &'a [FieldLayout],
+}
+
+impl<'a> TypeLayout<'a> {
+    /// Checks whether two layouts have the same size, alignment and fields, where fields are
+    /// compared by their name, offset and size.
+    ///
+    /// Unlike `==`, this ignores the names of the types themselves and of the types of their
+    /// fields, so it can compare a type against a mirror of it, e.g. one generated for FFI.
+    ///
+    /// ## Examples
+    /// ```
+    /// use memoffset::type_layout;
+    ///
+    /// #[repr(C)]
+    /// struct Int {
+    ///     tag: u8,
+    ///     value: u32,
+    /// }
+    ///
+    /// #[repr(C)]
+    /// struct Float {
+    ///     tag: u8,
+    ///     value: f32,
+    /// }
+    ///
+    /// let int = type_layout!(Int { tag, value });
+    /// let float = type_layout!(Float { tag, value });
+    /// assert!(int.same_layout(&float));
+    /// assert!(!int.same_typed_layout(&float));
+    /// ```
+    pub fn same_layout(&self, other: &TypeLayout) -> bool {
+        self.size == other.size
+            && self.align == other.align
+            && self.fields.len() == other.fields.len()
+            && self
+                .fields
+                .iter()
+                .zip(other.fields)
+                .all(|(a, b)| a.name == b.name && a.offset == b.offset && a.size == b.size)
+    }
+
+    /// Checks whether two layouts are the same as per `same_layout`, and whether their
+    /// corresponding fields also have the same type name.
+    ///
+    /// Type names are compared as text, so the same type reached through different paths
+    /// compares equal, but types that are only structurally the same do not.
+    /// On rustc older than 1.38, type names are not available and this is the same as
+    /// `same_layout`.
+    pub fn same_typed_layout(&self, other: &TypeLayout) -> bool {
+        self.same_layout(other)
+            && self
+                .fields
+                .iter()
+                .zip(other.fields)
+                .all(|(a, b)| a.type_name == b.type_name)
+    }
+}
+
+/// Writes the layout in a compact text form, one field per line:
+///
+/// ```text
+/// Foo: size 8, align 4
+///   a @ 0..4
+///   b @ 4..6
+/// ```
+impl<'a> fmt::Display for TypeLayout<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: size {}, align {}", self.name, self.size, self.align)?;
+        for field in self.fields {
+            write!(
+                f,
+                "\n  {} @ {}..{}",
+                field.name,
+                field.offset,
+                field.offset + field.size
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats the field as `name @ start..end`.
+#[cfg(feature = "defmt")]
+impl defmt::Format for FieldLayout {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "{=str} @ {=usize}..{=usize}",
+            self.name,
+            self.offset,
+            self.offset + self.size
+        );
+    }
+}
+
+/// Formats the layout on a single line, as `Foo: size 8, align 4 { a @ 0..4, b @ 4..6 }`.
+#[cfg(feature = "defmt")]
+impl<'a> defmt::Format for TypeLayout<'a> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "{=str}: size {=usize}, align {=usize}",
+            self.name,
+            self.size,
+            self.align
+        );
+        for (i, field) in self.fields.iter().enumerate() {
+            let separator = if i == 0 { " { " } else { ", " };
+            defmt::write!(f, "{=str}{}", separator, field);
+        }
+        if !self.fields.is_empty() {
+            defmt::write!(f, " }}");
+        }
+    }
+}
+
+/// Builds a `TypeLayout` describing the given fields of a struct.
+///
+/// ## Examples
+/// ```
+/// use memoffset::type_layout;
+///
+/// #[repr(C)]
+/// struct Foo {
+///     a: u32,
+///     b: u16,
+/// }
+///
+/// let layout = type_layout!(Foo { a, b });
+/// assert_eq!(layout.name, "Foo");
+/// assert_eq!(layout.size, 8);
+/// assert_eq!(layout.fields[1].name, "b");
+/// assert_eq!(layout.fields[1].offset, 4);
+/// assert_eq!(layout.fields[1].size, 2);
+/// assert_eq!(layout.fields[1].type_name, "u16");
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! type_layout {
+    ($parent:path { $($field:tt),* $(,)* } $(,)*) => {
+        $crate::layout::TypeLayout {
+            name: _memoffset__stringify!($parent),
+            size: $crate::__priv::mem::size_of::<$parent>(),
+            align: $crate::__priv::mem::align_of::<$parent>(),
+            fields: &[$(
+                $crate::layout::FieldLayout {
+                    name: _memoffset__stringify!($field),
+                    offset: offset_of!($parent, $field),
+                    size: _memoffset__field_size!($parent, $field),
+                    type_name: _memoffset__field_type_name!($parent, $field),
+                }
+            ),*],
+        }
+    };
+}
+
+/// Macro to obtain the type name of a field, without requiring a value of the parent type.
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__field_type_name {
+    ($parent:path, $field:tt) => {{
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        $crate::__priv::type_name_of_pointee(raw_field!(base_ptr, $parent, $field))
+    }};
+}
+
+/// Implements lookup of the offsets and spans of the given fields of a struct by their names,
+/// for debuggers and other tools that only know which field they want at runtime.
+///
+/// This adds two associated functions to the struct:
+/// - `field_offset(name: &str) -> Option<usize>`,
+/// - `field_span(name: &str) -> Option<Range<usize>>`.
+///
+/// Both return `None` for a name that is not listed.
+/// On rustc 1.82 and newer, nested fields can be listed too, and are looked up by their dotted
+/// path, such as `"header.len"`.
+/// The lookup is a `match` on the name, so it does not allocate.
+///
+/// ## Examples
+/// ```
+/// use memoffset::field_lookup;
+///
+/// #[repr(C)]
+/// struct Frame {
+///     len: u16,
+///     kind: u16,
+///     checksum: u32,
+/// }
+///
+/// field_lookup!(Frame { len, kind, checksum });
+///
+/// assert_eq!(Frame::field_offset("checksum"), Some(4));
+/// assert_eq!(Frame::field_span("kind"), Some(2..4));
+/// assert_eq!(Frame::field_offset("crc"), None);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! field_lookup {
+    ($parent:path { $($($field:tt).+),* $(,)* } $(,)*) => {
+        #[allow(dead_code)]
+        impl $parent {
+            /// Returns the offset of the field with the given name, or `None` if there is no
+            /// such field.
+            pub fn field_offset(name: &str) -> $crate::__priv::Option<usize> {
+                match name {
+                    $(_memoffset__field_name!($($field).+) => {
+                        $crate::__priv::Option::Some(offset_of!($parent, $($field).+))
+                    })*
+                    _ => $crate::__priv::Option::None,
+                }
+            }
+
+            /// Returns the byte range of the field with the given name, or `None` if there is
+            /// no such field.
+            pub fn field_span(name: &str) -> $crate::__priv::Option<$crate::__priv::ops::Range<usize>> {
+                match name {
+                    $(_memoffset__field_name!($($field).+) => {
+                        $crate::__priv::Option::Some(span_of!($parent, $($field).+))
+                    })*
+                    _ => $crate::__priv::Option::None,
+                }
+            }
+        }
+    };
+}
+
+/// Macro to spell a possibly nested field as a string literal, with the segments separated by
+/// dots.
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__field_name {
+    ($first:tt $(. $rest:tt)*) => {
+        _memoffset__concat!(_memoffset__stringify!($first) $(, ".", _memoffset__stringify!($rest))*)
+    };
+}
+
+/// The name that `dissect_with_padding` gives to padding entries.
+pub const PADDING: &str = "<padding>";
+
+/// An iterator over the bytes of each field of a value, as described by a `TypeLayout`.
+///
+/// Created by `dissect` and `dissect_with_padding`.
+#[derive(Debug, Clone)]
+pub struct Dissect<'l, 'b> {
+    fields: slice::Iter<'l, FieldLayout>,
+    buf: &'b [u8],
+    pos: usize,
+    padding: bool,
+}
+
+impl<'l, 'b> Iterator for Dissect<'l, 'b> {
+    type Item = (&'static str, &'b [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let field = match self.fields.as_slice().first() {
+            Some(field) => field,
+            None => {
+                // Trailing padding, if any.
+                if self.padding && self.pos < self.buf.len() {
+                    let pos = self.pos;
+                    self.pos = self.buf.len();
+                    return Some((PADDING, &self.buf[pos..]));
+                }
+                return None;
+            }
+        };
+
+        if self.padding && self.pos < field.offset {
+            let pos = self.pos;
+            self.pos = field.offset;
+            return Some((PADDING, &self.buf[pos..field.offset]));
+        }
+
+        self.fields.next();
+        let end = field.offset + field.size;
+        if end > self.pos {
+            self.pos = end;
+        }
+        Some((field.name, &self.buf[field.offset..end]))
+    }
+}
+
+/// Walks a byte buffer holding a value of the type described by `layout`, producing the name
+/// and the bytes of each described field, in the order they are listed.
+///
+/// ## Examples
+/// ```
+/// use memoffset::layout::dissect;
+/// use memoffset::type_layout;
+///
+/// #[repr(C)]
+/// struct Packet {
+///     kind: u8,
+///     len: u8,
+///     seq: u16,
+/// }
+///
+/// let buf = [1, 2, 3, 4];
+/// let fields: Vec<_> = dissect(&type_layout!(Packet { kind, seq }), &buf).collect();
+/// assert_eq!(fields, [("kind", &[1][..]), ("seq", &[3, 4][..])]);
+/// ```
+///
+/// ## Panics
+/// Panics if the buffer is shorter than the type, or if a field does not lie within the type.
+pub fn dissect<'l, 'b>(layout: &TypeLayout<'l>, buf: &'b [u8]) -> Dissect<'l, 'b> {
+    let buf = &buf[expect_span(0..layout.size, buf.len())];
+    for field in layout.fields {
+        assert!(
+            field.offset <= layout.size && field.size <= layout.size - field.offset,
+            "field `{}` does not lie within `{}`",
+            field.name,
+            layout.name
+        );
+    }
+    Dissect {
+        fields: layout.fields.iter(),
+        buf,
+        pos: 0,
+        padding: false,
+    }
+}
+
+/// Like `dissect`, but also produces an entry named `PADDING` for every run of bytes before,
+/// between or after the described fields that is not part of any of them.
+///
+/// If the fields are listed in layout order, concatenating all entries gives back the bytes of
+/// the value.
+///
+/// ## Panics
+/// Panics if the buffer is shorter than the type, or if a field does not lie within the type.
+pub fn dissect_with_padding<'l, 'b>(layout: &TypeLayout<'l>, buf: &'b [u8]) -> Dissect<'l, 'b> {
+    Dissect {
+        padding: true,
+        ..dissect(layout, buf)
+    }
+}
+
+/// A set of fields of a `TypeLayout`, by their index in `TypeLayout::fields`.
+///
+/// Only the first 64 fields can be part of a mask.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FieldMask(u64);
+
+impl FieldMask {
+    /// Creates an empty mask.
+    pub fn new() -> FieldMask {
+        FieldMask(0)
+    }
+
+    /// Adds the field at `index` to the mask.
+    ///
+    /// ## Panics
+    /// Panics if `index` is 64 or more.
+    pub fn insert(&mut self, index: usize) {
+        assert!(index < 64, "a FieldMask holds at most 64 fields");
+        self.0 |= 1 << index;
+    }
+
+    /// Checks whether the field at `index` is part of the mask.
+    pub fn contains(&self, index: usize) -> bool {
+        index < 64 && self.0 & (1 << index) != 0
+    }
+
+    /// Checks whether the mask is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// The number of fields in the mask.
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Iterates over the indices in the mask, in increasing order.
+    pub fn iter(&self) -> FieldMaskIter {
+        FieldMaskIter(self.0)
+    }
+
+    /// Iterates over the fields of `layout` that are in the mask, in increasing order.
+    pub fn fields<'l>(&self, layout: &TypeLayout<'l>) -> MaskedFields<'l> {
+        MaskedFields {
+            fields: layout.fields,
+            indices: self.iter(),
+        }
+    }
+}
+
+/// Formats the mask as the hexadecimal bit set of field indices.
+#[cfg(feature = "defmt")]
+impl defmt::Format for FieldMask {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "FieldMask({=u64:#x})", self.0);
+    }
+}
+
+/// An iterator over the indices in a `FieldMask`.
+#[derive(Debug, Clone)]
+pub struct FieldMaskIter(u64);
+
+impl Iterator for FieldMaskIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let index = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(index)
+    }
+}
+
+/// An iterator over the fields of a `TypeLayout` that are in a `FieldMask`.
+#[derive(Debug, Clone)]
+pub struct MaskedFields<'l> {
+    fields: &'l [FieldLayout],
+    indices: FieldMaskIter,
+}
+
+impl<'l> Iterator for MaskedFields<'l> {
+    type Item = &'l FieldLayout;
+
+    fn next(&mut self) -> Option<&'l FieldLayout> {
+        let fields = self.fields;
+        self.indices.by_ref().filter_map(|i| fields.get(i)).next()
+    }
+}
+
+/// The bytes of `field` in `value`, read through a raw pointer so that no other bytes of the
+/// value, such as padding, are ever looked at.
+///
+/// Panics if the field does not lie within `T`.
+unsafe fn field_bytes<'a, T>(value: &'a T, field: &FieldLayout) -> &'a [u8] {
+    let span = expect_span(field.offset..field.offset + field.size, mem::size_of::<T>());
+    let base = value as *const T as *const u8;
+    slice::from_raw_parts(base.add(span.start), span.end - span.start)
+}
+
+/// Compares the bytes of every described field of two values, and returns the mask of the
+/// fields that differ.
+///
+/// Only the bytes of the described fields are read, so padding between them is skipped.
+///
+/// ## Examples
+/// ```
+/// use memoffset::layout::changed_fields;
+/// use memoffset::type_layout;
+///
+/// #[repr(C)]
+/// struct Player {
+///     id: u32,
+///     x: f32,
+///     y: f32,
+/// }
+///
+/// let layout = type_layout!(Player { id, x, y });
+/// let old = Player { id: 1, x: 0.0, y: 0.0 };
+/// let new = Player { id: 1, x: 0.5, y: 0.0 };
+///
+/// let changed = unsafe { changed_fields(&layout, &old, &new) };
+/// assert_eq!(changed.fields(&layout).map(|f| f.name).collect::<Vec<_>>(), ["x"]);
+/// ```
+///
+/// ## Safety
+/// `layout` must describe `T`, and the described fields must not contain padding bytes,
+/// for example from a nested struct.
+///
+/// ## Panics
+/// Panics if `layout` has a different size than `T`, if a field does not lie within it, or if
+/// it has more than 64 fields.
+pub unsafe fn changed_fields<T>(layout: &TypeLayout, old: &T, new: &T) -> FieldMask {
+    assert_eq!(
+        layout.size,
+        mem::size_of::<T>(),
+        "the layout does not describe the type"
+    );
+
+    let mut mask = FieldMask::new();
+    for (i, field) in layout.fields.iter().enumerate() {
+        if field_bytes(old, field) != field_bytes(new, field) {
+            mask.insert(i);
+        }
+    }
+    mask
+}
+
+/// Copies the bytes of the fields in `mask` from `value` into `out`, back to back, in the
+/// order of their indices, and returns the number of bytes written.
+///
+/// Together with `changed_fields`, this produces the payload of a delta update.
+///
+/// ## Safety
+/// `layout` must describe `T`, and the fields in `mask` must not contain padding bytes.
+///
+/// ## Panics
+/// Panics if `layout` has a different size than `T`, if a field does not lie within it, or if
+/// `out` is too short.
+pub unsafe fn write_fields<T>(
+    layout: &TypeLayout,
+    mask: FieldMask,
+    value: &T,
+    out: &mut [u8],
+) -> usize {
+    assert_eq!(
+        layout.size,
+        mem::size_of::<T>(),
+        "the layout does not describe the type"
+    );
+
+    let mut pos = 0;
+    for field in mask.fields(layout) {
+        out[pos..pos + field.size].copy_from_slice(field_bytes(value, field));
+        pos += field.size;
+    }
+    pos
+}
+
+/// Writes a `TypeLayout` as a JSON object.
+///
+/// The schema is stable, and looks like this (whitespace aside):
+///
+/// ```json
+/// {
+///   "name": "Foo",
+///   "size": 8,
+///   "align": 4,
+///   "fields": [
+///     { "name": "a", "offset": 0, "size": 4, "type": "u32" },
+///     { "name": "b", "offset": 4, "size": 2, "type": "u16" }
+///   ]
+/// }
+/// ```
+///
+/// `name` and `type` are strings, all other values are non-negative integers, and `fields` is
+/// in the order the fields were listed when building the layout.
+/// `type` is the `type_name` of the field, so it is empty on rustc older than 1.38.
+///
+/// Requires the `std` cargo feature.
+#[cfg(feature = "std")]
+pub fn layout_to_json<W: Write>(layout: &TypeLayout, out: &mut W) -> io::Result<()> {
+    write!(out, "{{\n  \"name\": ")?;
+    write_json_string(layout.name, out)?;
+    write!(
+        out,
+        ",\n  \"size\": {},\n  \"align\": {},\n  \"fields\": [",
+        layout.size, layout.align
+    )?;
+    for (i, field) in layout.fields.iter().enumerate() {
+        if i > 0 {
+            write!(out, ",")?;
+        }
+        write!(out, "\n    {{ \"name\": ")?;
+        write_json_string(field.name, out)?;
+        write!(
+            out,
+            ", \"offset\": {}, \"size\": {}, \"type\": ",
+            field.offset, field.size
+        )?;
+        write_json_string(field.type_name, out)?;
+        write!(out, " }}")?;
+    }
+    if !layout.fields.is_empty() {
+        write!(out, "\n  ")?;
+    }
+    writeln!(out, "]\n}}")
+}
+
+/// Writes each layout as JSON into its own file in `dir`, named after the type.
+///
+/// Characters that do not belong in a file name, such as the `::` of a path, are replaced
+/// with `_`.
+/// If two layouts would end up in the same file, as `a::Foo` and `a__Foo` would, or the same
+/// name is listed twice, nothing is written and an error of kind `InvalidInput` is returned.
+///
+/// Requires the `std` cargo feature.
+#[cfg(feature = "std")]
+pub fn write_layouts_json<P: AsRef<Path>>(dir: P, layouts: &[TypeLayout]) -> io::Result<()> {
+    let files: Vec<String> = layouts
+        .iter()
+        .map(|layout| {
+            let name: String = layout
+                .name
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            name + ".json"
+        })
+        .collect();
+    for (i, file) in files.iter().enumerate() {
+        if let Some(j) = files[..i].iter().position(|other| other == file) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                std::format!(
+                    "the layouts of `{}` and `{}` would both be written to `{}`",
+                    layouts[j].name,
+                    layouts[i].name,
+                    file
+                ),
+            ));
+        }
+    }
+
+    for (layout, file) in layouts.iter().zip(files) {
+        let mut file = File::create(dir.as_ref().join(file))?;
+        layout_to_json(layout, &mut file)?;
+    }
+    Ok(())
+}
+
+/// The environment variable that makes `assert_snapshot` write snapshots instead of checking
+/// them.
+#[cfg(feature = "std")]
+pub const UPDATE_SNAPSHOTS_VAR: &str = "MEMOFFSET_UPDATE_SNAPSHOTS";
+
+/// Checks a layout against a snapshot file holding its compact text form, as written by the
+/// `Display` implementation of `TypeLayout`.
+///
+/// If the environment variable named by `UPDATE_SNAPSHOTS_VAR` is set to anything but an empty
+/// string or `0`, the snapshot is written instead, creating any missing directories.
+/// Checked-in snapshots turn layout changes into reviewable diffs.
+///
+/// Usually called through `layout_snapshot!`.
+///
+/// ## Panics
+/// Panics with a line-by-line diff if the snapshot is missing or does not match, and if the
+/// snapshot cannot be read or written.
+///
+/// Requires the `std` cargo feature.
+#[cfg(feature = "std")]
+pub fn assert_snapshot<P: AsRef<Path>>(layout: &TypeLayout, path: P) {
+    let path = path.as_ref();
+    let actual = layout.to_string() + "\n";
+
+    let update = std::env::var(UPDATE_SNAPSHOTS_VAR)
+        .map(|value| !value.is_empty() && value != "0")
+        .unwrap_or(false);
+    if update {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).expect("failed to create the snapshot directory");
+        }
+        fs::write(path, &actual).expect("failed to write the snapshot");
+        return;
+    }
+
+    let expected = match fs::read_to_string(path) {
+        Ok(expected) => expected.replace("\r\n", "\n"),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => panic!("failed to read the snapshot {}: {}", path.display(), err),
+    };
+    if expected != actual {
+        panic!(
+            "the layout of `{}` does not match the snapshot {}:\n{}\
+             set {}=1 to update the snapshot",
+            layout.name,
+            path.display(),
+            line_diff(&expected, &actual),
+            UPDATE_SNAPSHOTS_VAR
+        );
+    }
+}
+
+/// Compares two texts line by line, marking removed lines with `-` and added lines with `+`.
+#[cfg(feature = "std")]
+fn line_diff(old: &str, new: &str) -> String {
+    let mut old = old.lines();
+    let mut new = new.lines();
+    let mut diff = String::new();
+    loop {
+        match (old.next(), new.next()) {
+            (None, None) => return diff,
+            (Some(a), Some(b)) if a == b => diff = diff + "  " + a + "\n",
+            (a, b) => {
+                if let Some(a) = a {
+                    diff = diff + "- " + a + "\n";
+                }
+                if let Some(b) = b {
+                    diff = diff + "+ " + b + "\n";
+                }
+            }
+        }
+    }
+}
+
+/// Checks the layout of the given fields of a struct against a checked-in snapshot file.
+///
+/// This is meant to be used in a `#[test]`: the test fails with a readable diff when the layout
+/// changes, and running it with `MEMOFFSET_UPDATE_SNAPSHOTS=1` writes the new snapshot.
+/// A relative path is resolved against the current directory, which is the package root
+/// under `cargo test`.
+///
+/// ## Examples
+/// ```no_run
+/// use memoffset::layout_snapshot;
+///
+/// #[repr(C)]
+/// struct Header {
+///     magic: u32,
+///     len: u16,
+/// }
+///
+/// layout_snapshot!(Header { magic, len }, "tests/layouts/Header.layout");
+/// ```
+///
+/// With the snapshot file holding:
+///
+/// ```text
+/// Header: size 8, align 4
+///   magic @ 0..4
+///   len @ 4..6
+/// ```
+///
+/// Requires the `std` cargo feature.
+#[cfg(feature = "std")]
+#[macro_export(local_inner_macros)]
+macro_rules! layout_snapshot {
+    ($parent:path { $($field:tt),* $(,)* }, $path:expr $(,)*) => {
+        $crate::layout::assert_snapshot(&type_layout!($parent { $($field),* }), $path)
+    };
+}
+
+#[cfg(feature = "std")]
+fn write_json_string<W: Write>(s: &str, out: &mut W) -> io::Result<()> {
+    write!(out, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    write!(out, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    #[repr(C)]
+    struct Inner {
+        x: u8,
+        y: u32,
+    }
+
+    #[repr(C)]
+    struct Outer {
+        tag: u16,
+        inner: Inner,
+        tail: [u8; 3],
+    }
+
+    #[test]
+    #[cfg(type_name)]
+    fn type_names() {
+        let layout = type_layout!(Outer { tag, inner, tail });
+        assert_eq!(layout.fields[0].type_name, "u16");
+        assert!(layout.fields[1].type_name.ends_with("Inner"));
+        assert_eq!(layout.fields[2].type_name, "[u8; 3]");
+    }
+
+    #[repr(C)]
+    struct Pair(u32, Inner);
+
+    field_lookup!(Outer { tag, inner, tail });
+    field_lookup!(Pair { 0, 1 });
+    #[cfg(nested_offset_of)]
+    field_lookup!(Inner { x, y });
+
+    #[test]
+    fn lookup() {
+        assert_eq!(Outer::field_offset("tag"), Some(0));
+        assert_eq!(Outer::field_offset("inner"), Some(4));
+        assert_eq!(Outer::field_span("tail"), Some(12..15));
+        assert_eq!(Pair::field_span("1"), Some(4..12));
+    }
+
+    #[test]
+    fn lookup_unknown_names() {
+        assert_eq!(Outer::field_offset("Tag"), None);
+        assert_eq!(Outer::field_offset(""), None);
+        assert_eq!(Outer::field_span("inner.x"), None);
+        assert_eq!(Pair::field_offset("2"), None);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn lookup_nested() {
+        #[repr(C)]
+        struct Frame {
+            id: u64,
+            outer: Outer,
+        }
+
+        field_lookup!(Frame { id, outer.tag, outer.inner.y, outer.tail });
+
+        assert_eq!(Frame::field_offset("outer.tag"), Some(8));
+        assert_eq!(Frame::field_span("outer.inner.y"), Some(16..20));
+        assert_eq!(Frame::field_offset("outer"), None);
+        assert_eq!(Frame::field_offset("outer.inner"), None);
+        assert_eq!(Inner::field_offset("y"), Some(4));
+    }
+
+    #[test]
+    fn same_layout() {
+        #[repr(C)]
+        struct Mirror {
+            x: i8,
+            y: f32,
+        }
+
+        #[repr(C)]
+        struct Renamed {
+            x: u8,
+            z: u32,
+        }
+
+        let inner = type_layout!(Inner { x, y });
+        let mirror = type_layout!(Mirror { x, y });
+        assert!(inner != mirror);
+        assert!(inner.same_layout(&mirror));
+        assert!(!inner.same_layout(&type_layout!(Renamed { x, z })));
+        assert!(!inner.same_layout(&type_layout!(Inner { x })));
+
+        assert!(inner.same_typed_layout(&type_layout!(Inner { x, y })));
+        assert_eq!(inner.same_typed_layout(&mirror), cfg!(not(type_name)));
+    }
+
+    #[test]
+    fn dissect() {
+        use super::{dissect, dissect_with_padding, PADDING};
+
+        let mut buf = [0u8; 20];
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        let layout = type_layout!(Outer { tag, inner, tail });
+        let mut fields = dissect(&layout, &buf);
+        assert_eq!(fields.next(), Some(("tag", &buf[0..2])));
+        assert_eq!(fields.next(), Some(("inner", &buf[4..12])));
+        assert_eq!(fields.next(), Some(("tail", &buf[12..15])));
+        assert_eq!(fields.next(), None);
+
+        let mut reassembled = [0xFFu8; 16];
+        let mut pos = 0;
+        let mut padding = 0;
+        for (name, bytes) in dissect_with_padding(&layout, &buf) {
+            if name == PADDING {
+                padding += bytes.len();
+            }
+            reassembled[pos..pos + bytes.len()].copy_from_slice(bytes);
+            pos += bytes.len();
+        }
+        assert_eq!(pos, 16);
+        assert_eq!(padding, 2 + 1);
+        assert_eq!(reassembled, buf[..16]);
+    }
+
+    #[test]
+    fn dissect_selected_fields() {
+        use super::{dissect_with_padding, PADDING};
+
+        let buf = [1, 2, 3, 4, 5, 6, 7, 8];
+        let layout = type_layout!(Inner { y });
+        let mut fields = dissect_with_padding(&layout, &buf);
+        assert_eq!(fields.next(), Some((PADDING, &buf[0..4])));
+        assert_eq!(fields.next(), Some(("y", &buf[4..8])));
+        assert_eq!(fields.next(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn dissect_short_buffer() {
+        let _ = super::dissect(&type_layout!(Inner { x, y }), &[0; 7]);
+    }
+
+    #[test]
+    fn delta() {
+        use super::{changed_fields, write_fields, FieldMask};
+
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        struct Snapshot {
+            id: u32,
+            health: u8,
+            pos: [u16; 3],
+            score: u64,
+        }
+
+        let layout = type_layout!(Snapshot {
+            id,
+            health,
+            pos,
+            score
+        });
+        let old = Snapshot {
+            id: 7,
+            health: 100,
+            pos: [1, 2, 3],
+            score: 1000,
+        };
+        let mut new = old;
+        new.health = 90;
+        new.score = 0x0102_0304_0506_0708;
+
+        let changed = unsafe { changed_fields(&layout, &old, &new) };
+        assert_eq!(changed.len(), 2);
+        assert!(changed.contains(1) && changed.contains(3));
+        let mut indices = changed.iter();
+        assert_eq!(indices.next(), Some(1));
+        assert_eq!(indices.next(), Some(3));
+        assert_eq!(indices.next(), None);
+        let mut fields = changed.fields(&layout);
+        assert_eq!(fields.next().map(|f| f.name), Some("health"));
+        assert_eq!(fields.next().map(|f| f.name), Some("score"));
+        assert_eq!(fields.next(), None);
+
+        let mut out = [0xFFu8; 16];
+        let written = unsafe { write_fields(&layout, changed, &new, &mut out) };
+        assert_eq!(written, 1 + 8);
+        assert_eq!(out[0], 90);
+        assert_eq!(out[1..9], new.score.to_ne_bytes());
+        assert_eq!(out[9..], [0xFF; 7]);
+
+        assert!(unsafe { changed_fields(&layout, &old, &old) }.is_empty());
+        assert_eq!(FieldMask::new().iter().next(), None);
+    }
+
+    #[test]
+    fn delta_skips_padding() {
+        use super::{changed_fields, write_fields};
+        use core::mem::MaybeUninit;
+        use core::ptr;
+
+        #[repr(C)]
+        struct Padded {
+            a: u8,
+            b: u32,
+            c: u16,
+        }
+
+        let layout = type_layout!(Padded { a, b, c });
+        // The padding of `old` is left uninitialized, which Miri flags if it is ever read.
+        let old = Padded { a: 1, b: 2, c: 3 };
+        // The padding of `new` is initialized, and differs from anything `old` could hold there.
+        let new = unsafe {
+            let mut new = MaybeUninit::<Padded>::uninit();
+            ptr::write_bytes(new.as_mut_ptr(), 0xA5, 1);
+            let base = new.as_mut_ptr() as *const Padded;
+            (raw_field!(base, Padded, a) as *mut u8).write(1);
+            (raw_field!(base, Padded, b) as *mut u32).write(2);
+            (raw_field!(base, Padded, c) as *mut u16).write(0x0405);
+            new.assume_init()
+        };
+
+        let changed = unsafe { changed_fields(&layout, &old, &new) };
+        let mut indices = changed.iter();
+        assert_eq!(indices.next(), Some(2));
+        assert_eq!(indices.next(), None);
+        assert!(unsafe { changed_fields(&layout, &old, &old) }.is_empty());
+
+        let mut all = changed;
+        all.insert(0);
+        all.insert(1);
+        let mut out = [0u8; 8];
+        let written = unsafe { write_fields(&layout, all, &old, &mut out) };
+        assert_eq!(written, 1 + 4 + 2);
+        assert_eq!(out[0], 1);
+        assert_eq!(out[1..5], 2u32.to_ne_bytes());
+        assert_eq!(out[5..7], 3u16.to_ne_bytes());
+        assert_eq!(out[7], 0);
+    }
+
+    #[test]
+    fn layout() {
+        let layout = type_layout!(Outer { tag, inner, tail });
+        assert_eq!(layout.name, "Outer");
+        assert_eq!(layout.size, 16);
+        assert_eq!(layout.align, 4);
+        assert_eq!(layout.fields.len(), 3);
+        assert_eq!(layout.fields[1].name, "inner");
+        assert_eq!(layout.fields[1].offset, 4);
+        assert_eq!(layout.fields[1].size, 8);
+        assert_eq!(layout.fields[2].offset, 12);
+        assert_eq!(layout.fields[2].size, 3);
+    }
+
+    #[test]
+    fn display() {
+        use core::fmt::Write;
+
+        struct Buf([u8; 128], usize);
+
+        impl Write for Buf {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                self.0[self.1..self.1 + s.len()].copy_from_slice(s.as_bytes());
+                self.1 += s.len();
+                Ok(())
+            }
+        }
+
+        let mut buf = Buf([0; 128], 0);
+        write!(buf, "{}", type_layout!(Outer { tag, inner, tail })).unwrap();
+        assert_eq!(
+            core::str::from_utf8(&buf.0[..buf.1]).unwrap(),
+            "Outer: size 16, align 4\n  tag @ 0..2\n  inner @ 4..12\n  tail @ 12..15"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[cfg_attr(miri, ignore)] // Miri isolates the file system
+    fn snapshots() {
+        layout_snapshot!(Inner { x, y }, "tests/layouts/Inner.layout");
+        layout_snapshot!(Outer { tag, inner, tail }, "tests/layouts/Outer.layout");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn snapshot_diff() {
+        let old = "Foo: size 8, align 4\n  a @ 0..4\n  b @ 4..6\n";
+        let new = "Foo: size 8, align 4\n  a @ 0..2\n  b @ 4..6\n  c @ 6..8\n";
+        assert_eq!(
+            super::line_diff(old, new),
+            "  Foo: size 8, align 4\n-   a @ 0..4\n+   a @ 0..2\n    b @ 4..6\n+   c @ 6..8\n"
+        );
+    }
+
+    #[cfg(all(feature = "std", type_name))]
+    #[test]
+    fn json() {
+        use super::layout_to_json;
+        use std::vec::Vec;
+
+        let mut out = Vec::new();
+        layout_to_json(&type_layout!(Outer { tag, inner, tail }), &mut out).unwrap();
+        layout_to_json(&type_layout!(Inner { x, y }), &mut out).unwrap();
+        layout_to_json(&type_layout!(Inner {}), &mut out).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&out).unwrap(),
+            r#"{
+  "name": "Outer",
+  "size": 16,
+  "align": 4,
+  "fields": [
+    { "name": "tag", "offset": 0, "size": 2, "type": "u16" },
+    { "name": "inner", "offset": 4, "size": 8, "type": "memoffset::layout::tests::Inner" },
+    { "name": "tail", "offset": 12, "size": 3, "type": "[u8; 3]" }
+  ]
+}
+{
+  "name": "Inner",
+  "size": 8,
+  "align": 4,
+  "fields": [
+    { "name": "x", "offset": 0, "size": 1, "type": "u8" },
+    { "name": "y", "offset": 4, "size": 4, "type": "u32" }
+  ]
+}
+{
+  "name": "Inner",
+  "size": 8,
+  "align": 4,
+  "fields": []
+}
+"#
+        );
+    }
+
+    #[cfg(all(feature = "std", type_name))]
+    #[test]
+    #[cfg_attr(miri, ignore)] // Miri isolates the file system
+    fn json_files() {
+        use super::write_layouts_json;
+        use std::string::ToString;
+        use std::{fs, process};
+
+        let dir = std::env::temp_dir().join(std::format!("memoffset-json-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        write_layouts_json(
+            &dir,
+            &[type_layout!(Inner { x, y }), type_layout!(Pair { 1 })],
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("Inner.json")).unwrap(),
+            r#"{
+  "name": "Inner",
+  "size": 8,
+  "align": 4,
+  "fields": [
+    { "name": "x", "offset": 0, "size": 1, "type": "u8" },
+    { "name": "y", "offset": 4, "size": 4, "type": "u32" }
+  ]
+}
+"#
+        );
+        assert!(fs::read_to_string(dir.join("Pair.json")).unwrap().contains(
+            r#"{ "name": "1", "offset": 4, "size": 8, "type": "memoffset::layout::tests::Inner" }"#
+        ));
+
+        // Paths to the same type name, and names that only differ in punctuation, collide.
+        mod sub {
+            #[repr(C)]
+            pub struct Outer(pub u8);
+        }
+        let mut clash = type_layout!(Outer { tag });
+        clash.name = "sub__Outer";
+        let err = write_layouts_json(&dir, &[type_layout!(sub::Outer { 0 }), clash]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("`sub::Outer` and `sub__Outer`"));
+        assert!(!dir.join("sub__Outer.json").exists());
+
+        let inner = type_layout!(Inner { x });
+        let err = write_layouts_json(&dir, &[inner, inner]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn json_escaping() {
+        use super::{layout_to_json, TypeLayout};
+        use std::vec::Vec;
+
+        let mut out = Vec::new();
+        let layout = TypeLayout {
+            name: "a\"b\\c\n",
+            size: 0,
+            align: 1,
+            fields: &[],
+        };
+        layout_to_json(&layout, &mut out).unwrap();
+        assert!(std::str::from_utf8(&out)
+            .unwrap()
+            .contains(r#""name": "a\"b\\c\u000a","#));
+    }
+}
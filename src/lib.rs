@@ -52,8 +52,48 @@
 //! let checksum_range = &raw[span_of!(Message, header..checksum)];
 //! let checksum = crc16(checksum_range);
 //! ```
+//!
+//! ## Use in other macros
+//! Field names are not hygienic, so the macros of this crate can be called from other
+//! `macro_rules!` macros with field names taken as `$field:ident` (or `$field:tt`, which also
+//! covers tuple indices), through any number of layers.
+//! Take the parent type as `$parent:path` or `$parent:ident`, not as `$parent:ty`: a `ty`
+//! fragment can not be matched as a path again.
+//! The local bindings of the expansions are hygienic, so they never clash with field names or
+//! with the locals of the calling macro, whatever they are called.
+//!
+//! ```
+//! use memoffset::{offset_of, span_of};
+//!
+//! #[repr(C)]
+//! struct Point {
+//!     x: u32,
+//!     y: u32,
+//! }
+//!
+//! macro_rules! field_end {
+//!     ($parent:path, $field:ident) => {
+//!         offset_of!($parent, $field) + (span_of!($parent, $field).len())
+//!     };
+//! }
+//!
+//! macro_rules! coord_end {
+//!     ($field:ident) => {
+//!         field_end!(Point, $field)
+//!     };
+//! }
+//!
+//! assert_eq!(coord_end!(y), 8);
+//! ```
 
 #![no_std]
+#![cfg_attr(all(test, offset_of_enum), feature(offset_of_enum))]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "defmt")]
+extern crate defmt;
 
 #[macro_use]
 #[cfg(doctests)]
@@ -66,21 +106,787 @@ doctest!("../README.md");
 /// Hidden module for things the macros need to access.
 #[doc(hidden)]
 pub mod __priv {
+    #[doc(hidden)]
+    pub use core::clone::Clone;
+    #[doc(hidden)]
+    pub use core::convert::From;
+    #[doc(hidden)]
+    pub use core::iter::Iterator;
     #[doc(hidden)]
     pub use core::mem;
     #[doc(hidden)]
+    pub use core::ops;
+    #[doc(hidden)]
+    pub use core::option::Option;
+    #[doc(hidden)]
     pub use core::ptr;
+    #[doc(hidden)]
+    pub use core::result::Result;
+    #[doc(hidden)]
+    pub use core::slice;
+
+    #[cfg(feature = "std")]
+    #[doc(hidden)]
+    pub use std::boxed::Box;
+    #[cfg(feature = "std")]
+    #[doc(hidden)]
+    pub use std::pin::Pin;
 
     /// Use type inference to obtain the size of the pointee (without actually using the pointer).
+    #[cfg(stable_const)]
+    #[doc(hidden)]
+    pub const fn size_of_pointee<T>(_ptr: *const T) -> usize {
+        mem::size_of::<T>()
+    }
+    /// Use type inference to obtain the size of the pointee (without actually using the pointer).
+    #[cfg(not(stable_const))]
     #[doc(hidden)]
     pub fn size_of_pointee<T>(_ptr: *const T) -> usize {
         mem::size_of::<T>()
     }
+
+    /// Does nothing, but can only be called in an `unsafe` block, so that the unchecked macros
+    /// require one even where their expansion does nothing else that is unsafe.
+    #[cfg(stable_const)]
+    #[doc(hidden)]
+    pub const unsafe fn assume_direct_field() {}
+    /// Does nothing, but can only be called in an `unsafe` block, so that the unchecked macros
+    /// require one even where their expansion does nothing else that is unsafe.
+    #[cfg(not(stable_const))]
+    #[doc(hidden)]
+    pub unsafe fn assume_direct_field() {}
+
+    /// Converts a reference to a const raw pointer, so that the type of the pointer is known
+    /// right away, unlike with an `as *const _` cast.
+    #[cfg(not(raw_ref_macros))]
+    #[doc(hidden)]
+    pub fn ref_to_ptr<T: ?Sized>(r: &T) -> *const T {
+        r
+    }
+
+    /// A cell type whose contents have the same in-memory representation as the cell itself.
+    #[doc(hidden)]
+    pub trait CellField {
+        type Inner: ?Sized;
+    }
+    impl<T: ?Sized> CellField for core::cell::UnsafeCell<T> {
+        type Inner = T;
+    }
+    impl<T: ?Sized> CellField for core::cell::Cell<T> {
+        type Inner = T;
+    }
+    /// Projects a pointer to a cell to a mutable pointer to its contents, as
+    /// `UnsafeCell::raw_get` does, without creating a reference to the cell.
+    #[doc(hidden)]
+    pub fn cell_contents<C: CellField>(cell: *const C) -> *mut C::Inner
+    where
+        C::Inner: Sized,
+    {
+        cell as *mut C::Inner
+    }
+
+    /// Use type inference to obtain the name of the type of the pointee.
+    #[cfg(type_name)]
+    #[doc(hidden)]
+    pub fn type_name_of_pointee<T>(_ptr: *const T) -> &'static str {
+        core::any::type_name::<T>()
+    }
+    /// Use type inference to obtain the name of the type of the pointee.
+    #[cfg(not(type_name))]
+    #[doc(hidden)]
+    pub fn type_name_of_pointee<T>(_ptr: *const T) -> &'static str {
+        ""
+    }
+
+    /// Panics if casting `value` to a narrower integer type truncated it, given the result of
+    /// casting it back to `usize`.
+    #[cfg(stable_const)]
+    #[doc(hidden)]
+    pub const fn assert_cast_fits(value: usize, roundtrip: usize) {
+        assert!(
+            value == roundtrip,
+            "offset does not fit in the target integer type"
+        );
+    }
+    /// Panics if casting `value` to a narrower integer type truncated it, given the result of
+    /// casting it back to `usize`.
+    #[cfg(not(stable_const))]
+    #[doc(hidden)]
+    pub fn assert_cast_fits(value: usize, roundtrip: usize) {
+        assert!(
+            value == roundtrip,
+            "offset does not fit in the target integer type"
+        );
+    }
+
+    /// Panics unless the pointee has the same size and alignment as `T`.
+    /// `_ptr` is only used to infer the type of the pointee.
+    #[cfg(stable_const)]
+    #[doc(hidden)]
+    pub const fn assert_same_layout<T, U>(_ptr: *const U) {
+        assert!(
+            mem::size_of::<T>() == mem::size_of::<U>()
+                && mem::align_of::<T>() == mem::align_of::<U>(),
+            "the wrapper does not have the same size and alignment as the type it wraps"
+        );
+    }
+    /// Panics unless the pointee has the same size and alignment as `T`.
+    /// `_ptr` is only used to infer the type of the pointee.
+    #[cfg(not(stable_const))]
+    #[doc(hidden)]
+    pub fn assert_same_layout<T, U>(_ptr: *const U) {
+        assert!(
+            mem::size_of::<T>() == mem::size_of::<U>()
+                && mem::align_of::<T>() == mem::align_of::<U>(),
+            "the wrapper does not have the same size and alignment as the type it wraps"
+        );
+    }
+
+    /// Use type inference to obtain the size of the elements of the pointee.
+    #[doc(hidden)]
+    pub fn size_of_element<T>(_ptr: *const [T]) -> usize {
+        mem::size_of::<T>()
+    }
+
+    #[cfg(maybe_uninit)]
+    #[doc(hidden)]
+    pub use debug::FieldsWithOffsets;
+
+    #[cfg(stable_const)]
+    #[doc(hidden)]
+    pub use assertions::{cachelines_disjoint, cachelines_shared, is_aligned_to};
+
+    /// Copies the pointee of `from` to `to` bytewise, using type inference to obtain the size.
+    #[doc(hidden)]
+    pub unsafe fn copy_pointee<T>(from: *const T, to: *const T) {
+        ptr::copy_nonoverlapping(from as *const u8, to as *mut u8, mem::size_of::<T>());
+    }
+
+    /// Copies the pointee of `from` to `to`, as `copy_pointee` does, but only for `Copy` types.
+    #[doc(hidden)]
+    pub unsafe fn copy_value<T: Copy>(from: *const T, to: *const T) {
+        ptr::copy_nonoverlapping(from as *const u8, to as *mut u8, mem::size_of::<T>());
+    }
+
+    /// Replaces the pointee of `to` with a clone of the pointee of `from`, and drops the old
+    /// value. Both pointers may be unaligned: the clone is made from a bitwise copy of the
+    /// pointee of `from`, which is never dropped.
+    #[cfg(maybe_uninit)]
+    #[doc(hidden)]
+    pub unsafe fn clone_value<T: Clone>(from: *const T, to: *const T) {
+        let source = mem::ManuallyDrop::new(ptr::read_unaligned(from));
+        let value = T::clone(&source);
+        let old = ptr::read_unaligned(to);
+        ptr::write_unaligned(to as *mut T, value);
+        mem::drop(old);
+    }
+
+    /// Computes the address of the parent from a pointer to its field, given the offset of the
+    /// field. The field and base pointers of some other value of the parent type are only used
+    /// to infer the types.
+    #[doc(hidden)]
+    #[cfg_attr(allow_clippy, allow(clippy::ptr_offset_with_cast))] // `wrapping_sub` requires rustc 1.26
+    pub fn container_of<F, P>(
+        ptr: *const F,
+        _field: *const F,
+        _base: *const P,
+        offset: usize,
+    ) -> *const P {
+        (ptr as *const u8).wrapping_offset(-(offset as isize)) as *const P
+    }
+
+    /// Writes `value` to `ptr` without reading or dropping the old value, even if `ptr` is
+    /// unaligned. Using type inference, this makes sure `value` has the type of the pointee.
+    #[doc(hidden)]
+    pub unsafe fn write_pointee<T>(ptr: *const T, value: T) {
+        ptr::write_unaligned(ptr as *mut T, value);
+    }
+
+    /// The bitmask of an `uninit_builder!` builder that has all of its `fields` set.
+    #[doc(hidden)]
+    pub fn builder_mask(fields: usize) -> u64 {
+        assert!(fields <= 64, "`uninit_builder!` supports at most 64 fields");
+        if fields == 64 {
+            !0
+        } else {
+            (1 << fields) - 1
+        }
+    }
+
+    /// Drops the pointee of `ptr`, even if `ptr` is unaligned.
+    #[doc(hidden)]
+    pub unsafe fn drop_pointee<T>(ptr: *const T) {
+        mem::drop(ptr::read_unaligned(ptr));
+    }
+
+    /// Produces the bytes in the `(start, end)` range of a possibly uninitialized value.
+    /// The range must lie within the value.
+    #[cfg(maybe_uninit)]
+    #[doc(hidden)]
+    pub unsafe fn uninit_bytes<T>(
+        value: &mem::MaybeUninit<T>,
+        (start, end): (usize, usize),
+    ) -> &[mem::MaybeUninit<u8>] {
+        let base = value.as_ptr() as *const mem::MaybeUninit<u8>;
+        slice::from_raw_parts(base.add(start), end - start)
+    }
+    /// Produces the bytes in the `(start, end)` range of a possibly uninitialized value.
+    /// The range must lie within the value.
+    #[cfg(maybe_uninit)]
+    #[doc(hidden)]
+    pub unsafe fn uninit_bytes_mut<T>(
+        value: &mut mem::MaybeUninit<T>,
+        (start, end): (usize, usize),
+    ) -> &mut [mem::MaybeUninit<u8>] {
+        let base = value.as_mut_ptr() as *mut mem::MaybeUninit<u8>;
+        slice::from_raw_parts_mut(base.add(start), end - start)
+    }
+
+    /// Computes `n * size + offset`, or `None` on overflow.
+    #[cfg(stable_const)]
+    #[doc(hidden)]
+    pub const fn nth_offset(n: usize, size: usize, offset: usize) -> Option<usize> {
+        match n.checked_mul(size) {
+            Some(start) => start.checked_add(offset),
+            None => None,
+        }
+    }
+    /// Computes `n * size + offset`, or `None` on overflow.
+    #[cfg(not(stable_const))]
+    #[doc(hidden)]
+    pub fn nth_offset(n: usize, size: usize, offset: usize) -> Option<usize> {
+        n.checked_mul(size)
+            .and_then(|start| start.checked_add(offset))
+    }
+
+    /// Binary searches `count` records of `stride` bytes each, starting at `base`, for `key`,
+    /// which is read unaligned at `offset` into each record. `_field` is only used to infer
+    /// the type of the key.
+    #[doc(hidden)]
+    #[cfg_attr(allow_clippy, allow(clippy::ptr_offset_with_cast))] // `add` requires rustc 1.26
+    pub unsafe fn search_records<K: Ord + Copy>(
+        base: *const u8,
+        count: usize,
+        stride: usize,
+        offset: usize,
+        key: &K,
+        _field: *const K,
+    ) -> Result<usize, usize> {
+        let mut low = 0;
+        let mut high = count;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let probe =
+                ptr::read_unaligned(base.offset((mid * stride + offset) as isize) as *const K);
+            match probe.cmp(key) {
+                core::cmp::Ordering::Less => low = mid + 1,
+                core::cmp::Ordering::Greater => high = mid,
+                core::cmp::Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+
+    /// Reads the key at `offset` into `record`, unaligned. `_field` is only used to infer the
+    /// type of the key.
+    #[doc(hidden)]
+    #[cfg_attr(allow_clippy, allow(clippy::ptr_offset_with_cast))] // `add` requires rustc 1.26
+    pub unsafe fn record_key<P, K: Copy>(record: &P, offset: usize, _field: *const K) -> K {
+        ptr::read_unaligned((record as *const P as *const u8).offset(offset as isize) as *const K)
+    }
+
+    /// Sorts `records` by the key read unaligned at `offset` into each record, keeping records
+    /// with equal keys in order. `field` is only used to infer the type of the key.
+    #[cfg(feature = "std")]
+    #[doc(hidden)]
+    pub unsafe fn sort_records<P, K: Ord + Copy>(
+        records: &mut [P],
+        offset: usize,
+        field: *const K,
+    ) {
+        records.sort_by_key(|record| record_key(record, offset, field));
+    }
+
+    /// Checks whether `records` are sorted by the key read unaligned at `offset` into each
+    /// record. `field` is only used to infer the type of the key.
+    #[doc(hidden)]
+    pub unsafe fn records_sorted<P, K: Ord + Copy>(
+        records: &[P],
+        offset: usize,
+        field: *const K,
+    ) -> bool {
+        records
+            .windows(2)
+            .all(|pair| record_key(&pair[0], offset, field) <= record_key(&pair[1], offset, field))
+    }
+
+    /// Assembles a big-endian unsigned integer of up to 8 bytes.
+    #[doc(hidden)]
+    pub fn read_uint_be(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0, |acc, &byte| acc << 8 | byte as u64)
+    }
+    /// Assembles a little-endian unsigned integer of up to 8 bytes.
+    #[doc(hidden)]
+    pub fn read_uint_le(bytes: &[u8]) -> u64 {
+        bytes
+            .iter()
+            .rev()
+            .fold(0, |acc, &byte| acc << 8 | byte as u64)
+    }
+    /// Splits an unsigned integer into big-endian bytes, panicking if it does not fit.
+    #[doc(hidden)]
+    pub fn write_uint_be(bytes: &mut [u8], value: u64) {
+        write_uint_le(bytes, value);
+        bytes.reverse();
+    }
+    /// Splits an unsigned integer into little-endian bytes, panicking if it does not fit.
+    #[doc(hidden)]
+    pub fn write_uint_le(bytes: &mut [u8], value: u64) {
+        assert!(
+            bytes.len() >= 8 || value >> (8 * bytes.len()) == 0,
+            "the value does not fit in the field"
+        );
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = (value >> (8 * i)) as u8;
+        }
+    }
+
+    /// Resolves the element range `start..end` (`start..` if `end` is `None`) of an array of
+    /// `array_size` bytes to a `(start, end)` byte range relative to the start of the array,
+    /// panicking if it is reversed or out of bounds.
+    /// `_array` is only used to infer the type of the elements.
+    #[cfg(stable_const)]
+    #[doc(hidden)]
+    pub const fn element_span<T>(
+        _array: *const [T],
+        start: usize,
+        end: Option<usize>,
+        array_size: usize,
+    ) -> (usize, usize) {
+        let size = mem::size_of::<T>();
+        let len = match array_size.checked_div(size) {
+            Some(len) => len,
+            None => 0,
+        };
+        let end = match end {
+            Some(end) => end,
+            None => len,
+        };
+        assert!(
+            start <= end && (size == 0 || end <= len),
+            "element range out of bounds of the array"
+        );
+        (start * size, end * size)
+    }
+    /// Resolves the element range `start..end` (`start..` if `end` is `None`) of an array of
+    /// `array_size` bytes to a `(start, end)` byte range relative to the start of the array,
+    /// panicking if it is reversed or out of bounds.
+    /// `_array` is only used to infer the type of the elements.
+    #[cfg(not(stable_const))]
+    #[doc(hidden)]
+    pub fn element_span<T>(
+        _array: *const [T],
+        start: usize,
+        end: Option<usize>,
+        array_size: usize,
+    ) -> (usize, usize) {
+        let size = mem::size_of::<T>();
+        let len = array_size.checked_div(size).unwrap_or(0);
+        let end = end.unwrap_or(len);
+        assert!(
+            start <= end && (size == 0 || end <= len),
+            "element range out of bounds of the array"
+        );
+        (start * size, end * size)
+    }
+
+    /// Returns the index one past the element at `index`, the exclusive end of a range ending
+    /// at it, panicking instead of wrapping around if that does not fit in `usize`.
+    #[cfg(stable_const)]
+    #[doc(hidden)]
+    pub const fn end_of_element(index: usize) -> usize {
+        match index.checked_add(1) {
+            Some(end) => end,
+            None => panic!("element index out of bounds of the array"),
+        }
+    }
+    /// Returns the index one past the element at `index`, the exclusive end of a range ending
+    /// at it, panicking instead of wrapping around if that does not fit in `usize`.
+    #[cfg(not(stable_const))]
+    #[doc(hidden)]
+    pub fn end_of_element(index: usize) -> usize {
+        match index.checked_add(1) {
+            Some(end) => end,
+            None => panic!("element index out of bounds of the array"),
+        }
+    }
+
+    /// Resolves the element range `start..end` (`start..` if `end` is `None`) of an array of
+    /// `len` elements of type `T` to a `(start, end)` byte range relative to the start of the
+    /// array, panicking if it is reversed or out of bounds.
+    #[cfg(stable_const)]
+    #[doc(hidden)]
+    pub const fn array_span<T>(len: usize, start: usize, end: Option<usize>) -> (usize, usize) {
+        let end = match end {
+            Some(end) => end,
+            None => len,
+        };
+        assert!(
+            start <= end && end <= len,
+            "element range out of bounds of the array"
+        );
+        let size = mem::size_of::<T>();
+        (start * size, end * size)
+    }
+    /// Resolves the element range `start..end` (`start..` if `end` is `None`) of an array of
+    /// `len` elements of type `T` to a `(start, end)` byte range relative to the start of the
+    /// array, panicking if it is reversed or out of bounds.
+    #[cfg(not(stable_const))]
+    #[doc(hidden)]
+    pub fn array_span<T>(len: usize, start: usize, end: Option<usize>) -> (usize, usize) {
+        let end = end.unwrap_or(len);
+        assert!(
+            start <= end && end <= len,
+            "element range out of bounds of the array"
+        );
+        let size = mem::size_of::<T>();
+        (start * size, end * size)
+    }
+
+    /// Offsets a pointer to an array by `start` bytes, to get a pointer to one of its elements.
+    #[doc(hidden)]
+    #[cfg_attr(allow_clippy, allow(clippy::ptr_offset_with_cast))] // `wrapping_add` requires rustc 1.26
+    pub fn element_ptr<T>(array: *const [T], start: usize) -> *const T {
+        (array as *const T as *const u8).wrapping_offset(start as isize) as *const T
+    }
+
+    /// Checks that the `(start, end)` ranges are sorted and do not overlap.
+    #[cfg(stable_const)]
+    #[doc(hidden)]
+    pub const fn spans_ordered(spans: &[(usize, usize)]) -> bool {
+        let mut i = 1;
+        while i < spans.len() {
+            if spans[i - 1].1 > spans[i].0 {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+    /// Checks that the `(start, end)` ranges are sorted and do not overlap.
+    #[cfg(not(stable_const))]
+    #[doc(hidden)]
+    pub fn spans_ordered(spans: &[(usize, usize)]) -> bool {
+        spans.windows(2).all(|pair| pair[0].1 <= pair[1].0)
+    }
+    /// Returns the smallest `(start, end)` range that covers all of the given ranges, which
+    /// may be listed in any order.
+    #[cfg(stable_const)]
+    #[doc(hidden)]
+    pub const fn covering_span(spans: &[(usize, usize)]) -> (usize, usize) {
+        let mut covering = spans[0];
+        let mut i = 1;
+        while i < spans.len() {
+            if spans[i].0 < covering.0 {
+                covering.0 = spans[i].0;
+            }
+            if spans[i].1 > covering.1 {
+                covering.1 = spans[i].1;
+            }
+            i += 1;
+        }
+        covering
+    }
+    /// Returns the smallest `(start, end)` range that covers all of the given ranges, which
+    /// may be listed in any order.
+    #[cfg(not(stable_const))]
+    #[doc(hidden)]
+    pub fn covering_span(spans: &[(usize, usize)]) -> (usize, usize) {
+        spans.iter().fold(spans[0], |covering, span| {
+            (
+                core::cmp::min(covering.0, span.0),
+                core::cmp::max(covering.1, span.1),
+            )
+        })
+    }
+
+    /// Overwrites `len` bytes starting at `ptr` with zeros, using volatile stores.
+    #[cfg(feature = "zeroize")]
+    #[doc(hidden)]
+    #[cfg_attr(allow_clippy, allow(clippy::ptr_offset_with_cast))] // `add` requires rustc 1.26
+    pub unsafe fn volatile_zero(ptr: *mut u8, len: usize) {
+        for i in 0..len {
+            ptr::write_volatile(ptr.offset(i as isize), 0);
+        }
+    }
 }
 
+pub mod span;
+
 #[macro_use]
 mod raw_field;
 #[macro_use]
 mod offset_of;
 #[macro_use]
 mod span_of;
+#[macro_use]
+mod zeroize;
+#[macro_use]
+pub mod placement;
+#[macro_use]
+mod assertions;
+#[macro_use]
+mod roundtrip;
+#[macro_use]
+mod init;
+#[macro_use]
+mod array;
+#[macro_use]
+pub mod layout;
+#[macro_use]
+mod split;
+#[macro_use]
+mod uint;
+#[macro_use]
+mod debug;
+#[macro_use]
+mod verification;
+// Uses const generics, so it must not even be parsed by older compilers.
+#[cfg(stable_const)]
+#[macro_use]
+pub mod repr_c;
+#[cfg(stable_const)]
+#[macro_use]
+mod field_enum;
+#[cfg(stable_const)]
+#[macro_use]
+pub mod abi;
+#[cfg(feature = "std")]
+#[macro_use]
+pub mod io;
+#[cfg(feature = "std")]
+#[macro_use]
+mod self_ref;
+
+#[cfg(test)]
+mod tests {
+    #[repr(C)]
+    struct Foo {
+        a: u32,
+        b: [u8; 3],
+        c: u64,
+    }
+
+    #[repr(C)]
+    union Bits {
+        int: u32,
+        bytes: [u8; 4],
+    }
+
+    #[repr(C)]
+    struct Pair(u8, u32);
+
+    // Field names pass through two layers of user macros into each macro of the crate.
+    macro_rules! outer {
+        ($parent:path, $field:ident, $last:ident) => {
+            inner!($parent, $field, $last)
+        };
+    }
+
+    macro_rules! inner {
+        ($parent:path, $field:tt, $last:tt) => {{
+            assert_field_exists!($parent, $field);
+            let uninit = core::mem::MaybeUninit::<$parent>::uninit();
+            let base = uninit.as_ptr();
+            let field = raw_field!(base, $parent, $field);
+            assert_eq!(field, unsafe {
+                raw_field_unchecked!(base, $parent, $field)
+            });
+            assert_eq!(container_of!(field, $parent, $field), base);
+            assert_eq!(
+                unsafe { offset_of_unchecked!($parent, $field) },
+                offset_of!($parent, $field)
+            );
+            assert_eq!(
+                unsafe { span_of_unchecked!($parent, $field) },
+                span_of!($parent, $field)
+            );
+            assert_eq!(
+                span_of!($parent, $field..=$last).start,
+                offset_of!($parent, $field)
+            );
+            assert_eq!(type_layout!($parent { $field, $last }).fields.len(), 2);
+            assert_eq!(
+                nth_field_offset!($parent, $field, 1),
+                Some(core::mem::size_of::<$parent>() + offset_of!($parent, $field))
+            );
+            offset_of!($parent, $field)
+        }};
+    }
+
+    macro_rules! outer_tt {
+        ($parent:path, $field:tt, $last:tt) => {
+            inner!($parent, $field, $last)
+        };
+    }
+
+    macro_rules! outer_union {
+        ($parent:path, $field:ident) => {
+            inner_union!($parent, $field)
+        };
+    }
+
+    macro_rules! inner_union {
+        ($parent:path, $field:ident) => {{
+            assert_field_exists!(union $parent, $field);
+            let uninit = core::mem::MaybeUninit::<$parent>::uninit();
+            let field = raw_field_union!(uninit.as_ptr(), $parent, $field);
+            assert_eq!(field as usize, uninit.as_ptr() as usize);
+            offset_of_union!($parent, $field)
+        }};
+    }
+
+    macro_rules! outer_tuple {
+        ($parent:ty, $field:tt) => {
+            inner_tuple!($parent, $field)
+        };
+    }
+
+    macro_rules! inner_tuple {
+        ($parent:ty, $field:tt) => {{
+            let uninit = core::mem::MaybeUninit::<$parent>::uninit();
+            let field = raw_field_tuple!(uninit.as_ptr(), $parent, $field);
+            assert_eq!(
+                field as usize - uninit.as_ptr() as usize,
+                offset_of_tuple!($parent, $field)
+            );
+            offset_of_tuple!($parent, $field)
+        }};
+    }
+
+    #[test]
+    #[cfg(maybe_uninit)]
+    fn forwarded_field_names() {
+        assert_eq!(outer!(Foo, a, c), 0);
+        assert_eq!(outer!(Foo, b, c), 4);
+        assert_eq!(outer_tt!(Pair, 0, 1), 0);
+        assert_eq!(outer_tt!(Pair, 1, 1), 4);
+        assert_eq!(outer_union!(Bits, bytes), 0);
+        assert_eq!(outer_tuple!((u8, u16), 1), 2);
+    }
+
+    // Stands in for a proc macro that generates the name of the parent type. The expansion is
+    // not a path, so the checked macros reject it, while the unchecked ones take any type.
+    macro_rules! generated_parent {
+        () => {
+            Foo
+        };
+    }
+
+    macro_rules! generated_fields {
+        ($field:ident, $last:ident) => {{
+            let uninit = core::mem::MaybeUninit::<generated_parent!()>::uninit();
+            let base = uninit.as_ptr();
+            // Safety: the fields are fields of `Foo` itself, not of something it derefs to.
+            unsafe {
+                (
+                    raw_field_unchecked!(base, generated_parent!(), $field) as usize
+                        - base as usize,
+                    offset_of_unchecked!(generated_parent!(), $field),
+                    span_of_unchecked!(generated_parent!(), $field..=$last),
+                )
+            }
+        }};
+    }
+
+    #[test]
+    #[cfg(maybe_uninit)]
+    fn generated_parent_type() {
+        assert_eq!(generated_fields!(a, b), (0, 0, span_of!(Foo, a..=b)));
+        assert_eq!(generated_fields!(b, c), (4, 4, span_of!(Foo, b..=c)));
+        assert_eq!(
+            unsafe { span_of_unchecked!(generated_parent!(), ..) },
+            span_of!(Foo, ..)
+        );
+    }
+
+    // Every field is named after a local binding of some expansion.
+    #[repr(C)]
+    struct Internals {
+        base: u16,
+        base_ptr: u16,
+        root: u16,
+        uninit: u16,
+        field: u16,
+        field_ptr: u16,
+        offset: u16,
+        ptr: u16,
+        begin: u16,
+        end: u16,
+        start: u16,
+        val: u16,
+        value: u16,
+        span: u16,
+    }
+
+    macro_rules! with_locals {
+        ($parent:path, $field:ident, $last:ident) => {{
+            let offset = 1usize;
+            let base_ptr = 2usize;
+            let val = 3usize;
+            let field = offset_of!($parent, $field);
+            let span = span_of!($parent, $field..=$last);
+            (field, span, offset + base_ptr + val)
+        }};
+    }
+
+    #[test]
+    fn internal_names() {
+        assert_eq!(offset_of!(Internals, base), 0);
+        assert_eq!(offset_of!(Internals, base_ptr), 2);
+        assert_eq!(offset_of!(Internals, offset), 12);
+        assert_eq!(unsafe { offset_of_unchecked!(Internals, field_ptr) }, 10);
+        assert_eq!(span_of!(Internals, root), 4..6);
+        assert_eq!(span_of!(Internals, uninit..field), 6..8);
+        assert_eq!(span_of!(Internals, begin..=end), 16..20);
+        assert_eq!(span_of!(Internals, ..=ptr), 0..16);
+        assert_eq!(span_of!(Internals, val..), 22..28);
+        assert_eq!(
+            unsafe { span_of_unchecked!(Internals, value..span) },
+            24..26
+        );
+        assert_eq!(nth_field_offset!(Internals, start, 1), Some(28 + 20));
+        assert_eq!(with_locals!(Internals, offset, val), (12, 12..24, 6));
+
+        let value = Internals {
+            base: 0,
+            base_ptr: 1,
+            root: 2,
+            uninit: 3,
+            field: 4,
+            field_ptr: 5,
+            offset: 6,
+            ptr: 7,
+            begin: 8,
+            end: 9,
+            start: 10,
+            val: 11,
+            value: 12,
+            span: 13,
+        };
+        let base = &value as *const Internals;
+        let ptr = raw_field!(base, Internals, ptr);
+        let field = raw_field!(base, Internals, field);
+        assert_eq!(unsafe { (*ptr, *field) }, (7, 4));
+        assert_eq!(container_of!(ptr, Internals, ptr), base);
+        assert_eq!(container_of!(field, Internals, field), base);
+        assert_eq!(
+            unsafe { *raw_field_unchecked!(base, Internals, base_ptr) },
+            1
+        );
+        assert_eq!(
+            unsafe { *raw_field!(base, Internals, offset) },
+            value.offset
+        );
+    }
+}
@@ -35,30 +35,49 @@ macro_rules! _memoffset__let_base_ptr {
     };
 }
 #[cfg(not(maybe_uninit))]
-#[macro_export]
+#[macro_export(local_inner_macros)]
 #[doc(hidden)]
 macro_rules! _memoffset__let_base_ptr {
     ($name:ident, $type:ty) => {
-        // No UB right here, but we will later dereference this pointer to
-        // offset into a field, and that is UB because the pointer is dangling.
-        let $name = $crate::__priv::mem::align_of::<$type>() as *const $type;
+        _memoffset__let_fallback_base_ptr!($name, $type);
+    };
+}
+
+/// Macro to create a local `base_ptr` raw pointer of the given type without `MaybeUninit`,
+/// for rustc versions older than 1.36.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__let_fallback_base_ptr {
+    ($name:ident, $type:ty) => {
+        // A `None` is a valid value of any `Option<T>`, and an `Option<T>` is at least as
+        // large and as aligned as a `T`, so the pointer does not dangle. Its bytes may be
+        // uninitialized, but they are never read.
+        // A `static` can not be generic, so the storage has to live on the stack, in the same
+        // scope as `$name`.
+        let fallback_storage: $crate::__priv::Option<$type> = $crate::__priv::Option::None;
+        let $name = &fallback_storage as *const $crate::__priv::Option<$type> as *const $type;
     };
 }
 
 /// Macro to compute the distance between two pointers.
-#[cfg(stable_const)]
+#[cfg(offset_from)]
 #[macro_export]
 #[doc(hidden)]
 macro_rules! _memoffset_offset_from_unsafe {
     ($field:expr, $base:expr) => {{
         let field = $field; // evaluate $field outside the `unsafe` block
         let base = $base; // evaluate $base outside the `unsafe` block
-        // Compute offset, with unstable `offset_from` for const-compatibility.
+        // Compute offset with `offset_from`, which neither casts the pointers to integers nor
+        // exposes their provenance, and which can be used in `const` contexts on rustc 1.65 and
+        // newer.
         // (Requires the pointers to not dangle, but we already need that for `raw_field!` anyway.)
-        unsafe { (field as *const u8).offset_from(base as *const u8) as usize }
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            (field as *const u8).offset_from(base as *const u8) as usize
+        }
     }};
 }
-#[cfg(not(stable_const))]
+#[cfg(not(offset_from))]
 #[macro_export]
 #[doc(hidden)]
 macro_rules! _memoffset_offset_from_unsafe {
@@ -67,12 +86,119 @@ macro_rules! _memoffset_offset_from_unsafe {
         ($field as usize) - ($base as usize)
     };
 }
+
+/// With the `checked` feature, panics in debug builds if the byte range of a field does not lie
+/// within its parent.
+///
+/// This is not spelled with `debug_assert!`, which is not a builtin macro; the message is
+/// passed through `"{}"` so that braces in the stringified parent can not be taken for
+/// formatting arguments.
+/// The field is stringified token by token, since the spacing that `stringify!` puts between
+/// tokens differs between rustc versions.
+#[cfg(feature = "checked")]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__check_field {
+    ($parent:ty, ($($field:tt)+), $start:expr, $end:expr, $size:expr) => {
+        if cfg!(debug_assertions) {
+            let (start, end, size): (usize, usize, usize) = ($start, $end, $size);
+            assert!(
+                start <= end && end <= size,
+                "{}",
+                concat!(
+                    "field `",
+                    $(stringify!($field),)+
+                    "` is not inside its parent `",
+                    stringify!($parent),
+                    "`"
+                )
+            );
+        }
+    };
+}
+#[cfg(not(feature = "checked"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__check_field {
+    ($($args:tt)*) => {};
+}
+
+/// With the `checked` feature, panics in debug builds if a span is reversed or does not lie
+/// within its parent.
+/// The span is stringified token by token, as in `_memoffset__check_field!`.
+#[cfg(feature = "checked")]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__check_span {
+    ($parent:ty, ($($exp:tt)+), $begin:expr, $end:expr, $size:expr) => {
+        if cfg!(debug_assertions) {
+            let (begin, end, size): (usize, usize, usize) = ($begin, $end, $size);
+            assert!(
+                begin <= end,
+                "{}",
+                concat!(
+                    "span `",
+                    $(stringify!($exp),)+
+                    "` of `",
+                    stringify!($parent),
+                    "` ends before it starts"
+                )
+            );
+            assert!(
+                end <= size,
+                "{}",
+                concat!(
+                    "span `",
+                    $(stringify!($exp),)+
+                    "` is not inside its parent `",
+                    stringify!($parent),
+                    "`"
+                )
+            );
+        }
+    };
+}
+#[cfg(not(feature = "checked"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__check_span {
+    ($($args:tt)*) => {};
+}
+
+/// Macro to compute the size of a field, without requiring a value of the parent type.
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__field_size {
+    ($parent:path, $($field:tt).+) => {{
+        // Get a (non-dangling) base pointer.
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        // Use the type of the field pointer to obtain the size.
+        $crate::__priv::size_of_pointee(raw_field!(base_ptr, $parent, $($field).+))
+    }};
+}
+
+/// Macro to compute the `(start, end)` byte range of a field, without requiring a value of
+/// the parent type.
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__field_range {
+    ($parent:path, $($field:tt).+) => {{
+        // Get a (non-dangling) base pointer.
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        // Get field pointer.
+        let field_ptr = raw_field!(base_ptr, $parent, $($field).+);
+        // Compute offset, and use the type of the field pointer to obtain the size.
+        let offset = _memoffset_offset_from_unsafe!(field_ptr, base_ptr);
+        (offset, offset + $crate::__priv::size_of_pointee(field_ptr))
+    }};
+}
+
 #[cfg(not(stable_offset_of))]
 #[macro_export(local_inner_macros)]
 #[doc(hidden)]
 macro_rules! _memoffset__offset_of_impl {
     ($parent:path, $field:tt) => {{
-        // Get a base pointer (non-dangling if rustc supports `MaybeUninit`).
+        // Get a (non-dangling) base pointer.
         _memoffset__let_base_ptr!(base_ptr, $parent);
         // Get field pointer.
         let field_ptr = raw_field!(base_ptr, $parent, $field);
@@ -89,6 +215,127 @@ macro_rules! _memoffset__offset_of_impl {
     }};
 }
 
+#[cfg(not(nested_offset_of))]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__offset_of_nested_impl {
+    ($parent:path, $($field:tt).+) => {
+        _memoffset__compile_error!("nested field paths require rustc 1.82 or newer")
+    };
+}
+#[cfg(nested_offset_of)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__offset_of_nested_impl {
+    // The built-in macro checks that no segment of the path goes through a `Deref` impl.
+    ($parent:path, $($field:tt).+) => {{
+        $crate::__priv::mem::offset_of!($parent, $($field).+)
+    }};
+}
+
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__offset_of_element_impl {
+    ($parent:path, $($field:tt).+, $index:expr) => {{
+        // Get a (non-dangling) base pointer.
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        // Get the array pointer, whose type gives the size and number of the elements.
+        let array_ptr = raw_field!(base_ptr, $parent, $($field).+);
+        let index = $index;
+        let (element, _) = $crate::__priv::element_span(
+            array_ptr as *const [_],
+            index,
+            $crate::__priv::Option::Some($crate::__priv::end_of_element(index)),
+            $crate::__priv::size_of_pointee(array_ptr),
+        );
+        offset_of!($parent, $($field).+) + element
+    }};
+}
+
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__offset_of_array_impl {
+    ($elem:ty, $len:expr, $index:expr) => {{
+        let index = $index;
+        let (element, _) = $crate::__priv::array_span::<$elem>(
+            $len,
+            index,
+            $crate::__priv::Option::Some($crate::__priv::end_of_element(index)),
+        );
+        element
+    }};
+}
+
+/// Checks that a field has the same size and alignment as the type it is declared to wrap, at
+/// compile time if rustc supports inline `const` blocks.
+#[cfg(inline_const)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__assert_transparent {
+    ($parent:path, $($field:tt).+, $inner:path) => {
+        const {
+            $crate::_memoffset__let_base_ptr!(base_ptr, $parent);
+            let wrapper_ptr = $crate::raw_field!(base_ptr, $parent, $($field).+);
+            $crate::__priv::assert_same_layout::<$inner, _>(wrapper_ptr);
+        }
+    };
+}
+#[cfg(not(inline_const))]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__assert_transparent {
+    ($parent:path, $($field:tt).+, $inner:path) => {{
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        let wrapper_ptr = raw_field!(base_ptr, $parent, $($field).+);
+        $crate::__priv::assert_same_layout::<$inner, _>(wrapper_ptr);
+    }};
+}
+
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__offset_of_transparent_impl {
+    ($parent:path, $($field:tt).+, $inner:path, $($rest:tt).+) => {{
+        _memoffset__assert_transparent!($parent, $($field).+, $inner);
+        offset_of!($parent, $($field).+) + offset_of!($inner, $($rest).+)
+    }};
+}
+
+#[cfg(not(nested_offset_of))]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__offset_of_variant_impl {
+    ($parent:path, $variant:ident, $($field:tt).+) => {
+        _memoffset__compile_error!("enum variant fields require rustc 1.82 or newer")
+    };
+}
+#[cfg(nested_offset_of)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__offset_of_variant_impl {
+    // The built-in macro checks that the variant and the field exist, and is gated behind the
+    // `offset_of_enum` feature of the calling crate.
+    ($parent:path, $variant:ident, $($field:tt).+) => {{
+        $crate::__priv::mem::offset_of!($parent, $variant.$($field).+)
+    }};
+}
+
+#[cfg(not(stable_offset_of))]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__offset_of_qualified_impl {
+    ($parent:ty, $field:tt) => {
+        _memoffset__compile_error!("qualified paths as the parent type require rustc 1.77 or newer")
+    };
+}
+#[cfg(stable_offset_of)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__offset_of_qualified_impl {
+    ($parent:ty, $field:tt) => {{
+        $crate::__priv::mem::offset_of!($parent, $field)
+    }};
+}
+
 /// Calculates the offset of the specified field from the start of the named struct.
 ///
 /// ## Examples
@@ -115,22 +362,509 @@ macro_rules! _memoffset__offset_of_impl {
 /// based on the compiler version, concrete struct type, time of day, or rustc's mood.
 ///
 /// As a result, the value should not be retained and used between different compilations.
+///
+/// On rustc 1.77 and newer, this forwards to `core::mem::offset_of!`.
+/// On older versions, it computes the offset from a pointer to an uninitialized parent, which
+/// can also be evaluated in `const` contexts, including `const fn`s, on rustc 1.65 and newer.
+/// On rustc 1.65 to 1.76, that excludes parents that are generic over a type parameter, such as
+/// `Self` in a `const` of a generic `impl`: the parameter might contain an `UnsafeCell`, and
+/// `const` evaluation can not point into one there.
+/// A macro can not tell whether it is expanded in a `const` context, so on older compilers such
+/// a use is rejected by rustc itself, with an error about the calls in the expansion.
+/// Either way, it expands to a single expression, so it can be used wherever one is expected,
+/// such as in a function argument, an enum discriminant, or a `const` operand of `asm!`.
+///
+/// No value of the parent type is ever created or dropped, so the parent may own resources,
+/// implement `Drop`, or be `!Send` or `!Unpin`.
+///
+/// On rustc 1.77 and newer, the parent may also be unsized, or generic over a `?Sized` last
+/// field, as long as the field itself comes before the unsized tail; for the tail, see
+/// `offset_of_tail!`.
+///
+/// A generic parent must be given with all of its arguments, or through an alias that supplies
+/// them, as in `offset_of!(Node<K, V>, _marker)` inside generic code: field offsets may depend
+/// on the arguments, even when the field itself is a `PhantomData` marker.
+/// A type alias may stand in for the parent anywhere, including a generic alias that fills in
+/// defaulted parameters, such as `offset_of!(V1<u32>, header.len)` for
+/// `type V1<T> = Message<T, Strict>`; the field is checked against the aliased struct.
+/// Inside an `impl` block, including a generic one, the parent may also be named `Self`.
+///
+/// Fields named with raw identifiers, such as the `r#type` fields emitted by bindgen, can be
+/// named as they are declared, as in `offset_of!(Request, r#type)`.
+///
+/// A field that is not part of the parent, such as a misspelled one, is a compile error that
+/// points at the field as written in the call.
+/// On rustc 1.77 and newer, that is the only error reported for it.
+///
+/// ```compile_fail
+/// use memoffset::offset_of;
+///
+/// struct Packet {
+///     len: u32,
+/// }
+///
+/// let offset = offset_of!(Packet, lenght);
+/// ```
+///
+/// ```compile_fail
+/// use memoffset::offset_of;
+///
+/// struct Pair(u8, u32);
+///
+/// let offset = offset_of!(Pair, 2);
+/// ```
+///
+/// With the `checked` cargo feature, debug builds also check that the field lies within the
+/// parent on rustc versions below 1.77, where the offset is computed through `raw_field!`.
+/// Newer versions forward to `core::mem::offset_of!`, so the compiler computes the offset.
+///
+/// Giving a reference or a raw pointer as the parent, such as `&Packet` or `*const Packet`, is
+/// a compile error that suggests naming the parent itself, as in `offset_of!(Packet, len)`.
+///
+/// ```compile_fail
+/// use memoffset::offset_of;
+///
+/// struct Packet {
+///     len: u32,
+/// }
+///
+/// let offset = offset_of!(&Packet, len);
+/// ```
+///
+/// ## Nested fields
+/// On rustc 1.82 and newer, the field may be a path through nested structs, tuple structs and
+/// union members, as in `offset_of!(Outer, inner.point.1)`.
+/// The result is the offset of the innermost field from the start of the parent.
+/// No segment of the path may go through a `Deref` impl, such as that of a `Box` field.
+///
+/// ```
+/// use memoffset::offset_of;
+///
+/// #[repr(C)]
+/// struct Point(u16, u16);
+///
+/// #[repr(C)]
+/// struct Inner {
+///     tag: u32,
+///     point: Point,
+/// }
+///
+/// #[repr(C)]
+/// struct Outer {
+///     len: u64,
+///     inner: Inner,
+/// }
+///
+/// assert_eq!(offset_of!(Outer, inner.point.1), 8 + 4 + 2);
+/// ```
+///
+/// ```compile_fail
+/// use memoffset::offset_of;
+///
+/// struct Inner {
+///     tag: u32,
+/// }
+///
+/// struct Outer {
+///     inner: Box<Inner>,
+/// }
+///
+/// let offset = offset_of!(Outer, inner.tag);
+/// ```
+///
+/// Each segment of the path must be a field of the type before it:
+///
+/// ```compile_fail
+/// use memoffset::offset_of;
+///
+/// struct Inner {
+///     tag: u32,
+/// }
+///
+/// struct Outer {
+///     inner: Inner,
+/// }
+///
+/// let offset = offset_of!(Outer, inner.tga);
+/// ```
+///
+/// ## Array elements
+/// A field, or nested path, of an array type may be followed by an index, as in
+/// `offset_of!(Frame, samples[3])` or `offset_of!(Outer, payload.as_bytes[3])`.
+/// The result is the offset of that element from the start of the parent.
+/// Indexing into a nested path requires rustc 1.82 or newer, as nested paths do.
+/// An index out of bounds of the array panics, or fails to compile in a `const` context.
+///
+/// ```
+/// use memoffset::offset_of;
+///
+/// #[repr(C)]
+/// union Payload {
+///     as_u64: u64,
+///     as_bytes: [u8; 8],
+/// }
+///
+/// #[repr(C)]
+/// struct Frame {
+///     len: u32,
+///     samples: [u16; 4],
+///     payload: Payload,
+/// }
+///
+/// assert_eq!(offset_of!(Frame, samples[3]), 4 + 3 * 2);
+/// assert_eq!(offset_of!(Frame, payload.as_bytes[3]), 16 + 3);
+/// ```
+///
+/// ## Array parents
+/// The parent may also be an array type, such as a vertex buffer or a ring of DMA descriptors,
+/// with an index in place of the field: `offset_of!([Vertex; 8], [3])` is the offset of the
+/// fourth element, that is, `3 * size_of::<Vertex>()`.
+/// If the elements are named by a path, the index may be followed by fields of the element,
+/// as in `offset_of!([Vertex; 8], [3].pos)`.
+/// As with array fields, an index out of bounds panics, or fails to compile in a `const`
+/// context.
+///
+/// ```
+/// use memoffset::offset_of;
+///
+/// #[repr(C)]
+/// struct Vertex {
+///     pos: [f32; 3],
+///     uv: [f32; 2],
+/// }
+///
+/// assert_eq!(offset_of!([Vertex; 8], [3]), 3 * 20);
+/// assert_eq!(offset_of!([Vertex; 8], [3].uv), 3 * 20 + 12);
+/// assert_eq!(offset_of!([Vertex; 8], [3].uv[1]), 3 * 20 + 12 + 4);
+/// assert_eq!(offset_of!([[u16; 4]; 2], [1]), 8);
+/// ```
+///
+/// ```compile_fail
+/// use memoffset::offset_of;
+///
+/// #[repr(C)]
+/// struct Descriptor {
+///     addr: u64,
+///     len: u32,
+///     flags: u32,
+/// }
+///
+/// const LAST: usize = offset_of!([Descriptor; 16], [16]);
+/// ```
+///
+/// ## Transparent wrappers
+/// A field whose type is a `repr(transparent)` wrapper can be seen through by naming the
+/// wrapped type, as in `offset_of!(Outer, fd as RawFd, field)`, even if the field of the
+/// wrapper is private.
+/// The result is the offset of the field of the wrapped type from the start of the parent.
+/// The wrapper must have the same size and alignment as the wrapped type, which is checked
+/// at compile time on rustc 1.79 and newer, and with a panic otherwise.
+/// `ManuallyDrop<T>` is such a wrapper, so a field `node: ManuallyDrop<Node>` is seen through
+/// as in `offset_of!(Entry, node as Node, next)`.
+///
+/// ```
+/// use memoffset::offset_of;
+///
+/// mod ffi {
+///     #[repr(C)]
+///     pub struct Stat {
+///         pub dev: u64,
+///         pub mode: u32,
+///     }
+///
+///     #[repr(transparent)]
+///     pub struct FileStat(Stat);
+/// }
+///
+/// #[repr(C)]
+/// struct Entry {
+///     id: u32,
+///     stat: ffi::FileStat,
+/// }
+///
+/// assert_eq!(offset_of!(Entry, stat as ffi::Stat, mode), 8 + 8);
+/// ```
+///
+/// ```compile_fail
+/// use memoffset::offset_of;
+///
+/// #[repr(C)]
+/// struct Header {
+///     len: u32,
+///     flags: u32,
+/// }
+///
+/// #[repr(C)]
+/// struct Tagged(u8, Header);
+///
+/// #[repr(C)]
+/// struct Entry {
+///     header: Tagged,
+/// }
+///
+/// const FLAGS: usize = offset_of!(Entry, header as Header, flags);
+/// ```
+///
+/// ## Enum variants
+/// The field of a variant of an enum is named by passing the variant as an extra argument,
+/// as in `offset_of!(Event, KeyPress, scancode)`, or `offset_of!(Event, Resize, 0)` for a
+/// tuple variant.
+/// The result is the offset of the field from the start of the whole enum, so it accounts
+/// for the discriminant.
+/// Naming a variant or a field that does not exist is a compile error, and so is naming a
+/// fieldless variant, which has no fields to point into.
+///
+/// The offsets of enum variant fields can only be computed by the compiler itself, so this form
+/// requires rustc 1.82 or newer, and the calling crate must currently enable the nightly
+/// `offset_of_enum` feature.
+/// Only enums with a `repr(C)` or primitive representation have a layout that can be relied
+/// upon across compilations.
+///
+/// ```ignore
+/// #![feature(offset_of_enum)]
+///
+/// use memoffset::offset_of;
+///
+/// #[repr(C)]
+/// enum Event {
+///     Quit,
+///     KeyPress { scancode: u16, repeat: u8 },
+///     Resize(u32, u64),
+/// }
+///
+/// assert_eq!(offset_of!(Event, KeyPress, repeat), 8 + 2);
+/// assert_eq!(offset_of!(Event, Resize, 1), 8 + 8);
+/// ```
+///
+/// An enum is not a parent by itself, so naming a field of it without a variant is a compile
+/// error, even if only one variant has a field of that name.
+/// The error points at the enum as written in the call: before rustc 1.77 it says that a struct
+/// was expected and an enum found, and since then that using enums in `offset_of!` is
+/// experimental.
+/// The macro can not tell enums apart from structs by their names, so it can not give a more
+/// specific error itself.
+///
+/// ```compile_fail
+/// use memoffset::offset_of;
+///
+/// enum Shape {
+///     Circle { radius: f32 },
+///     Square { side: f32 },
+/// }
+///
+/// offset_of!(Shape, radius);
+/// ```
+///
+/// The same goes for an enum behind a type alias, such as a bindgen type that turned out to be
+/// an enum:
+///
+/// ```compile_fail
+/// use memoffset::offset_of;
+///
+/// #[repr(C)]
+/// enum sockaddr_kind {
+///     Inet(u32),
+///     Unix([u8; 108]),
+/// }
+///
+/// type sockaddr = sockaddr_kind;
+///
+/// offset_of!(sockaddr, 0);
+/// ```
+///
+/// ## Qualified paths
+/// On rustc 1.77 and newer, the parent may also be named through an associated type, as
+/// `T::Assoc`, `<T>::Assoc` or `<T as Trait>::Assoc`, as long as it resolves to a known struct
+/// type.
+/// Inside a generic function, that means the associated type must be pinned down by a bound:
+///
+/// ```
+/// use memoffset::offset_of;
+///
+/// #[repr(C)]
+/// struct Header<Ext> {
+///     ext: Ext,
+///     seq: u32,
+/// }
+///
+/// trait Proto {
+///     type Ext;
+///     type Header;
+/// }
+///
+/// fn seq_offset<T: Proto<Header = Header<<T as Proto>::Ext>>>() -> usize {
+///     offset_of!(<T as Proto>::Header, seq)
+/// }
+///
+/// struct Small;
+/// impl Proto for Small {
+///     type Ext = u8;
+///     type Header = Header<u8>;
+/// }
+///
+/// assert_eq!(seq_offset::<Small>(), 4);
+/// ```
 #[macro_export(local_inner_macros)]
 macro_rules! offset_of {
-    ($parent:path, $field:tt) => {
+    (<$qself:ty as $trait:path>::$assoc:ident, $field:tt $(,)*) => {
+        _memoffset__offset_of_qualified_impl!(<$qself as $trait>::$assoc, $field)
+    };
+    (<$qself:ty>::$assoc:ident, $field:tt $(,)*) => {
+        _memoffset__offset_of_qualified_impl!(<$qself>::$assoc, $field)
+    };
+    ([$elem:ty; $len:expr], [$index:expr] $(,)*) => {
+        _memoffset__offset_of_array_impl!($elem, $len, $index)
+    };
+    ([$elem:path; $len:expr], [$index:expr] . $($rest:tt)+) => {
+        _memoffset__offset_of_array_impl!($elem, $len, $index) + offset_of!($elem, $($rest)+)
+    };
+    ($parent:path, $field:tt $(,)*) => {
         // Macro implementation is delegated to another macro to have a
         // single top-level macro to attach documentation to.
         _memoffset__offset_of_impl!($parent, $field)
     };
+    ($parent:path, $field:tt $(. $rest:tt)+ $(,)*) => {
+        _memoffset__offset_of_nested_impl!($parent, $field $(. $rest)+)
+    };
+    ($parent:path, $($field:tt).+ as $inner:path, $($rest:tt).+ $(,)*) => {
+        _memoffset__offset_of_transparent_impl!($parent, $($field).+, $inner, $($rest).+)
+    };
+    ($parent:path, $field:tt $(. $rest:tt)* [$index:expr] $(,)*) => {
+        _memoffset__offset_of_element_impl!($parent, $field $(. $rest)*, $index)
+    };
+    ($parent:path, $variant:ident, $field:tt $(. $rest:tt)* $(,)*) => {
+        _memoffset__offset_of_variant_impl!($parent, $variant, $field $(. $rest)*)
+    };
+    (&mut $parent:ty, $($rest:tt)+) => {
+        _memoffset__reject_parent!("a reference", offset_of, [], $parent, $($rest)+)
+    };
+    (& $parent:ty, $($rest:tt)+) => {
+        _memoffset__reject_parent!("a reference", offset_of, [], $parent, $($rest)+)
+    };
+    (*const $parent:ty, $($rest:tt)+) => {
+        _memoffset__reject_parent!("a pointer", offset_of, [], $parent, $($rest)+)
+    };
+    (*mut $parent:ty, $($rest:tt)+) => {
+        _memoffset__reject_parent!("a pointer", offset_of, [], $parent, $($rest)+)
+    };
+}
+
+/// Rejects a reference or a raw pointer given as the parent type, with an error that suggests
+/// naming the type it points to.
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__reject_parent {
+    ($kind:tt, $macro:ident, [$($before:tt)*], $parent:ty, $($rest:tt)+) => {
+        _memoffset__compile_error!(_memoffset__concat!(
+            "`",
+            _memoffset__stringify!($macro),
+            "!` takes the parent type itself, not ",
+            $kind,
+            " to it: use `",
+            _memoffset__stringify!($macro),
+            "!(",
+            $(_memoffset__stringify!($before), ", ",)*
+            _memoffset__stringify!($parent),
+            ", ",
+            _memoffset__stringify!($($rest)+),
+            ")`"
+        ))
+    };
 }
 
-#[cfg(tuple_ty)]
 #[cfg(not(stable_offset_of))]
 #[macro_export(local_inner_macros)]
 #[doc(hidden)]
-macro_rules! _memoffset__offset_of_tuple_impl {
+macro_rules! _memoffset__offset_of_unchecked_impl {
     ($parent:ty, $field:tt) => {{
-        // Get a base pointer (non-dangling if rustc supports `MaybeUninit`).
+        // Get a (non-dangling) base pointer.
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        // Get field pointer.
+        let field_ptr = raw_field_unchecked!(base_ptr, $parent, $field);
+        // Compute offset.
+        _memoffset_offset_from_unsafe!(field_ptr, base_ptr)
+    }};
+}
+#[cfg(stable_offset_of)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__offset_of_unchecked_impl {
+    ($parent:ty, $field:tt) => {{
+        $crate::__priv::mem::offset_of!($parent, $field)
+    }};
+}
+
+/// Calculates the offset of the specified field from the start of the named type,
+/// without checking that the field is part of the type.
+///
+/// This is a last resort for macro authors whose generated field names are rejected by the
+/// check that `offset_of!` performs, and for parent types the check does not support, such
+/// as unions.
+/// Prefer `offset_of!` wherever it works.
+///
+/// ## Examples
+/// ```
+/// use memoffset::offset_of_unchecked;
+///
+/// #[repr(C)]
+/// union Value {
+///     int: u32,
+///     bytes: [u8; 4],
+/// }
+///
+/// #[repr(C)]
+/// struct Tagged {
+///     tag: u8,
+///     value: Value,
+/// }
+///
+/// // Safety: `bytes` and `value` are fields of the unions and structs themselves.
+/// assert_eq!(unsafe { offset_of_unchecked!(Value, bytes) }, 0);
+/// assert_eq!(unsafe { offset_of_unchecked!(Tagged, value) }, 4);
+/// ```
+///
+/// The parent may be any type, including one that a macro expands to, which `offset_of!` does
+/// not accept:
+///
+/// ```compile_fail
+/// use memoffset::offset_of;
+///
+/// #[repr(C)]
+/// struct Tagged {
+///     tag: u8,
+///     value: u32,
+/// }
+///
+/// macro_rules! generated_type {
+///     () => { Tagged };
+/// }
+///
+/// let offset = offset_of!(generated_type!(), value);
+/// ```
+///
+/// ## Safety
+/// On rustc versions below 1.77, this macro relies on `raw_field_unchecked!`: if the field is
+/// reached through a `Deref` impl of the parent, it reads uninitialized memory.
+/// On newer versions it forwards to `core::mem::offset_of!`, which never goes through `Deref`.
+/// The caller has to make sure that the field is a direct field of the parent either way, so
+/// the macro must be used in an `unsafe` block on every version.
+#[macro_export(local_inner_macros)]
+macro_rules! offset_of_unchecked {
+    ($parent:ty, $field:tt $(,)*) => {{
+        $crate::__priv::assume_direct_field();
+        // Macro implementation is delegated to another macro to have a
+        // single top-level macro to attach documentation to.
+        _memoffset__offset_of_unchecked_impl!($parent, $field)
+    }};
+}
+
+#[cfg(tuple_ty)]
+#[cfg(not(stable_offset_of))]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__offset_of_tuple_impl {
+    ($parent:ty, $field:tt) => {{
+        // Get a (non-dangling) base pointer.
         _memoffset__let_base_ptr!(base_ptr, $parent);
         // Get field pointer.
         let field_ptr = raw_field_tuple!(base_ptr, $parent, $field);
@@ -160,7 +894,7 @@ macro_rules! _memoffset__offset_of_tuple_impl {
 #[cfg(tuple_ty)]
 #[macro_export(local_inner_macros)]
 macro_rules! offset_of_tuple {
-    ($parent:ty, $field:tt) => {{
+    ($parent:ty, $field:tt $(,)*) => {{
         // Macro implementation is delegated to another macro to have a
         // single top-level macro to attach documentation to.
         _memoffset__offset_of_tuple_impl!($parent, $field)
@@ -172,7 +906,7 @@ macro_rules! offset_of_tuple {
 #[doc(hidden)]
 macro_rules! _memoffset__offset_of_union_impl {
     ($parent:path, $field:tt) => {{
-        // Get a base pointer (non-dangling if rustc supports `MaybeUninit`).
+        // Get a (non-dangling) base pointer.
         _memoffset__let_base_ptr!(base_ptr, $parent);
         // Get field pointer.
         let field_ptr = raw_field_union!(base_ptr, $parent, $field);
@@ -205,25 +939,840 @@ macro_rules! _memoffset__offset_of_union_impl {
 /// assert!(offset_of_union!(Foo, foo64) == 0);
 /// ```
 ///
+/// The offset is a matter of layout only: it says nothing about which member is active, and
+/// reading a member that is not active is up to the caller to avoid.
+/// Members of a union nested in a struct, and elements of their arrays, can be reached with a
+/// nested path in `offset_of!` instead, as in `offset_of!(Outer, payload.as_bytes[3])`.
+///
 /// ## Note
 /// Due to `macro_rules!` limitations, this macro will accept structs with a single field as well as unions.
 /// This is not a stable guarantee, and future versions of this crate might fail
 /// on any use of this macro with a struct, without a semver bump.
 #[macro_export(local_inner_macros)]
 macro_rules! offset_of_union {
-    ($parent:path, $field:tt) => {{
+    ($parent:path, $field:tt $(,)*) => {{
         // Macro implementation is delegated to another macro to have a
         // single top-level macro to attach documentation to.
         _memoffset__offset_of_union_impl!($parent, $field)
     }};
 }
 
+/// Calculates the offset of the specified field from the start of the named struct, as a value
+/// of the given integer type, such as `u32` for an FFI table.
+///
+/// Panics if the offset does not fit in the integer type, instead of silently truncating it.
+/// On rustc 1.65 and newer, this can be used in constants, where that is a compile-time error.
+///
+/// ## Examples
+/// ```
+/// use memoffset::offset_of_as;
+///
+/// #[repr(C)]
+/// struct Foo {
+///     a: u32,
+///     b: [u8; 300],
+///     c: u8,
+/// }
+///
+/// assert_eq!(offset_of_as!(Foo, c, u32), 304u32);
+/// assert_eq!(offset_of_as!(Foo, b, i8), 4i8);
+/// ```
+///
+/// ```should_panic
+/// use memoffset::offset_of_as;
+///
+/// #[repr(C)]
+/// struct Foo {
+///     a: u32,
+///     b: [u8; 300],
+///     c: u8,
+/// }
+///
+/// let c = offset_of_as!(Foo, c, u8);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! offset_of_as {
+    ($parent:path, $($field:tt).+, $ty:ty $(,)*) => {{
+        let offset = offset_of!($parent, $($field).+);
+        let value = offset as $ty;
+        $crate::__priv::assert_cast_fits(offset, value as usize);
+        value
+    }};
+}
+
+/// Calculates the offset of the unsized tail field of a struct, such as a trailing slice,
+/// given a pointer to a value of the struct.
+///
+/// A value of an unsized struct can not be made up out of nothing, so unlike `offset_of!`, this
+/// needs a `base` pointer, which carries the metadata of the tail.
+/// For a slice tail, the offset does not depend on the length; for a trait object tail, it
+/// depends on the alignment of the actual type behind it.
+///
+/// The `base` pointer *must not* be dangling, but it *may* point to
+/// uninitialized memory.
+///
+/// The sized fields before the tail can be passed to `offset_of!` directly, on rustc 1.77 and
+/// newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::{offset_of, offset_of_tail};
+/// use std::ptr;
+///
+/// #[repr(C)]
+/// struct Packet {
+///     len: u32,
+///     flags: u16,
+///     data: [u8],
+/// }
+///
+/// let buf = [0u32; 4];
+/// let packet = ptr::slice_from_raw_parts(buf.as_ptr() as *const u8, 0) as *const Packet;
+///
+/// assert_eq!(offset_of!(Packet, flags), 4);
+/// assert_eq!(offset_of_tail!(packet, Packet, data), 6);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! offset_of_tail {
+    ($base:expr, $parent:path, $field:tt $(,)*) => {{
+        let base: *const $parent = $base;
+        let field = raw_field_tail!(base, $parent, $field);
+        _memoffset_offset_from_unsafe!(field as *const u8, base as *const u8)
+    }};
+}
+
 #[cfg(test)]
 mod tests {
     #![cfg_attr(allow_clippy, allow(clippy::identity_op))] // For `... + 0` constructs below.
 
     #[test]
-    fn offset_simple() {
+    fn offset_simple() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: [u8; 2],
+            c: i64,
+        }
+
+        assert_eq!(offset_of!(Foo, a), 0);
+        assert_eq!(offset_of!(Foo, b), 4);
+        assert_eq!(offset_of!(Foo, c), 8);
+    }
+
+    #[test]
+    fn offset_simple_packed() {
+        #[repr(C, packed)]
+        struct Foo {
+            a: u32,
+            b: [u8; 2],
+            c: i64,
+        }
+
+        assert_eq!(offset_of!(Foo, a), 0);
+        assert_eq!(offset_of!(Foo, b), 4);
+        assert_eq!(offset_of!(Foo, c), 6);
+    }
+
+    #[test]
+    fn tuple_struct() {
+        #[repr(C)]
+        struct Tup(i32, i32);
+
+        assert_eq!(offset_of!(Tup, 0), 0);
+        assert_eq!(offset_of!(Tup, 1), 4);
+    }
+
+    #[test]
+    fn offset_union() {
+        // Since we're specifying repr(C), all fields are supposed to be at offset 0
+        #[repr(C)]
+        union Foo {
+            a: u32,
+            b: [u8; 2],
+            c: i64,
+        }
+
+        assert_eq!(offset_of_union!(Foo, a), 0);
+        assert_eq!(offset_of_union!(Foo, b), 0);
+        assert_eq!(offset_of_union!(Foo, c), 0);
+    }
+
+    #[test]
+    fn path() {
+        mod sub {
+            #[repr(C)]
+            pub struct Foo {
+                pub x: u32,
+            }
+        }
+
+        assert_eq!(offset_of!(sub::Foo, x), 0);
+    }
+
+    #[test]
+    fn module_paths() {
+        mod sub {
+            #[repr(C)]
+            pub struct Foo {
+                pub x: u32,
+                pub y: u16,
+            }
+
+            #[repr(C)]
+            pub struct Pair(pub u8, pub u32);
+
+            pub mod inner {
+                pub fn y_offset() -> usize {
+                    offset_of!(super::Foo, y)
+                }
+            }
+        }
+
+        assert_eq!(offset_of!(sub::Foo, y), 4);
+        assert_eq!(offset_of!(sub::Pair, 1), 4);
+        assert_eq!(sub::inner::y_offset(), 4);
+    }
+
+    #[test]
+    fn crate_path() {
+        assert_eq!(offset_of!(crate::offset_of::tests::View<'static>, len), 0);
+        assert_eq!(
+            offset_of!(crate::offset_of::tests::View, ptr),
+            core::mem::size_of::<usize>()
+        );
+    }
+
+    #[cfg(stable_offset_of)]
+    #[test]
+    fn self_qualified_path() {
+        #[repr(C)]
+        struct Header {
+            kind: u8,
+            seq: u32,
+        }
+
+        trait Proto {
+            type Header;
+
+            fn seq_offsets() -> (usize, usize);
+            fn seq_ptr(header: &Self::Header) -> *const u32;
+        }
+
+        struct Small;
+
+        impl Proto for Small {
+            type Header = Header;
+
+            fn seq_offsets() -> (usize, usize) {
+                (
+                    offset_of!(<Self>::Header, seq),
+                    offset_of!(Self::Header, seq),
+                )
+            }
+
+            fn seq_ptr(header: &Header) -> *const u32 {
+                raw_field!(header as *const Header, <Self>::Header, seq)
+            }
+        }
+
+        let header = Header { kind: 1, seq: 2 };
+        assert_eq!(Small::seq_offsets(), (4, 4));
+        assert_eq!(Small::seq_ptr(&header), &header.seq as *const u32);
+        assert_eq!(header.kind, 1);
+    }
+
+    #[test]
+    fn inside_generic_method() {
+        struct Pair<T, U>(T, U);
+
+        fn foo<T, U>(_: Pair<T, U>) -> usize {
+            offset_of!(Pair<T, U>, 1)
+        }
+
+        assert_eq!(foo(Pair(0, 0)), 4);
+    }
+
+    // Const generics, like `addr_of!`, require rustc 1.51.
+    #[cfg(raw_ref_macros)]
+    #[test]
+    fn const_generic_arguments() {
+        #[repr(C)]
+        struct Block<const N: usize> {
+            header: u32,
+            data: [u8; N],
+            tail: u16,
+        }
+
+        fn tail_offset<const N: usize>() -> usize {
+            offset_of!(Block<N>, tail)
+        }
+
+        assert_eq!(offset_of!(Block<64>, data), 4);
+        assert_eq!(offset_of!(Block<64>, tail), 68);
+        assert_eq!(offset_of!(Block<{ 2 + 3 }>, tail), 10);
+        assert_eq!(tail_offset::<1>(), 6);
+        assert_eq!(tail_offset::<100>(), 104);
+    }
+
+    #[repr(C)]
+    struct View<'a> {
+        len: usize,
+        ptr: &'a u8,
+    }
+
+    impl<'a> View<'a> {
+        fn ptr_offset() -> usize {
+            offset_of!(View<'a>, ptr)
+        }
+    }
+
+    #[cfg(any(stable_offset_of, stable_const))]
+    const VIEW_PTR_OFFSET: usize = offset_of!(View<'static>, ptr);
+
+    #[test]
+    fn lifetime_parameters() {
+        let word = core::mem::size_of::<usize>();
+        assert_eq!(offset_of!(View<'static>, len), 0);
+        assert_eq!(offset_of!(View<'static>, ptr), word);
+        assert_eq!(View::ptr_offset(), word);
+        #[cfg(any(stable_offset_of, stable_const))]
+        assert_eq!(VIEW_PTR_OFFSET, word);
+    }
+
+    #[test]
+    fn generic_arguments() {
+        #[repr(C)]
+        struct Pair<A, B> {
+            first: A,
+            second: B,
+        }
+
+        #[repr(C)]
+        struct Triple<A, B, C> {
+            a: A,
+            b: B,
+            c: C,
+        }
+
+        assert_eq!(offset_of!(Pair<u8, u16>, second), 2);
+        assert_eq!(offset_of!(Pair::<u8, u16>, second), 2);
+        assert_eq!(offset_of!(Triple<u8, u16, u32>, c), 4);
+        assert_eq!(offset_of!(Pair<Pair<u8, u64>, u16>, second), 16);
+        assert_eq!(offset_of!(Triple<u8, Pair<u16, u32>, u8>, c), 12);
+    }
+
+    #[test]
+    fn generic_aliases() {
+        mod proto {
+            pub struct Strict;
+
+            #[repr(C)]
+            pub struct Header {
+                pub version: u8,
+                pub len: u32,
+            }
+
+            #[repr(C)]
+            pub struct Message<T, P = Strict> {
+                pub header: Header,
+                pub body: [T; 4],
+                pub policy: core::marker::PhantomData<P>,
+            }
+
+            pub type V2<T> = Message<T, ()>;
+        }
+
+        type V1<T> = proto::Message<T>;
+        type Lenient<T, P = ()> = proto::Message<T, P>;
+        type Batch<T> = proto::Message<V1<T>>;
+
+        assert_eq!(offset_of!(V1<u16>, body), 8);
+        assert_eq!(offset_of!(V1::<u16>, body[3]), 14);
+        assert_eq!(offset_of!(Lenient<u64>, body[1]), 16);
+        assert_eq!(offset_of!(Lenient<u8, proto::Strict>, policy), 12);
+        assert_eq!(offset_of!(proto::V2<u32>, body[2]), 16);
+        assert_eq!(offset_of!(proto::V2<u8>, policy), 12);
+        assert_eq!(offset_of!(Batch<u8>, body[2]), 8 + 2 * 12);
+        assert_eq!(unsafe { offset_of_unchecked!(Batch<u8>, body) }, 8);
+
+        #[cfg(nested_offset_of)]
+        {
+            assert_eq!(offset_of!(V1<u8>, header.len), 4);
+            assert_eq!(offset_of!(proto::V2<u8>, header.len), 4);
+            assert_eq!(offset_of!(Batch<u8>, header.len), 4);
+        }
+
+        let batch: Batch<u8> = unsafe { core::mem::zeroed() };
+        let base = &batch as *const Batch<u8>;
+        let body = raw_field!(base, Batch<u8>, body);
+        assert_eq!(body, &batch.body as *const [V1<u8>; 4]);
+        assert_eq!(container_of!(body, Batch<u8>, body), base);
+    }
+
+    #[cfg(raw_ref_macros)]
+    #[test]
+    fn unsized_tail() {
+        use core::fmt::Debug;
+
+        #[repr(C)]
+        struct Packet {
+            len: u32,
+            flags: u16,
+            data: [u8],
+        }
+
+        #[repr(C)]
+        struct Tagged<T: ?Sized> {
+            tag: u8,
+            value: T,
+        }
+
+        let buf = [0xABAB_ABABu32; 4];
+        let base = buf.as_ptr() as *const u8;
+        let packet = core::ptr::slice_from_raw_parts(base, 7) as *const Packet;
+        let tail = raw_field_tail!(packet, Packet, data);
+        assert_eq!(offset_of_tail!(packet, Packet, data), 6);
+        assert_eq!(tail as *const u8 as usize, base as usize + 6);
+        assert_eq!(unsafe { (&*tail).len() }, 7);
+
+        let small = Tagged {
+            tag: 1,
+            value: 2u16,
+        };
+        let large = Tagged {
+            tag: 1,
+            value: 2u64,
+        };
+        let small: &Tagged<dyn Debug> = &small;
+        let large: &Tagged<dyn Debug> = &large;
+        assert_eq!(offset_of_tail!(small, Tagged<dyn Debug>, value), 2);
+        assert_eq!(offset_of_tail!(large, Tagged<dyn Debug>, value), 8);
+        let value = raw_field_tail!(large, Tagged<dyn Debug>, value);
+        assert_eq!(
+            value as *const u8,
+            &large.value as *const dyn Debug as *const u8
+        );
+    }
+
+    #[cfg(stable_offset_of)]
+    #[test]
+    fn unsized_prefix() {
+        #[repr(C)]
+        struct Packet {
+            len: u32,
+            flags: u16,
+            data: [u8],
+        }
+
+        assert_eq!(offset_of!(Packet, len), 0);
+        assert_eq!(offset_of!(Packet, flags), 4);
+    }
+
+    #[cfg(stable_offset_of)]
+    #[test]
+    fn unsized_generic_parent() {
+        use core::fmt::Debug;
+        use core::sync::atomic::AtomicUsize;
+
+        #[repr(C)]
+        struct Wrapper<T: ?Sized> {
+            refcount: AtomicUsize,
+            weak: u32,
+            value: T,
+        }
+
+        impl<T: ?Sized> Wrapper<T> {
+            fn weak_offset() -> usize {
+                offset_of!(Wrapper<T>, weak)
+            }
+        }
+
+        let word = core::mem::size_of::<usize>();
+        assert_eq!(Wrapper::<[u8]>::weak_offset(), word);
+        assert_eq!(Wrapper::<dyn Debug>::weak_offset(), word);
+        assert_eq!(Wrapper::<u64>::weak_offset(), word);
+        assert_eq!(offset_of!(Wrapper<str>, refcount), 0);
+    }
+
+    #[test]
+    fn generic_marker_fields() {
+        use core::marker::PhantomData;
+
+        #[repr(C)]
+        struct Node<K, V> {
+            key: u32,
+            value: u64,
+            _marker: PhantomData<(K, V)>,
+        }
+
+        type ByteNode<K> = Node<K, u8>;
+        type Concrete = Node<u16, u8>;
+
+        fn marker_offsets<K, V>() -> (usize, usize) {
+            (
+                offset_of!(Node<K, V>, _marker),
+                offset_of!(ByteNode<K>, _marker),
+            )
+        }
+
+        assert_eq!(offset_of!(Concrete, _marker), 16);
+        assert_eq!(offset_of!(ByteNode<i8>, _marker), 16);
+        assert_eq!(marker_offsets::<u8, u64>(), (16, 16));
+    }
+
+    #[test]
+    fn matches_pointer_offsets() {
+        // Field ranges are always computed from raw pointers, even where `offset_of!` forwards
+        // to the built-in macro, so the two must agree.
+        #[repr(C)]
+        struct Plain {
+            a: u8,
+            b: u64,
+            c: u16,
+        }
+
+        #[repr(C, packed)]
+        struct Packed {
+            a: u8,
+            b: u64,
+            c: u16,
+        }
+
+        #[repr(C)]
+        struct Tuple(u8, u32, [u16; 3]);
+
+        #[repr(C)]
+        struct Generic<T> {
+            a: u8,
+            b: T,
+            c: (),
+        }
+
+        struct Rust {
+            a: u8,
+            b: u64,
+            c: u16,
+        }
+
+        macro_rules! check {
+            ($($parent:path, $field:tt;)*) => {$(
+                assert_eq!(offset_of!($parent, $field), _memoffset__field_range!($parent, $field).0);
+            )*};
+        }
+
+        check! {
+            Plain, a; Plain, b; Plain, c;
+            Packed, a; Packed, b; Packed, c;
+            Tuple, 0; Tuple, 1; Tuple, 2;
+            Generic<u32>, b; Generic<[u64; 2]>, c;
+            Rust, a; Rust, b; Rust, c;
+        }
+    }
+
+    // Without `addr_of!`, projecting from the fallback pointer creates references, which may be
+    // unaligned for the packed struct.
+    #[cfg(maybe_uninit)]
+    #[test]
+    #[cfg_attr(all(miri, not(raw_ref_macros)), ignore)]
+    fn fallback_base_ptr() {
+        #[repr(C)]
+        struct Plain {
+            a: u8,
+            b: u64,
+            c: u16,
+        }
+
+        #[repr(C, packed)]
+        struct Packed {
+            a: u8,
+            b: u64,
+            c: u16,
+        }
+
+        #[repr(C)]
+        struct Niche {
+            flag: bool,
+            target: &'static u32,
+            tail: [u8; 3],
+        }
+
+        #[repr(C, align(32))]
+        struct Aligned {
+            a: u8,
+            b: [u16; 5],
+        }
+
+        struct Owned {
+            a: u8,
+            b: core::cell::Cell<u64>,
+            c: Option<&'static str>,
+        }
+
+        impl Drop for Owned {
+            fn drop(&mut self) {}
+        }
+
+        #[repr(C)]
+        struct Empty {
+            a: (),
+            b: [u64; 0],
+        }
+
+        macro_rules! check {
+            ($($parent:path, $field:tt;)*) => {$({
+                _memoffset__let_fallback_base_ptr!(base_ptr, $parent);
+                assert_eq!(base_ptr as usize % core::mem::align_of::<$parent>(), 0);
+                let field_ptr = raw_field!(base_ptr, $parent, $field);
+                assert_eq!(field_ptr as usize - base_ptr as usize, offset_of!($parent, $field));
+            })*};
+        }
+
+        check! {
+            Plain, a; Plain, b; Plain, c;
+            Packed, a; Packed, b; Packed, c;
+            Niche, flag; Niche, target; Niche, tail;
+            Aligned, a; Aligned, b;
+            Owned, a; Owned, b; Owned, c;
+            Empty, a; Empty, b;
+        }
+    }
+
+    #[cfg(tuple_ty)]
+    #[test]
+    fn test_tuple_offset() {
+        let f = (0i32, 0.0f32, 0u8);
+        let f_ptr = &f as *const _;
+        let f1_ptr = &f.1 as *const _;
+
+        assert_eq!(
+            f1_ptr as usize - f_ptr as usize,
+            offset_of_tuple!((i32, f32, u8), 1)
+        );
+    }
+
+    #[cfg(all(feature = "checked", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "field `inner.b` is not inside its parent `Outer`")]
+    fn checked_field_outside_parent() {
+        _memoffset__check_field!(Outer, (inner.b), 12, 16, 8);
+    }
+
+    #[test]
+    fn test_raw_field() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: [u8; 2],
+            c: i64,
+        }
+
+        let f: Foo = Foo {
+            a: 0,
+            b: [0, 0],
+            c: 0,
+        };
+        let f_ptr = &f as *const _;
+        assert_eq!(f_ptr as usize + 0, raw_field!(f_ptr, Foo, a) as usize);
+        assert_eq!(f_ptr as usize + 4, raw_field!(f_ptr, Foo, b) as usize);
+        assert_eq!(f_ptr as usize + 8, raw_field!(f_ptr, Foo, c) as usize);
+    }
+
+    #[cfg(tuple_ty)]
+    #[test]
+    fn test_raw_field_tuple() {
+        let t = (0u32, 0u8, false);
+        let t_ptr = &t as *const _;
+        let t_addr = t_ptr as usize;
+
+        assert_eq!(
+            &t.0 as *const _ as usize - t_addr,
+            raw_field_tuple!(t_ptr, (u32, u8, bool), 0) as usize - t_addr
+        );
+        assert_eq!(
+            &t.1 as *const _ as usize - t_addr,
+            raw_field_tuple!(t_ptr, (u32, u8, bool), 1) as usize - t_addr
+        );
+        assert_eq!(
+            &t.2 as *const _ as usize - t_addr,
+            raw_field_tuple!(t_ptr, (u32, u8, bool), 2) as usize - t_addr
+        );
+    }
+
+    #[test]
+    fn test_raw_field_union() {
+        #[repr(C)]
+        union Foo {
+            a: u32,
+            b: [u8; 2],
+            c: i64,
+        }
+
+        let f = Foo { a: 0 };
+        let f_ptr = &f as *const _;
+        assert_eq!(f_ptr as usize + 0, raw_field_union!(f_ptr, Foo, a) as usize);
+        assert_eq!(f_ptr as usize + 0, raw_field_union!(f_ptr, Foo, b) as usize);
+        assert_eq!(f_ptr as usize + 0, raw_field_union!(f_ptr, Foo, c) as usize);
+    }
+
+    #[cfg(any(stable_offset_of, stable_const))]
+    #[test]
+    fn const_offset() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: [u8; 2],
+            c: i64,
+        }
+
+        assert_eq!([0; offset_of!(Foo, b)].len(), 4);
+    }
+
+    #[cfg(stable_offset_of)]
+    #[test]
+    fn const_offset_interior_mutable() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: core::cell::Cell<u32>,
+        }
+
+        assert_eq!([0; offset_of!(Foo, b)].len(), 4);
+    }
+
+    #[cfg(any(stable_offset_of, stable_const))]
+    #[test]
+    fn const_fn_offset() {
+        const fn test_fn() -> usize {
+            #[repr(C)]
+            struct Foo {
+                a: u32,
+                b: [u8; 2],
+                c: i64,
+            }
+
+            offset_of!(Foo, b)
+        }
+
+        assert_eq!([0; test_fn()].len(), 4);
+    }
+
+    #[cfg(any(stable_offset_of, stable_const))]
+    #[test]
+    fn const_fn_array_lengths() {
+        #[repr(C)]
+        struct Frame {
+            len: u32,
+            kind: u16,
+            payload: [u8; 8],
+        }
+
+        const fn header_len() -> usize {
+            offset_of!(Frame, payload)
+        }
+
+        const fn trailer_len(frames: usize) -> usize {
+            frames * (offset_of!(Frame, payload) - offset_of!(Frame, kind))
+        }
+
+        struct Header {
+            bytes: [u8; header_len()],
+        }
+
+        static TRAILER: [u8; trailer_len(2)] = [0; trailer_len(2)];
+
+        let header = Header {
+            bytes: [0; header_len()],
+        };
+        assert_eq!(header.bytes.len(), 6);
+        assert_eq!(TRAILER.len(), 4);
+    }
+
+    #[cfg(any(stable_offset_of, stable_const))]
+    #[test]
+    fn const_assoc_offset() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: [u8; 2],
+            c: i64,
+        }
+
+        impl Foo {
+            const C_OFFSET: usize = offset_of!(Foo, c);
+        }
+
+        assert_eq!([0; Foo::C_OFFSET].len(), 8);
+    }
+
+    #[cfg(any(stable_offset_of, stable_const))]
+    #[test]
+    fn const_static_offsets() {
+        #[repr(C)]
+        struct Regs {
+            ctrl: u32,
+            status: u16,
+            data: [u8; 6],
+            irq: u64,
+        }
+
+        struct Descriptor {
+            name: &'static str,
+            offset: usize,
+        }
+
+        static STATUS_OFFSET: usize = offset_of!(Regs, status);
+        const DATA_OFFSET: usize = offset_of!(Regs, data);
+        static OFFSETS: [usize; 3] = [
+            offset_of!(Regs, ctrl),
+            offset_of!(Regs, data),
+            offset_of!(Regs, irq),
+        ];
+        static DESCRIPTORS: [Descriptor; 2] = [
+            Descriptor {
+                name: "status",
+                offset: offset_of!(Regs, status),
+            },
+            Descriptor {
+                name: "irq",
+                offset: offset_of!(Regs, irq),
+            },
+        ];
+
+        assert_eq!(STATUS_OFFSET, 4);
+        assert_eq!(DATA_OFFSET, 6);
+        assert_eq!(OFFSETS, [0, 6, 16]);
+        assert_eq!(DESCRIPTORS[0].name, "status");
+        assert_eq!(DESCRIPTORS[0].offset, 4);
+        assert_eq!(DESCRIPTORS[1].name, "irq");
+        assert_eq!(DESCRIPTORS[1].offset, 16);
+    }
+
+    #[test]
+    fn expression_positions() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: [u8; 2],
+            c: i64,
+        }
+
+        fn add(a: usize, b: usize) -> usize {
+            a + b
+        }
+
+        assert_eq!(add(offset_of!(Foo, b), offset_of!(Foo, c)), 12);
+        assert_eq!(
+            match offset_of!(Foo, c) {
+                8 => offset_of!(Foo, b),
+                _ => 0,
+            },
+            4
+        );
+    }
+
+    #[cfg(any(stable_offset_of, stable_const))]
+    #[test]
+    fn const_expression_positions() {
         #[repr(C)]
         struct Foo {
             a: u32,
@@ -231,87 +1780,211 @@ mod tests {
             c: i64,
         }
 
-        assert_eq!(offset_of!(Foo, a), 0);
-        assert_eq!(offset_of!(Foo, b), 4);
-        assert_eq!(offset_of!(Foo, c), 8);
+        struct Layout {
+            b: usize,
+            c: usize,
+        }
+
+        #[repr(usize)]
+        enum Field {
+            B = offset_of!(Foo, b),
+            C = offset_of!(Foo, c),
+        }
+
+        const LAYOUT: Layout = Layout {
+            b: offset_of!(Foo, b),
+            c: offset_of!(Foo, c),
+        };
+
+        assert_eq!((LAYOUT.b, LAYOUT.c), (4, 8));
+        assert_eq!(Field::B as usize, 4);
+        assert_eq!(Field::C as usize, 8);
     }
 
+    // `const` operands of `asm!` require rustc 1.82.
+    #[cfg(all(nested_offset_of, target_arch = "x86_64", not(miri)))]
     #[test]
-    fn offset_simple_packed() {
-        #[repr(C, packed)]
+    fn asm_const_operand() {
+        #[repr(C)]
         struct Foo {
             a: u32,
             b: [u8; 2],
             c: i64,
         }
 
-        assert_eq!(offset_of!(Foo, a), 0);
-        assert_eq!(offset_of!(Foo, b), 4);
-        assert_eq!(offset_of!(Foo, c), 6);
+        let offset: usize;
+        unsafe {
+            core::arch::asm!(
+                "mov {0}, {1}",
+                out(reg) offset,
+                const offset_of!(Foo, c),
+                options(nomem, nostack, pure),
+            );
+        }
+        assert_eq!(offset, 8);
     }
 
     #[test]
-    fn tuple_struct() {
+    fn self_parent() {
         #[repr(C)]
-        struct Tup(i32, i32);
+        struct Packet {
+            len: u32,
+            payload: [u8; 4],
+        }
 
-        assert_eq!(offset_of!(Tup, 0), 0);
-        assert_eq!(offset_of!(Tup, 1), 4);
+        impl Packet {
+            fn payload_offset() -> usize {
+                offset_of!(Self, payload)
+            }
+        }
+
+        #[repr(C)]
+        struct Container<T> {
+            tag: u8,
+            value: T,
+        }
+
+        impl<T> Container<T> {
+            fn value_offset() -> usize {
+                offset_of!(Self, value)
+            }
+        }
+
+        #[repr(C)]
+        struct Pair(u8, u32);
+
+        impl Pair {
+            fn second_offset() -> usize {
+                offset_of!(Self, 1)
+            }
+        }
+
+        assert_eq!(Packet::payload_offset(), 4);
+        assert_eq!(Container::<u8>::value_offset(), 1);
+        assert_eq!(
+            Container::<u64>::value_offset(),
+            core::mem::align_of::<u64>()
+        );
+        assert_eq!(Pair::second_offset(), 4);
     }
 
+    // On older versions, `const` evaluation can not point into a parent that is generic over a
+    // type parameter, which might contain an `UnsafeCell`.
+    #[cfg(stable_offset_of)]
     #[test]
-    fn offset_union() {
-        // Since we're specifying repr(C), all fields are supposed to be at offset 0
+    fn const_self_parent() {
         #[repr(C)]
-        union Foo {
+        struct Container<T> {
+            tag: u8,
+            value: T,
+        }
+
+        impl<T> Container<T> {
+            const VALUE_OFFSET: usize = offset_of!(Self, value);
+        }
+
+        assert_eq!(Container::<u16>::VALUE_OFFSET, 2);
+        assert_eq!([0; Container::<u32>::VALUE_OFFSET].len(), 4);
+    }
+
+    #[test]
+    fn offset_as() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: [u8; 300],
+            c: u8,
+        }
+
+        assert_eq!(offset_of_as!(Foo, b, u8), 4u8);
+        assert_eq!(offset_of_as!(Foo, c, u16), 304u16);
+        assert_eq!(offset_of_as!(Foo, c, i64,), 304i64);
+        assert_eq!(offset_of_as!(Foo, c, usize), offset_of!(Foo, c));
+    }
+
+    #[test]
+    #[should_panic]
+    fn offset_as_overflow() {
+        #[repr(C)]
+        struct Foo {
+            a: [u8; 200],
+            b: u8,
+        }
+
+        let _ = offset_of_as!(Foo, b, i8);
+    }
+
+    #[cfg(stable_const)]
+    #[test]
+    fn const_offset_as() {
+        #[repr(C)]
+        struct Foo {
             a: u32,
             b: [u8; 2],
             c: i64,
         }
 
-        assert_eq!(offset_of_union!(Foo, a), 0);
-        assert_eq!(offset_of_union!(Foo, b), 0);
-        assert_eq!(offset_of_union!(Foo, c), 0);
+        const C_OFFSET: u32 = offset_of_as!(Foo, c, u32);
+        assert_eq!(C_OFFSET, 8);
     }
 
     #[test]
-    fn path() {
-        mod sub {
-            #[repr(C)]
-            pub struct Foo {
-                pub x: u32,
-            }
+    fn offset_unchecked() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: [u8; 2],
+            c: i64,
         }
 
-        assert_eq!(offset_of!(sub::Foo, x), 0);
+        assert_eq!(unsafe { offset_of_unchecked!(Foo, a) }, offset_of!(Foo, a));
+        assert_eq!(unsafe { offset_of_unchecked!(Foo, b) }, offset_of!(Foo, b));
+        assert_eq!(unsafe { offset_of_unchecked!(Foo, c) }, offset_of!(Foo, c));
     }
 
     #[test]
-    fn inside_generic_method() {
-        struct Pair<T, U>(T, U);
-
-        fn foo<T, U>(_: Pair<T, U>) -> usize {
-            offset_of!(Pair<T, U>, 1)
+    fn offset_unchecked_union() {
+        // `offset_of!` cannot check union fields with a struct pattern.
+        #[repr(C)]
+        union Foo {
+            a: u32,
+            b: [u8; 2],
         }
 
-        assert_eq!(foo(Pair(0, 0)), 4);
+        assert_eq!(unsafe { offset_of_unchecked!(Foo, a) }, 0);
+        assert_eq!(unsafe { offset_of_unchecked!(Foo, b) }, 0);
     }
 
-    #[cfg(tuple_ty)]
+    #[cfg(any(stable_offset_of, stable_const))]
     #[test]
-    fn test_tuple_offset() {
-        let f = (0i32, 0.0f32, 0u8);
-        let f_ptr = &f as *const _;
-        let f1_ptr = &f.1 as *const _;
+    fn const_unchecked_union_table() {
+        // As emitted by bindgen for an anonymous union member.
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        struct Sockaddr__bindgen_ty_1__bindgen_ty_1 {
+            port: u16,
+            addr: [u8; 4],
+        }
 
-        assert_eq!(
-            f1_ptr as usize - f_ptr as usize,
-            offset_of_tuple!((i32, f32, u8), 1)
-        );
+        #[repr(C)]
+        union Sockaddr__bindgen_ty_1 {
+            raw: [u8; 14],
+            inet: Sockaddr__bindgen_ty_1__bindgen_ty_1,
+            family_hint: u64,
+        }
+
+        const UNION_OFFSETS: [usize; 3] = [
+            unsafe { offset_of_unchecked!(Sockaddr__bindgen_ty_1, raw) },
+            unsafe { offset_of_unchecked!(Sockaddr__bindgen_ty_1, inet) },
+            unsafe { offset_of_unchecked!(Sockaddr__bindgen_ty_1, family_hint) },
+        ];
+
+        assert_eq!(UNION_OFFSETS, [0, 0, 0]);
+        assert_eq!(offset_of!(Sockaddr__bindgen_ty_1__bindgen_ty_1, addr), 2);
     }
 
     #[test]
-    fn test_raw_field() {
+    fn test_raw_field_unchecked() {
         #[repr(C)]
         struct Foo {
             a: u32,
@@ -325,87 +1998,325 @@ mod tests {
             c: 0,
         };
         let f_ptr = &f as *const _;
-        assert_eq!(f_ptr as usize + 0, raw_field!(f_ptr, Foo, a) as usize);
-        assert_eq!(f_ptr as usize + 4, raw_field!(f_ptr, Foo, b) as usize);
-        assert_eq!(f_ptr as usize + 8, raw_field!(f_ptr, Foo, c) as usize);
+        assert_eq!(raw_field!(f_ptr, Foo, a), unsafe {
+            raw_field_unchecked!(f_ptr, Foo, a)
+        });
+        assert_eq!(raw_field!(f_ptr, Foo, c), unsafe {
+            raw_field_unchecked!(f_ptr, Foo, c)
+        });
     }
 
-    #[cfg(tuple_ty)]
+    #[cfg(stable_offset_of)]
     #[test]
-    fn test_raw_field_tuple() {
-        let t = (0u32, 0u8, false);
-        let t_ptr = &t as *const _;
-        let t_addr = t_ptr as usize;
+    fn qualified_parent() {
+        #[repr(C)]
+        struct Header<Ext> {
+            ext: Ext,
+            seq: u32,
+            len: u16,
+        }
+
+        trait Proto {
+            type Ext;
+            type Header;
+        }
+
+        struct Small;
+        impl Proto for Small {
+            type Ext = u8;
+            type Header = Header<u8>;
+        }
+
+        struct Large;
+        impl Proto for Large {
+            type Ext = [u64; 2];
+            type Header = Header<[u64; 2]>;
+        }
+
+        fn offsets<T: Proto<Header = Header<<T as Proto>::Ext>>>() -> (usize, usize) {
+            (
+                offset_of!(<T as Proto>::Header, seq),
+                offset_of!(T::Header, len),
+            )
+        }
+
+        fn seq_ptr<T: Proto<Header = Header<<T as Proto>::Ext>>>(
+            header: &T::Header,
+        ) -> (*const u32, *const u32, *const u32) {
+            let base = header as *const T::Header;
+            (
+                raw_field!(base, <T as Proto>::Header, seq),
+                raw_field!(base, T::Header, seq),
+                raw_field!(base, <T>::Header, seq),
+            )
+        }
+
+        fn len_offset<T: Proto<Header = Header<<T as Proto>::Ext>>>() -> usize {
+            offset_of!(<T>::Header, len)
+        }
+
+        assert_eq!(offsets::<Small>(), (4, 8));
+        assert_eq!(offsets::<Large>(), (16, 20));
+        assert_eq!(len_offset::<Large>(), 20);
+
+        let header = Header {
+            ext: 0u8,
+            seq: 7,
+            len: 0,
+        };
+        let (a, b, c) = seq_ptr::<Small>(&header);
+        assert_eq!(a, &header.seq as *const u32);
+        assert_eq!(b, &header.seq as *const u32);
+        assert_eq!(c, &header.seq as *const u32);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn nested_fields() {
+        #[repr(C)]
+        struct Point(u16, u16);
+
+        #[repr(C)]
+        struct Inner {
+            tag: u8,
+            point: Point,
+            len: u32,
+        }
+
+        #[repr(C)]
+        struct Middle {
+            flags: u16,
+            inner: Inner,
+        }
+
+        #[repr(C)]
+        struct Outer {
+            id: u64,
+            middle: Middle,
+        }
 
         assert_eq!(
-            &t.0 as *const _ as usize - t_addr,
-            raw_field_tuple!(t_ptr, (u32, u8, bool), 0) as usize - t_addr
-        );
-        assert_eq!(
-            &t.1 as *const _ as usize - t_addr,
-            raw_field_tuple!(t_ptr, (u32, u8, bool), 1) as usize - t_addr
-        );
-        assert_eq!(
-            &t.2 as *const _ as usize - t_addr,
-            raw_field_tuple!(t_ptr, (u32, u8, bool), 2) as usize - t_addr
+            offset_of!(Outer, middle.inner.len),
+            offset_of!(Outer, middle) + offset_of!(Middle, inner) + offset_of!(Inner, len)
         );
+        assert_eq!(offset_of!(Outer, middle.inner.point.1), 8 + 4 + 2 + 2);
+        assert_eq!(offset_of!(Outer, middle.flags), 8);
     }
 
     #[test]
-    fn test_raw_field_union() {
+    fn array_elements() {
         #[repr(C)]
-        union Foo {
-            a: u32,
-            b: [u8; 2],
-            c: i64,
+        struct Frame {
+            len: u32,
+            samples: [u16; 4],
+            grid: [[u8; 3]; 2],
         }
 
-        let f = Foo { a: 0 };
-        let f_ptr = &f as *const _;
-        assert_eq!(f_ptr as usize + 0, raw_field_union!(f_ptr, Foo, a) as usize);
-        assert_eq!(f_ptr as usize + 0, raw_field_union!(f_ptr, Foo, b) as usize);
-        assert_eq!(f_ptr as usize + 0, raw_field_union!(f_ptr, Foo, c) as usize);
+        assert_eq!(offset_of!(Frame, samples[0]), 4);
+        assert_eq!(offset_of!(Frame, samples[3]), 10);
+        assert_eq!(offset_of!(Frame, samples[1 + 1],), 8);
+        assert_eq!(offset_of!(Frame, grid[1]), 12 + 3);
     }
 
-    #[cfg(any(stable_offset_of, stable_const))]
     #[test]
-    fn const_offset() {
+    #[should_panic]
+    fn array_element_out_of_bounds() {
         #[repr(C)]
-        struct Foo {
-            a: u32,
-            b: [u8; 2],
-            c: i64,
+        struct Frame {
+            len: u32,
+            samples: [u16; 4],
         }
 
-        assert_eq!([0; offset_of!(Foo, b)].len(), 4);
+        let index = 4;
+        let _ = offset_of!(Frame, samples[index]);
     }
 
-    #[cfg(stable_offset_of)]
     #[test]
-    fn const_offset_interior_mutable() {
+    #[should_panic(expected = "out of bounds")]
+    fn array_element_index_overflow() {
         #[repr(C)]
-        struct Foo {
-            a: u32,
-            b: core::cell::Cell<u32>,
+        struct Frame {
+            len: u32,
+            samples: [(); 4],
         }
 
-        assert_eq!([0; offset_of!(Foo, b)].len(), 4);
+        let index = !0;
+        let _ = offset_of!(Frame, samples[index]);
     }
 
-    #[cfg(any(stable_offset_of, stable_const))]
+    #[cfg(stable_const)]
     #[test]
-    fn const_fn_offset() {
-        const fn test_fn() -> usize {
+    fn const_array_element() {
+        #[repr(C)]
+        struct Frame {
+            len: u32,
+            samples: [u16; 4],
+        }
+
+        const LAST: usize = offset_of!(Frame, samples[3]);
+        assert_eq!(LAST, 10);
+    }
+
+    #[test]
+    fn array_parent() {
+        #[repr(C)]
+        struct Vertex {
+            pos: [f32; 3],
+            color: u32,
+        }
+
+        assert_eq!(offset_of!([Vertex; 8], [0]), 0);
+        assert_eq!(offset_of!([Vertex; 8], [3]), 3 * 16);
+        assert_eq!(offset_of!([Vertex; 8], [3].color), 3 * 16 + 12);
+        assert_eq!(offset_of!([Vertex; 8], [7].pos[2],), 7 * 16 + 8);
+        assert_eq!(offset_of!([[u8; 3]; 4], [2]), 6);
+        assert_eq!(offset_of!([(u8, u32); 4], [1],), 8);
+    }
+
+    #[test]
+    #[should_panic]
+    fn array_parent_out_of_bounds() {
+        let index = 8;
+        let _ = offset_of!([u32; 8], [index]);
+    }
+
+    #[cfg(stable_const)]
+    #[test]
+    fn const_array_parent() {
+        #[repr(C)]
+        struct Vertex {
+            pos: [f32; 3],
+            color: u32,
+        }
+
+        const LAST: usize = offset_of!([Vertex; 8], [7].color);
+        assert_eq!(LAST, 7 * 16 + 12);
+    }
+
+    #[test]
+    fn transparent_wrappers() {
+        mod sys {
             #[repr(C)]
-            struct Foo {
-                a: u32,
-                b: [u8; 2],
-                c: i64,
+            pub struct Stat {
+                pub dev: u64,
+                pub mode: u32,
+                pub times: [u32; 2],
             }
 
-            offset_of!(Foo, b)
+            #[repr(transparent)]
+            pub struct Fd(i32);
+
+            #[repr(transparent)]
+            pub struct FileStat(Stat);
+
+            #[repr(transparent)]
+            pub struct Tagged<T>(pub T, pub core::marker::PhantomData<u8>);
         }
 
-        assert_eq!([0; test_fn()].len(), 4);
+        #[repr(C)]
+        struct Entry {
+            fd: sys::Fd,
+            stat: sys::FileStat,
+            tagged: sys::Tagged<sys::Stat>,
+        }
+
+        assert_eq!(offset_of!(Entry, stat as sys::Stat, dev), 8);
+        assert_eq!(offset_of!(Entry, stat as sys::Stat, mode), 8 + 8);
+        assert_eq!(offset_of!(Entry, stat as sys::Stat, times,), 8 + 12);
+        assert_eq!(offset_of!(Entry, tagged as sys::Stat, mode), 32 + 8);
+    }
+
+    // `ManuallyDrop` requires rustc 1.20.
+    #[cfg(tuple_ty)]
+    #[test]
+    fn manually_drop_wrappers() {
+        use core::mem::ManuallyDrop;
+
+        #[repr(C)]
+        struct Node {
+            prev: u32,
+            next: u32,
+        }
+
+        #[repr(C)]
+        struct Entry {
+            key: u64,
+            node: ManuallyDrop<Node>,
+        }
+
+        assert_eq!(offset_of!(Entry, node as Node, prev), 8);
+        assert_eq!(offset_of!(Entry, node as Node, next), 12);
+    }
+
+    #[cfg(not(inline_const))]
+    #[test]
+    #[should_panic]
+    fn transparent_layout_mismatch() {
+        #[repr(C)]
+        struct Header {
+            len: u32,
+            flags: u32,
+        }
+
+        #[repr(C)]
+        struct Tagged(u8, Header);
+
+        #[repr(C)]
+        struct Entry {
+            header: Tagged,
+        }
+
+        let _ = offset_of!(Entry, header as Header, flags);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn union_paths() {
+        #[repr(C)]
+        union Payload {
+            as_u64: u64,
+            as_bytes: [u8; 8],
+            as_pair: (u16, u32),
+        }
+
+        #[repr(C)]
+        struct Outer {
+            tag: u32,
+            payload: Payload,
+        }
+
+        assert_eq!(offset_of_union!(Payload, as_bytes), 0);
+        assert_eq!(offset_of!(Outer, payload), 8);
+        assert_eq!(offset_of!(Outer, payload.as_u64), 8);
+        assert_eq!(offset_of!(Outer, payload.as_bytes[3]), 8 + 3);
+        assert_eq!(offset_of!(Outer, payload.as_pair.1), 8 + 4);
+    }
+
+    #[cfg(offset_of_enum)]
+    #[test]
+    fn enum_variant_fields() {
+        #[repr(C)]
+        #[allow(dead_code)]
+        enum Event {
+            Quit,
+            KeyPress { scancode: u16, repeat: u8 },
+            Resize(u32, u64),
+        }
+
+        #[repr(u8)]
+        #[allow(dead_code)]
+        enum Small {
+            A(u8, u16),
+            B { x: u32 },
+        }
+
+        assert_eq!(offset_of!(Event, KeyPress, scancode), 8);
+        assert_eq!(offset_of!(Event, KeyPress, repeat), 10);
+        assert_eq!(offset_of!(Event, Resize, 0), 8);
+        assert_eq!(offset_of!(Event, Resize, 1), 16);
+
+        assert_eq!(offset_of!(Small, A, 0), 1);
+        assert_eq!(offset_of!(Small, A, 1), 2);
+        assert_eq!(offset_of!(Small, B, x), 4);
     }
 }
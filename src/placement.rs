@@ -0,0 +1,337 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Helpers for placing values at fixed offsets inside a raw memory region,
+//! such as a shared-memory mapping.
+
+use core::fmt;
+use core::mem;
+use span::{check_span, check_span_aligned, span_at, SpanError};
+
+/// The reason a placement was rejected by `validate_placement`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementError {
+    /// The value does not fit in the region at the requested offset.
+    OutOfBounds {
+        /// The requested offset into the region.
+        offset: usize,
+        /// The size of the placed type.
+        size: usize,
+        /// The length of the region.
+        region_len: usize,
+    },
+    /// The address at the requested offset is not suitably aligned for the placed type.
+    Misaligned {
+        /// The requested offset into the region.
+        offset: usize,
+        /// The alignment required by the placed type.
+        align: usize,
+    },
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PlacementError::OutOfBounds {
+                offset,
+                size,
+                region_len,
+            } => write!(
+                f,
+                "{} bytes at offset {} do not fit in a region of {} bytes",
+                size, offset, region_len
+            ),
+            PlacementError::Misaligned { offset, align } => write!(
+                f,
+                "offset {} into the region is not aligned to {} bytes",
+                offset, align
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for PlacementError {
+    fn format(&self, f: defmt::Formatter) {
+        match *self {
+            PlacementError::OutOfBounds {
+                offset,
+                size,
+                region_len,
+            } => defmt::write!(
+                f,
+                "{=usize} bytes at offset {=usize} do not fit in a region of {=usize} bytes",
+                size,
+                offset,
+                region_len
+            ),
+            PlacementError::Misaligned { offset, align } => defmt::write!(
+                f,
+                "offset {=usize} into the region is not aligned to {=usize} bytes",
+                offset,
+                align
+            ),
+        }
+    }
+}
+
+/// Checks that a `T` can be placed at `offset` bytes into the region starting at `region`
+/// and spanning `region_len` bytes, and returns a pointer to it.
+///
+/// The value must fit entirely inside the region, and the resulting address must be
+/// suitably aligned for `T`.
+/// Nothing is read from or written to the region.
+///
+/// ## Examples
+/// ```
+/// use memoffset::placement::{validate_placement, PlacementError};
+///
+/// #[repr(C)]
+/// struct Header {
+///     magic: u32,
+///     len: u32,
+/// }
+///
+/// let region = [0u64; 4];
+/// let base = region.as_ptr() as *const u8;
+///
+/// assert!(validate_placement::<Header>(base, 32, 8).is_ok());
+/// assert_eq!(
+///     validate_placement::<Header>(base, 32, 2),
+///     Err(PlacementError::Misaligned { offset: 2, align: 4 })
+/// );
+/// ```
+#[cfg_attr(allow_clippy, allow(clippy::ptr_offset_with_cast))] // `wrapping_add` requires rustc 1.26
+pub fn validate_placement<T>(
+    region: *const u8,
+    region_len: usize,
+    offset: usize,
+) -> Result<*const T, PlacementError> {
+    let size = mem::size_of::<T>();
+    let align = mem::align_of::<T>();
+    let out_of_bounds = PlacementError::OutOfBounds {
+        offset,
+        size,
+        region_len,
+    };
+
+    let span = match span_at(offset, size) {
+        Ok(span) => span,
+        Err(_) => return Err(out_of_bounds),
+    };
+    if check_span(span.clone(), region_len).is_err() {
+        return Err(out_of_bounds);
+    }
+    match check_span_aligned::<T>(span, region as usize) {
+        Ok(()) => {}
+        Err(SpanError::Misaligned { .. }) => {
+            return Err(PlacementError::Misaligned { offset, align })
+        }
+        Err(_) => return Err(out_of_bounds),
+    }
+
+    let ptr = region.wrapping_offset(offset as isize);
+    Ok(ptr as *const T)
+}
+
+/// Mutable version of `validate_placement`.
+pub fn validate_placement_mut<T>(
+    region: *mut u8,
+    region_len: usize,
+    offset: usize,
+) -> Result<*mut T, PlacementError> {
+    validate_placement::<T>(region, region_len, offset).map(|ptr| ptr as *mut T)
+}
+
+/// Validates the placement of a parent struct inside a memory region, as `validate_placement`
+/// does, and computes a const raw pointer to the given field of the placed struct.
+///
+/// The region *must not* be dangling, but it *may* contain uninitialized memory.
+///
+/// ## Examples
+/// ```
+/// use memoffset::placed_field;
+///
+/// #[repr(C)]
+/// struct Header {
+///     magic: u32,
+///     len: u32,
+/// }
+///
+/// let region = [0u64; 4];
+/// let base = region.as_ptr() as *const u8;
+///
+/// let len = placed_field!(base, 32, 16, Header, len).unwrap();
+/// assert_eq!(len as usize, base as usize + 20);
+/// assert!(placed_field!(base, 32, 28, Header, len).is_err());
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! placed_field {
+    ($region:expr, $region_len:expr, $offset:expr, $parent:path, $field:tt $(,)*) => {{
+        $crate::placement::validate_placement::<$parent>($region, $region_len, $offset)
+            .map(|base| raw_field!(base, $parent, $field))
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    struct Header {
+        magic: u32,
+        flags: u16,
+        len: u64,
+    }
+
+    #[test]
+    fn good_offsets() {
+        let region = [0u64; 8];
+        let base = region.as_ptr() as *const u8;
+
+        let at_0 = validate_placement::<Header>(base, 64, 0).unwrap();
+        assert_eq!(at_0 as usize, base as usize);
+        let at_48 = validate_placement::<Header>(base, 64, 48).unwrap();
+        assert_eq!(at_48 as usize, base as usize + 48);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let region = [0u64; 8];
+        let base = region.as_ptr() as *const u8;
+
+        assert_eq!(
+            validate_placement::<Header>(base, 64, 56),
+            Err(PlacementError::OutOfBounds {
+                offset: 56,
+                size: 16,
+                region_len: 64,
+            })
+        );
+        assert_eq!(
+            validate_placement::<Header>(base, 64, !0),
+            Err(PlacementError::OutOfBounds {
+                offset: !0,
+                size: 16,
+                region_len: 64,
+            })
+        );
+    }
+
+    #[test]
+    fn misaligned() {
+        let region = [0u64; 8];
+        let base = region.as_ptr() as *const u8;
+
+        assert_eq!(
+            validate_placement::<Header>(base, 64, 2),
+            Err(PlacementError::Misaligned {
+                offset: 2,
+                align: core::mem::align_of::<u64>(),
+            })
+        );
+        assert!(validate_placement::<u32>(base, 64, 4).is_ok());
+    }
+
+    #[test]
+    fn mutable_placement() {
+        let mut region = [0u64; 8];
+        let base = region.as_mut_ptr() as *mut u8;
+
+        let header = validate_placement_mut::<Header>(base, 64, 16).unwrap();
+        unsafe {
+            (*header).magic = 0xFEED;
+            (*header).flags = 1;
+            (*header).len = 7;
+        }
+        assert_eq!(region[3], 7);
+    }
+
+    #[test]
+    fn placed_fields() {
+        let region = [0u64; 8];
+        let base = region.as_ptr() as *const u8;
+
+        let flags = placed_field!(base, 64, 32, Header, flags).unwrap();
+        assert_eq!(flags as usize, base as usize + 32 + 4);
+        assert_eq!(
+            placed_field!(base, 64, 3, Header, magic),
+            Err(PlacementError::Misaligned {
+                offset: 3,
+                align: core::mem::align_of::<u64>(),
+            })
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn vec_region() {
+        use std::vec;
+
+        // A byte buffer, as read from a file, has no alignment guarantee beyond that of `u8`.
+        let mut region = vec![0u8; 64];
+        let base = region.as_mut_ptr();
+        let align = core::mem::align_of::<Header>();
+        let aligned = (align - base as usize % align) % align;
+
+        let header = validate_placement_mut::<Header>(base, region.len(), aligned + 16).unwrap();
+        assert_eq!(header as usize % align, 0);
+        unsafe {
+            header.write(Header {
+                magic: 0xFEED_FACE,
+                flags: 3,
+                len: 7,
+            })
+        };
+        let magic = &region[aligned + 16..aligned + 20];
+        assert_eq!(magic, &0xFEED_FACEu32.to_ne_bytes()[..]);
+
+        let base = region.as_ptr();
+        for skew in 1..align {
+            assert_eq!(
+                validate_placement::<Header>(base, region.len(), aligned + skew),
+                Err(PlacementError::Misaligned {
+                    offset: aligned + skew,
+                    align,
+                })
+            );
+            assert_eq!(
+                placed_field!(base, region.len(), aligned + skew, Header, len),
+                Err(PlacementError::Misaligned {
+                    offset: aligned + skew,
+                    align,
+                })
+            );
+        }
+        // Fields of a struct that is aligned in the buffer are aligned as well.
+        let len = placed_field!(base, region.len(), aligned + 16, Header, len).unwrap();
+        assert_eq!(len as usize, base as usize + aligned + 24);
+        assert_eq!(unsafe { len.read() }, 7);
+
+        assert_eq!(
+            validate_placement::<Header>(base, region.len(), aligned + 56),
+            Err(PlacementError::OutOfBounds {
+                offset: aligned + 56,
+                size: 16,
+                region_len: 64,
+            })
+        );
+    }
+}
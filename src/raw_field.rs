@@ -34,7 +34,7 @@ macro_rules! _memoffset__addr_of {
     ($path:expr) => {{
         // This is UB because we create an intermediate reference to uninitialized memory.
         // Nothing we can do about that without `addr_of!` though.
-        &$path as *const _
+        $crate::__priv::ref_to_ptr(&$path)
     }};
 }
 
@@ -54,6 +54,40 @@ macro_rules! _memoffset__addr_of {
 ///
 /// _memoffset__field_check!(BoxedFoo, foo);
 /// ```
+///
+/// The struct pattern also rejects unions, which `core::mem::offset_of!` would accept, whether
+/// they are named directly or through an associated type:
+///
+/// ```compile_fail
+/// use memoffset::raw_field;
+///
+/// union Foo {
+///     foo: i32,
+/// }
+///
+/// trait Proto {
+///     type Header;
+/// }
+///
+/// impl Proto for Foo {
+///     type Header = Foo;
+/// }
+///
+/// fn foo_ptr<T: Proto<Header = Foo>>(header: &T::Header) -> *const i32 {
+///     raw_field!(header as *const T::Header, T::Header, foo)
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use memoffset::raw_field;
+///
+/// union Foo {
+///     foo: i32,
+/// }
+///
+/// let foo = Foo { foo: 0 };
+/// raw_field!(&foo as *const Foo, Foo, foo);
+/// ```
 #[cfg(allow_clippy)]
 #[macro_export]
 #[doc(hidden)]
@@ -78,6 +112,49 @@ macro_rules! _memoffset__field_check {
     };
 }
 
+/// Deref-coercion protection macro for parents given as a qualified path, such as
+/// `<T as Trait>::Assoc`.
+///
+/// Struct patterns cannot name a type through a qualified path, so this relies on
+/// `core::mem::offset_of!` and requires rustc 1.77 or newer.
+/// Unlike `_memoffset__field_check!`, it cannot tell a union from a struct.
+///
+/// ```compile_fail
+/// use memoffset::_memoffset__field_check_qualified;
+///
+/// struct Foo {
+///     foo: i32,
+/// }
+///
+/// trait Boxed {
+///     type Boxed;
+/// }
+///
+/// impl Boxed for Foo {
+///     type Boxed = Box<Foo>;
+/// }
+///
+/// _memoffset__field_check_qualified!(<Foo as Boxed>::Boxed, foo);
+/// ```
+#[cfg(stable_offset_of)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__field_check_qualified {
+    ($type:ty, $field:tt) => {
+        let _ = $crate::__priv::mem::offset_of!($type, $field);
+    };
+}
+#[cfg(not(stable_offset_of))]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__field_check_qualified {
+    ($type:ty, $field:tt) => {
+        _memoffset__compile_error!(
+            "qualified paths as the parent type require rustc 1.77 or newer"
+        );
+    };
+}
+
 /// Deref-coercion protection macro.
 ///
 /// Prevents compilation if the specified type is not a tuple.
@@ -107,6 +184,34 @@ macro_rules! _memoffset__field_check_tuple {
     };
 }
 
+/// Deref-coercion protection macro for the unsized tail field of a struct.
+///
+/// `core::mem::offset_of!` rejects unsized fields, so this matches the field by reference,
+/// in a closure that is never called.
+///
+/// ```compile_fail
+/// use memoffset::_memoffset__field_check_tail;
+///
+/// struct Foo {
+///     data: [u8],
+/// }
+///
+/// type BoxedFoo = Box<Foo>;
+///
+/// _memoffset__field_check_tail!(BoxedFoo, data);
+/// ```
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__field_check_tail {
+    ($type:path, $field:tt) => {
+        let _ = |value: &$type| {
+            let $type {
+                $field: ref _field, ..
+            } = *value;
+        };
+    };
+}
+
 /// Deref-coercion protection macro for unions.
 /// Unfortunately accepts single-field structs as well, which is not ideal,
 /// but ultimately pretty harmless.
@@ -154,24 +259,419 @@ macro_rules! _memoffset__field_check_union {
     };
 }
 
+/// Deref-coercion protection macro for nested field paths, such as `a.b.c`.
+///
+/// Struct patterns cannot follow a path into the type of a field, so this relies on
+/// `core::mem::offset_of!` and requires rustc 1.82 or newer.
+///
+/// ```compile_fail
+/// use memoffset::_memoffset__field_check_nested;
+///
+/// struct Inner {
+///     foo: i32,
+/// }
+///
+/// struct Outer {
+///     inner: Box<Inner>,
+/// }
+///
+/// _memoffset__field_check_nested!(Outer, inner.foo);
+/// ```
+#[cfg(nested_offset_of)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__field_check_nested {
+    ($type:path, $($field:tt).+) => {
+        let _ = $crate::__priv::mem::offset_of!($type, $($field).+);
+    };
+}
+#[cfg(not(nested_offset_of))]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__field_check_nested {
+    ($type:path, $($field:tt).+) => {
+        _memoffset__compile_error!("nested field paths require rustc 1.82 or newer");
+    };
+}
+
+/// Deref-coercion protection macro for a tuple index.
+///
+/// A nested path of tuple indices, such as `1.0`, is lexed as a single float literal and cannot
+/// be used in a struct pattern, so on rustc 1.82 or newer the field is checked by
+/// `core::mem::offset_of!`, and an empty struct pattern still rejects unions.
+#[cfg(nested_offset_of)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__field_check_index {
+    ($type:path, $field:tt) => {
+        let $type { .. };
+        let _ = $crate::__priv::mem::offset_of!($type, $field);
+    };
+}
+/// Deref-coercion protection macro for a tuple index.
+///
+/// A nested path of tuple indices, such as `1.0`, is lexed as a single float literal and cannot
+/// be used in a struct pattern, so on rustc 1.82 or newer the field is checked by
+/// `core::mem::offset_of!`, and an empty struct pattern still rejects unions.
+#[cfg(not(nested_offset_of))]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__field_check_index {
+    ($type:path, $field:tt) => {
+        _memoffset__field_check!($type, $field);
+    };
+}
+
+/// Checks at compile time that a field is a direct field of a type, and not reached through a
+/// `Deref` implementation.
+///
+/// This is the check that `raw_field!` and the other projection macros of this crate perform
+/// before computing a field address from a possibly uninitialized base pointer. Macros in
+/// other crates that do the same kind of projection can use it to get the same protection.
+/// It generates no code beyond the check, and can be used in statement position.
+///
+/// The type may be:
+///  * a struct or a tuple struct, as `assert_field_exists!(Foo, field)`;
+///  * a union, as `assert_field_exists!(union Foo, field)`;
+///  * a tuple type, as `assert_field_exists!((u8, u32), 1)` (requires rustc 1.20 or newer);
+///  * a qualified path, such as `<T as Trait>::Assoc` or `<T>::Assoc` (requires rustc 1.77 or
+///    newer).
+///
+/// The field may also be a nested path, as `assert_field_exists!(Foo, a.b)`; then every
+/// segment is checked, which requires rustc 1.82 or newer.
+///
+/// ## Examples
+/// A projection macro in another crate:
+/// ```
+/// use memoffset::assert_field_exists;
+///
+/// macro_rules! project {
+///     ($ptr:expr, $parent:path, $field:tt) => {{
+///         assert_field_exists!($parent, $field);
+///         let ptr: *mut $parent = $ptr;
+///         unsafe { std::ptr::addr_of_mut!((*ptr).$field) }
+///     }};
+/// }
+///
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// let mut p = std::mem::MaybeUninit::<Point>::uninit();
+/// let y = project!(p.as_mut_ptr(), Point, y);
+/// unsafe { y.write(5) };
+/// ```
+///
+/// A field behind a `Deref` impl is rejected:
+/// ```compile_fail
+/// use memoffset::assert_field_exists;
+///
+/// struct Point {
+///     x: i32,
+/// }
+///
+/// type Boxed = Box<Point>;
+///
+/// assert_field_exists!(Boxed, x);
+/// ```
+///
+/// So is a field that does not exist:
+/// ```compile_fail
+/// use memoffset::assert_field_exists;
+///
+/// union Bits {
+///     int: u32,
+/// }
+///
+/// assert_field_exists!(union Bits, float);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! assert_field_exists {
+    (union $type:path, $field:tt $(,)*) => {
+        _memoffset__field_check_union!($type, $field);
+    };
+    (<$qself:ty as $trait:path>::$assoc:ident, $field:tt $(,)*) => {
+        _memoffset__field_check_qualified!(<$qself as $trait>::$assoc, $field);
+    };
+    (<$qself:ty>::$assoc:ident, $field:tt $(,)*) => {
+        _memoffset__field_check_qualified!(<$qself>::$assoc, $field);
+    };
+    ($type:path, $field:ident $(,)*) => {
+        _memoffset__field_check!($type, $field);
+    };
+    ($type:path, $field:tt $(,)*) => {
+        _memoffset__field_check_index!($type, $field);
+    };
+    ($type:path, $field:tt $(. $rest:tt)+ $(,)*) => {
+        _memoffset__field_check_nested!($type, $field $(. $rest)+);
+    };
+    ($type:ty, $field:tt $(,)*) => {
+        _memoffset__field_check_tuple!($type, $field);
+    };
+}
+
 /// Computes a const raw pointer to the given field of the given base pointer
 /// to the given parent type.
 ///
 /// The `base` pointer *must not* be dangling, but it *may* point to
 /// uninitialized memory.
+///
+/// On rustc 1.51 and newer, the projection never creates a reference to the field, so it is
+/// sound for the possibly unaligned fields of `repr(packed(N))` structs, for any `N`.
+/// The returned pointer may then be unaligned as well; read it with `read_unaligned`.
+///
+/// The parent may also be given as a qualified path, such as `<T as Trait>::Assoc` or
+/// `<T>::Assoc`, as long as it resolves to a known struct type; this requires rustc 1.77 or newer.
+///
+/// The field may be a nested path, such as `config.u.net.mac`, which may also go through union
+/// members; this requires rustc 1.82 or newer.
+///
+/// A `MaybeUninit<T>` has the same layout as `T`, so the parent may also be given as
+/// `MaybeUninit<T>`, with a `*const MaybeUninit<T>` or `&MaybeUninit<T>` base; this requires
+/// rustc 1.36 or newer.
+///
+/// ```
+/// use memoffset::raw_field;
+/// use std::mem::MaybeUninit;
+///
+/// #[repr(C)]
+/// struct Header {
+///     magic: u32,
+///     len: u16,
+/// }
+///
+/// let mut header = MaybeUninit::<Header>::uninit();
+/// let base: *mut MaybeUninit<Header> = &mut header;
+/// unsafe {
+///     (raw_field!(base, MaybeUninit<Header>, magic) as *mut u32).write(0xFEED);
+///     (raw_field!(base, MaybeUninit<Header>, len) as *mut u16).write(4);
+/// }
+/// assert_eq!(raw_field!(&header, MaybeUninit<Header>, len) as usize - base as usize, 4);
+///
+/// let header = unsafe { header.assume_init() };
+/// assert_eq!((header.magic, header.len), (0xFEED, 4));
+/// ```
+///
+/// The parent is the type that `base` points to, so giving the pointer type as the parent is a
+/// compile error:
+///
+/// ```compile_fail
+/// use memoffset::raw_field;
+///
+/// struct Packet {
+///     len: u32,
+/// }
+///
+/// let packet = Packet { len: 4 };
+/// let len = raw_field!(&packet as *const Packet, *const Packet, len);
+/// ```
+///
+/// As with `offset_of!`, a field whose type is a `repr(transparent)` wrapper, such as
+/// `ManuallyDrop<T>`, can be seen through by naming the wrapped type, as in
+/// `raw_field!(base, Entry, node as Node, next)`.
+///
+/// ```
+/// use memoffset::raw_field;
+/// use std::mem::ManuallyDrop;
+///
+/// #[repr(C)]
+/// struct Node {
+///     prev: *const Node,
+///     next: *const Node,
+/// }
+///
+/// #[repr(C)]
+/// struct Entry {
+///     key: u64,
+///     node: ManuallyDrop<Node>,
+/// }
+///
+/// let entry = Entry {
+///     key: 1,
+///     node: ManuallyDrop::new(Node { prev: std::ptr::null(), next: std::ptr::null() }),
+/// };
+/// let base = &entry as *const Entry;
+/// let next: *const *const Node = raw_field!(base, Entry, node as Node, next);
+/// assert_eq!(next as usize - base as usize, 8 + std::mem::size_of::<*const Node>());
+/// ```
+///
+/// With the `checked` cargo feature, debug builds also check that the field lies within the
+/// parent, and panic with a message naming both otherwise.
+/// This covers `container_of!` and the other macros built on `raw_field!`, but it needs the size
+/// of the parent, so the parent must be sized: for the fields before an unsized tail, use
+/// `raw_field_unchecked!`, which is never checked.
 #[macro_export(local_inner_macros)]
 macro_rules! raw_field {
-    ($base:expr, $parent:path, $field:tt) => {{
-        _memoffset__field_check!($parent, $field);
+    ($base:expr, MaybeUninit<$parent:path>, $($field:tt).+ $(,)*) => {{
+        let base: *const $crate::__priv::mem::MaybeUninit<$parent> = $base;
+        raw_field!(base as *const $parent, $parent, $($field).+)
+    }};
+    ($base:expr, <$qself:ty as $trait:path>::$assoc:ident, $field:tt $(,)*) => {{
+        assert_field_exists!(<$qself as $trait>::$assoc, $field);
+        let base = $base; // evaluate $base outside the `unsafe` block
+
+        // The check above does the same job as the one in the arm below.
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            raw_field_unchecked!(base, <$qself as $trait>::$assoc, $field)
+        }
+    }};
+    ($base:expr, <$qself:ty>::$assoc:ident, $field:tt $(,)*) => {{
+        assert_field_exists!(<$qself>::$assoc, $field);
+        let base = $base; // evaluate $base outside the `unsafe` block
+
+        // The check above does the same job as the one in the arm below.
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            raw_field_unchecked!(base, <$qself>::$assoc, $field)
+        }
+    }};
+    ($base:expr, $parent:path, $($field:tt).+ $(,)*) => {{
+        assert_field_exists!($parent, $($field).+);
         let base = $base; // evaluate $base outside the `unsafe` block
 
         // Get the field address.
         // Crucially, we know that this will not trigger a deref coercion because
         // of the field check we did above.
         #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
-        unsafe {
-            _memoffset__addr_of!((*(base as *const $parent)).$field)
-        }
+        let field = unsafe {
+            _memoffset__addr_of!((*(base as *const $parent)).$($field).+)
+        };
+        _memoffset__check_field!(
+            $parent,
+            ($($field).+),
+            _memoffset_offset_from_unsafe!(field, base),
+            _memoffset_offset_from_unsafe!(field, base) + $crate::__priv::size_of_pointee(field),
+            $crate::__priv::size_of_pointee(base as *const $parent)
+        );
+        field
+    }};
+    ($base:expr, $parent:path, $($field:tt).+ as $inner:path, $($rest:tt).+ $(,)*) => {{
+        _memoffset__assert_transparent!($parent, $($field).+, $inner);
+        let wrapper_ptr = raw_field!($base, $parent, $($field).+);
+        raw_field!(wrapper_ptr as *const $inner, $inner, $($rest).+)
+    }};
+    ($base:expr, &mut $parent:ty, $($rest:tt)+) => {
+        _memoffset__reject_parent!("a reference", raw_field, [$base], $parent, $($rest)+)
+    };
+    ($base:expr, & $parent:ty, $($rest:tt)+) => {
+        _memoffset__reject_parent!("a reference", raw_field, [$base], $parent, $($rest)+)
+    };
+    ($base:expr, *const $parent:ty, $($rest:tt)+) => {
+        _memoffset__reject_parent!("a pointer", raw_field, [$base], $parent, $($rest)+)
+    };
+    ($base:expr, *mut $parent:ty, $($rest:tt)+) => {
+        _memoffset__reject_parent!("a pointer", raw_field, [$base], $parent, $($rest)+)
+    };
+}
+
+/// Recovers a const raw pointer to a struct from a pointer to one of its fields.
+///
+/// This is the inverse of `raw_field!`, and the equivalent of the C `container_of` macro:
+/// the offset of the field is subtracted from the field pointer, which must have the type of
+/// the field. Cast the result with `as *mut _` if you need a mutable pointer.
+///
+/// The field may be a nested path, such as `config.u.net.mac`, which may also go through union
+/// members; this requires rustc 1.82 or newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::{container_of, raw_field};
+///
+/// #[repr(C)]
+/// struct Node {
+///     value: u64,
+///     link: Link,
+/// }
+///
+/// #[repr(C)]
+/// struct Link {
+///     next: *const Link,
+/// }
+///
+/// let node = Node { value: 7, link: Link { next: std::ptr::null() } };
+/// // Derive the field pointer from a pointer to the whole node: a pointer derived from
+/// // `&node.link` would only grant access to the link itself.
+/// let link = raw_field!(&node as *const Node, Node, link);
+///
+/// let recovered = container_of!(link, Node, link);
+/// assert_eq!(recovered, &node as *const Node);
+/// assert_eq!(unsafe { (*recovered).value }, 7);
+/// ```
+///
+/// Computing the pointer is safe, but dereferencing it is only sound if the field pointer
+/// really points into a value of the parent type.
+#[macro_export(local_inner_macros)]
+macro_rules! container_of {
+    ($ptr:expr, $parent:path, $($field:tt).+ $(,)*) => {{
+        let ptr = $ptr;
+        // Get a (non-dangling) base pointer.
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        // Get the field pointer, whose type also makes sure `ptr` points to the field type.
+        let field_ptr = raw_field!(base_ptr, $parent, $($field).+);
+        let offset = _memoffset_offset_from_unsafe!(field_ptr, base_ptr);
+        $crate::__priv::container_of(ptr, field_ptr, base_ptr, offset)
+    }};
+}
+
+/// Computes a const raw pointer to the given field of the given base pointer
+/// to the given parent type, without checking that the field is part of the parent.
+///
+/// This is a last resort for macro authors whose generated field names are rejected by the
+/// check that `raw_field!` performs, and for parent types the check does not support.
+/// Prefer `raw_field!` wherever it works.
+///
+/// The `base` pointer *must not* be dangling, but it *may* point to
+/// uninitialized memory.
+///
+/// ## Examples
+/// ```
+/// use memoffset::raw_field_unchecked;
+///
+/// #[repr(C)]
+/// struct Foo {
+///     a: u32,
+///     b: u64,
+/// }
+///
+/// // Stands in for a type name that a macro generates.
+/// macro_rules! generated_type {
+///     () => { Foo };
+/// }
+///
+/// let foo = Foo { a: 1, b: 2 };
+/// // Safety: `b` is a field of `Foo` itself, not of something it derefs to.
+/// let b = unsafe { raw_field_unchecked!(&foo as *const Foo, generated_type!(), b) };
+/// assert_eq!(unsafe { *b }, 2);
+/// ```
+///
+/// ## Safety
+/// Without the check, nothing prevents the field access from going through a `Deref` impl of
+/// the parent, e.g. when the parent is a `Box` of a struct that has the field.
+/// That would read the (likely uninitialized) parent to call `deref`, which is undefined behavior.
+/// It is up to the caller to make sure the field is a direct field of the parent, so the macro
+/// must be used in an `unsafe` block:
+///
+/// ```compile_fail
+/// use memoffset::raw_field_unchecked;
+///
+/// struct Foo {
+///     a: u32,
+/// }
+///
+/// let foo = Box::new(Foo { a: 1 });
+/// let a = raw_field_unchecked!(&foo as *const Box<Foo>, Box<Foo>, a);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! raw_field_unchecked {
+    ($base:expr, $parent:ty, $($field:tt).+ $(,)*) => {{
+        let base = $base;
+
+        // Get the field address.
+        // This is deliberately not wrapped in an `unsafe` block: nothing checks that the field
+        // is not reached through a `Deref` impl, so the caller has to vouch for it.
+        _memoffset__addr_of!((*(base as *const $parent)).$($field).+)
     }};
 }
 
@@ -183,7 +683,7 @@ macro_rules! raw_field {
 #[cfg(tuple_ty)]
 #[macro_export(local_inner_macros)]
 macro_rules! raw_field_tuple {
-    ($base:expr, $parent:ty, $field:tt) => {{
+    ($base:expr, $parent:ty, $field:tt $(,)*) => {{
         _memoffset__field_check_tuple!($parent, $field);
         let base = $base; // evaluate $base outside the `unsafe` block
 
@@ -211,8 +711,8 @@ macro_rules! raw_field_tuple {
 /// on any use of this macro with a struct, without a semver bump.
 #[macro_export(local_inner_macros)]
 macro_rules! raw_field_union {
-    ($base:expr, $parent:path, $field:tt) => {{
-        _memoffset__field_check_union!($parent, $field);
+    ($base:expr, $parent:path, $field:tt $(,)*) => {{
+        assert_field_exists!(union $parent, $field);
         let base = $base; // evaluate $base outside the `unsafe` block
 
         // Get the field address.
@@ -224,3 +724,322 @@ macro_rules! raw_field_union {
         }
     }};
 }
+
+/// Computes a mutable raw pointer to the contents of an `UnsafeCell` or `Cell` field, given a
+/// pointer to the parent struct.
+///
+/// This is `raw_field!` followed by `UnsafeCell::raw_get`.
+/// On rustc 1.51 and newer, it never creates a reference to the cell, so the pointer may be used
+/// while other threads write to the contents.
+/// A cell has the same in-memory representation as its contents, so this also works on rustc
+/// versions that do not have `raw_get`.
+///
+/// The `base` pointer *must not* be dangling, but it *may* point to
+/// uninitialized memory.
+///
+/// ## Examples
+/// ```
+/// use memoffset::raw_cell_field;
+/// use std::cell::UnsafeCell;
+///
+/// #[repr(C)]
+/// struct Slot {
+///     seq: u64,
+///     value: UnsafeCell<u32>,
+/// }
+///
+/// let slot = Slot { seq: 0, value: UnsafeCell::new(1) };
+/// let value: *mut u32 = raw_cell_field!(&slot as *const Slot, Slot, value);
+/// unsafe { value.write(2) };
+/// assert_eq!(slot.value.into_inner(), 2);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! raw_cell_field {
+    ($base:expr, $parent:path, $($field:tt).+ $(,)*) => {
+        $crate::__priv::cell_contents(raw_field!($base, $parent, $($field).+))
+    };
+}
+
+/// Computes a const raw pointer to the unsized tail field of a struct, such as a trailing
+/// slice, from a pointer to the struct.
+///
+/// The returned pointer keeps the metadata of `base`, so for a slice tail it has the length of
+/// the slice, and for a trait object tail it has the vtable.
+///
+/// The `base` pointer *must not* be dangling, but it *may* point to
+/// uninitialized memory.
+///
+/// Sized fields before the tail can be projected to with `raw_field!` on rustc 1.77 and newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::raw_field_tail;
+/// use std::ptr;
+///
+/// #[repr(C)]
+/// struct Packet {
+///     len: u32,
+///     flags: u16,
+///     data: [u8],
+/// }
+///
+/// let buf = [0u32; 4];
+/// let packet = ptr::slice_from_raw_parts(buf.as_ptr() as *const u8, 10) as *const Packet;
+///
+/// let data = raw_field_tail!(packet, Packet, data);
+/// assert_eq!(data as *const u8 as usize - packet as *const u8 as usize, 6);
+/// assert_eq!(unsafe { (&*data).len() }, 10);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! raw_field_tail {
+    ($base:expr, $parent:path, $field:tt $(,)*) => {{
+        _memoffset__field_check_tail!($parent, $field);
+        let base: *const $parent = $base; // evaluate $base outside the `unsafe` block
+
+        // Get the field address.
+        // Crucially, we know that this will not trigger a deref coercion because
+        // of the field check we did above.
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            _memoffset__addr_of!((*base).$field)
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    #[repr(C)]
+    struct Inner {
+        a: u8,
+        b: u32,
+    }
+
+    #[repr(C)]
+    struct Outer {
+        inner: Inner,
+        pair: (u16, u64),
+    }
+
+    struct Tuple(u8, Inner);
+
+    #[repr(C)]
+    union Bits {
+        int: u32,
+        bytes: [u8; 4],
+    }
+
+    #[cfg(maybe_uninit)]
+    #[test]
+    fn maybe_uninit_parent() {
+        use core::mem::MaybeUninit;
+
+        #[repr(C)]
+        struct Generic<T> {
+            tag: u8,
+            value: T,
+        }
+
+        let mut outer = MaybeUninit::<Outer>::uninit();
+        let base = &mut outer as *mut MaybeUninit<Outer>;
+        let a = raw_field!(base, MaybeUninit<Outer>, inner) as *mut Inner;
+        assert_eq!(a as usize, base as usize);
+        let pair = raw_field!(base, MaybeUninit<Outer>, pair,) as *mut (u16, u64);
+        assert_eq!(pair as usize - base as usize, 8);
+
+        unsafe {
+            a.write(Inner { a: 1, b: 7 });
+            pair.write((2, 3));
+        }
+        assert_eq!(
+            raw_field!(&outer, MaybeUninit<Outer>, pair) as usize - base as usize,
+            8
+        );
+        let outer = unsafe { outer.assume_init() };
+        assert_eq!((outer.inner.a, outer.inner.b, outer.pair), (1, 7, (2, 3)));
+
+        let generic = MaybeUninit::<Generic<Generic<u16>>>::uninit();
+        let value = raw_field!(&generic, MaybeUninit<Generic<Generic<u16>>>, value);
+        assert_eq!(value as usize - generic.as_ptr() as usize, 2);
+    }
+
+    // `ManuallyDrop` requires rustc 1.20.
+    #[cfg(tuple_ty)]
+    #[test]
+    fn transparent_wrappers() {
+        use core::mem::ManuallyDrop;
+
+        #[repr(C)]
+        struct Entry {
+            key: u16,
+            inner: ManuallyDrop<Inner>,
+            outer: ManuallyDrop<Outer>,
+        }
+
+        let entry = Entry {
+            key: 1,
+            inner: ManuallyDrop::new(Inner { a: 2, b: 3 }),
+            outer: ManuallyDrop::new(Outer {
+                inner: Inner { a: 4, b: 5 },
+                pair: (6, 7),
+            }),
+        };
+        let base = &entry as *const Entry;
+        let b = raw_field!(base, Entry, inner as Inner, b);
+        assert_eq!(b as usize - base as usize, 4 + 4);
+        assert_eq!(unsafe { *b }, 3);
+        let pair = raw_field!(base, Entry, outer as Outer, pair,);
+        assert_eq!(unsafe { (*pair).1 }, 7);
+        assert_eq!(entry.key, 1);
+    }
+
+    #[test]
+    fn cell_fields() {
+        use core::cell::{Cell, UnsafeCell};
+
+        #[repr(C)]
+        struct Shared {
+            seq: Cell<u32>,
+            data: UnsafeCell<Inner>,
+            flags: u8,
+        }
+
+        let shared = Shared {
+            seq: Cell::new(0),
+            data: UnsafeCell::new(Inner { a: 1, b: 2 }),
+            flags: 3,
+        };
+        let shared_ref = &shared;
+        let base = shared_ref as *const Shared;
+
+        // Two pointers to the same contents, used in turn, as a writer and a reader on
+        // different threads would, while the struct is also read through a reference.
+        let writer = raw_cell_field!(base, Shared, data);
+        let reader = raw_cell_field!(base, Shared, data,);
+        let seq = raw_cell_field!(base, Shared, seq);
+        assert_eq!(writer as usize - base as usize, 4);
+        unsafe {
+            (*writer).b = 5;
+            *seq += 1;
+            assert_eq!((*reader).b, 5);
+            assert_eq!(shared_ref.seq.get(), 1);
+            (*writer).a = 4;
+            *seq += 1;
+            assert_eq!((*reader).a, 4);
+        }
+        assert_eq!(shared_ref.flags, 3);
+        assert_eq!(shared.seq.get(), 2);
+        let data = shared.data.into_inner();
+        assert_eq!((data.a, data.b), (4, 5));
+    }
+
+    #[test]
+    fn field_exists() {
+        assert_field_exists!(Inner, b);
+        assert_field_exists!(Outer, inner);
+        assert_field_exists!(Tuple, 0);
+        assert_field_exists!(Tuple, 1);
+        assert_field_exists!(union Bits, int);
+        assert_field_exists!(union Bits, bytes);
+    }
+
+    #[cfg(tuple_ty)]
+    #[test]
+    fn tuple_field_exists() {
+        assert_field_exists!((u8, u32), 1);
+    }
+
+    #[test]
+    fn container_of() {
+        let outer = Outer {
+            inner: Inner { a: 1, b: 2 },
+            pair: (3, 4),
+        };
+        let inner = &outer.inner as *const Inner;
+        assert_eq!(container_of!(inner, Outer, inner), &outer as *const Outer);
+        let pair = &outer.pair as *const (u16, u64) as *mut (u16, u64);
+        assert_eq!(container_of!(pair, Outer, pair), &outer as *const Outer);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    struct NetConfig {
+        mac: [u8; 6],
+        status: u16,
+    }
+
+    #[cfg(nested_offset_of)]
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    struct BlkConfig {
+        capacity: u64,
+        seg_max: u32,
+    }
+
+    #[cfg(nested_offset_of)]
+    #[repr(C)]
+    union DeviceConfig {
+        net: NetConfig,
+        blk: BlkConfig,
+    }
+
+    #[cfg(nested_offset_of)]
+    #[repr(C)]
+    struct Config {
+        generation: u32,
+        u: DeviceConfig,
+    }
+
+    #[cfg(nested_offset_of)]
+    #[repr(C)]
+    struct Dev {
+        id: u16,
+        config: Config,
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn through_unions() {
+        let dev = Dev {
+            id: 1,
+            config: Config {
+                generation: 2,
+                u: DeviceConfig {
+                    net: NetConfig {
+                        mac: [0xAA; 6],
+                        status: 1,
+                    },
+                },
+            },
+        };
+        let base = &dev as *const Dev;
+
+        let mac = raw_field!(base, Dev, config.u.net.mac);
+        let capacity = raw_field!(base, Dev, config.u.blk.capacity);
+        // All members of a union start at its beginning.
+        assert_eq!(mac as usize, capacity as usize);
+        let u = raw_field!(base, Dev, config.u);
+        assert_eq!(mac as usize, u as usize);
+        assert_eq!(unsafe { *mac }, [0xAA; 6]);
+        let status = raw_field!(base, Dev, config.u.net.status);
+        assert_eq!(status as usize, u as usize + 6);
+
+        assert_eq!(container_of!(mac, Dev, config.u.net.mac), base);
+        assert_eq!(container_of!(capacity, Dev, config.u.blk.capacity), base);
+        assert_eq!(container_of!(status, Dev, config.u.net.status), base);
+        assert_eq!(container_of!(u, Dev, config.u), base);
+        assert_eq!(
+            unsafe { (*container_of!(status, Dev, config.u.net.status)).id },
+            1
+        );
+        assert_eq!(dev.config.generation, 2);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn nested_field_exists() {
+        assert_field_exists!(Outer, inner.b);
+        assert_field_exists!(Outer, pair.1);
+        assert_field_exists!(Tuple, 1.a);
+    }
+}
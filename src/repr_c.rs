@@ -0,0 +1,386 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A compile-time calculator for the layout of `repr(C)` structs that are only known by the
+//! types of their fields.
+//!
+//! Requires rustc 1.65 or newer.
+
+/// The size and alignment of one field, as used by `repr_c_layout!`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReprCField {
+    /// The size of the field.
+    pub size: usize,
+    /// The alignment of the field.
+    pub align: usize,
+}
+
+impl ReprCField {
+    /// Describes a field of type `T`.
+    pub const fn of<T>() -> ReprCField {
+        ReprCField {
+            size: core::mem::size_of::<T>(),
+            align: core::mem::align_of::<T>(),
+        }
+    }
+
+    /// Raises the alignment of the field to at least `align`, which must be a power of two.
+    pub const fn aligned(self, align: usize) -> ReprCField {
+        assert!(align.is_power_of_two(), "alignments must be powers of two");
+        ReprCField {
+            size: self.size,
+            align: if align > self.align {
+                align
+            } else {
+                self.align
+            },
+        }
+    }
+}
+
+/// The layout of a `repr(C)` struct with `N` fields, as computed by `repr_c_layout!`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReprCLayout<const N: usize> {
+    /// The offset of each field, in declaration order.
+    pub offsets: [usize; N],
+    /// The size of the struct, including trailing padding.
+    pub size: usize,
+    /// The alignment of the struct.
+    pub align: usize,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ReprCField {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "size {=usize}, align {=usize}", self.size, self.align);
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> defmt::Format for ReprCLayout<N> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "size {=usize}, align {=usize}, offsets {=[?]}",
+            self.size,
+            self.align,
+            &self.offsets[..]
+        );
+    }
+}
+
+const fn round_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+/// Applies the `repr(C)` layout algorithm to the given fields.
+///
+/// The alignment of every field is capped at `pack`, as with `repr(packed(pack))`, and the
+/// alignment of the struct is raised to at least `align`, as with `repr(align(align))`.
+/// Pass `!0` and `1` respectively to leave them out.
+#[doc(hidden)]
+pub const fn layout<const N: usize>(
+    fields: [ReprCField; N],
+    pack: usize,
+    align: usize,
+) -> ReprCLayout<N> {
+    assert!(
+        pack.is_power_of_two() || pack == !0,
+        "packing must be a power of two"
+    );
+    assert!(align.is_power_of_two(), "alignments must be powers of two");
+
+    let mut offsets = [0; N];
+    let mut offset = 0;
+    let mut max_align = align;
+    let mut i = 0;
+    while i < N {
+        let field_align = if fields[i].align > pack {
+            pack
+        } else {
+            fields[i].align
+        };
+        offset = round_up(offset, field_align);
+        offsets[i] = offset;
+        offset += fields[i].size;
+        if field_align > max_align {
+            max_align = field_align;
+        }
+        i += 1;
+    }
+
+    ReprCLayout {
+        offsets,
+        size: round_up(offset, max_align),
+        align: max_align,
+    }
+}
+
+/// Computes the layout of a `repr(C)` struct from the types of its fields, without declaring
+/// the struct.
+///
+/// The result is a `ReprCLayout`, with the offset of every field in declaration order, and the
+/// size and alignment of the struct.
+/// It is a constant expression.
+///
+/// The layout can be adjusted like the `repr` attribute would:
+///  * `repr_c_layout!(packed [...])` and `repr_c_layout!(packed(N) [...])` cap the alignment
+///    of every field, as `repr(C, packed)` and `repr(C, packed(N))` do,
+///  * `repr_c_layout!(align(N) [...])` raises the alignment of the struct, as `repr(C, align(N))`
+///    does,
+///  * `#[align(N)]` before the type of a field raises the alignment of that field, like C's
+///    `_Alignas(N)`. Packing still caps the result, as with `#pragma pack(N)`.
+///
+/// ## Examples
+/// ```
+/// use memoffset::repr_c_layout;
+/// use memoffset::repr_c::ReprCLayout;
+///
+/// const LAYOUT: ReprCLayout<4> = repr_c_layout!([u32, u16, [u8; 6], u64]);
+/// assert_eq!(LAYOUT.offsets, [0, 4, 6, 16]);
+/// assert_eq!(LAYOUT.size, 24);
+/// assert_eq!(LAYOUT.align, 8);
+///
+/// let packed = repr_c_layout!(packed [u8, u32]);
+/// assert_eq!(packed.offsets, [0, 1]);
+/// assert_eq!(packed.size, 5);
+///
+/// let aligned = repr_c_layout!([u8, #[align(16)] u32]);
+/// assert_eq!(aligned.offsets, [0, 16]);
+/// assert_eq!(aligned.size, 32);
+/// ```
+///
+/// Requires rustc 1.65 or newer.
+#[macro_export]
+macro_rules! repr_c_layout {
+    (packed [$($fields:tt)*] $(,)*) => {
+        $crate::repr_c_layout!(@layout 1, 1, [$($fields)*])
+    };
+    (packed($pack:expr) [$($fields:tt)*] $(,)*) => {
+        $crate::repr_c_layout!(@layout $pack, 1, [$($fields)*])
+    };
+    (align($align:expr) [$($fields:tt)*] $(,)*) => {
+        $crate::repr_c_layout!(@layout !0, $align, [$($fields)*])
+    };
+    ([$($fields:tt)*] $(,)*) => {
+        $crate::repr_c_layout!(@layout !0, 1, [$($fields)*])
+    };
+    (@layout $pack:expr, $align:expr, [$($(#[align($field_align:expr)])* $ty:ty),* $(,)*]) => {
+        $crate::repr_c::layout(
+            [$($crate::repr_c::ReprCField::of::<$ty>()$(.aligned($field_align))*),*],
+            $pack,
+            $align,
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReprCLayout;
+
+    #[repr(C)]
+    struct Mixed {
+        a: u8,
+        b: u32,
+        c: [u8; 3],
+        d: u64,
+        e: u16,
+    }
+
+    #[repr(C)]
+    struct Nested {
+        tag: u8,
+        mixed: Mixed,
+        tail: [u16; 3],
+    }
+
+    #[repr(C, packed)]
+    struct Packed {
+        a: u8,
+        b: u64,
+        c: u16,
+    }
+
+    #[repr(C, packed(2))]
+    struct Packed2 {
+        a: u8,
+        b: u64,
+        c: u8,
+    }
+
+    #[repr(C, align(32))]
+    struct Aligned {
+        a: u16,
+        b: u8,
+    }
+
+    #[repr(C)]
+    struct Empty {}
+
+    macro_rules! assert_agrees {
+        ($layout:expr, $parent:path, [$($field:tt),*]) => {{
+            let layout = $layout;
+            assert_eq!(layout.offsets, [$(offset_of!($parent, $field)),*]);
+            assert_eq!(layout.size, core::mem::size_of::<$parent>());
+            assert_eq!(layout.align, core::mem::align_of::<$parent>());
+        }};
+    }
+
+    #[test]
+    fn agrees_with_offset_of() {
+        assert_agrees!(
+            repr_c_layout!([u8, u32, [u8; 3], u64, u16]),
+            Mixed,
+            [a, b, c, d, e]
+        );
+        assert_agrees!(
+            repr_c_layout!([u8, Mixed, [u16; 3]]),
+            Nested,
+            [tag, mixed, tail]
+        );
+        assert_agrees!(repr_c_layout!(packed [u8, u64, u16]), Packed, [a, b, c]);
+        assert_agrees!(repr_c_layout!(packed(2) [u8, u64, u8]), Packed2, [a, b, c]);
+        assert_agrees!(repr_c_layout!(align(32) [u16, u8]), Aligned, [a, b]);
+        assert_agrees!(repr_c_layout!([]), Empty, []);
+    }
+
+    #[test]
+    fn field_alignment() {
+        let layout = repr_c_layout!([
+            u8,
+            #[align(8)]
+            u8,
+            u8,
+        ]);
+        assert_eq!(layout.offsets, [0, 8, 9]);
+        assert_eq!(layout.size, 16);
+        assert_eq!(layout.align, 8);
+
+        // Packing caps explicit alignment too.
+        let layout = repr_c_layout!(packed(4) [u8, #[align(8)] u8]);
+        assert_eq!(layout.offsets, [0, 4]);
+        assert_eq!(layout.size, 8);
+        assert_eq!(layout.align, 4);
+    }
+
+    #[test]
+    fn constant() {
+        const LAYOUT: ReprCLayout<3> = repr_c_layout!([u16, u64, u8]);
+        assert_eq!([0; LAYOUT.offsets[1]].len(), 8);
+        assert_eq!([0; LAYOUT.size].len(), 24);
+    }
+
+    /// Cross-checks `offset_of!` and `span_of!` against `layout`, which computes the layout
+    /// independently, from the sizes and alignments of the field types alone.
+    ///
+    /// Every shape is declared once with every representation, so adding one of either tests it
+    /// against all of the other.
+    mod oracle {
+        use super::super::{layout, ReprCField};
+        use core::marker::PhantomData;
+        use core::mem;
+
+        #[repr(C)]
+        pub struct Inner {
+            a: u8,
+            b: u32,
+        }
+
+        macro_rules! oracle {
+            (reprs { $($repr_mod:ident $repr:tt $pack:expr, $align:expr;)* } shapes $shapes:tt) => {
+                $(oracle!(@repr $repr_mod $repr $pack, $align, $shapes);)*
+            };
+            (@repr $repr_mod:ident $repr:tt $pack:expr, $align:expr, {
+                $($shape:ident [$($fields:tt)*];)*
+            }) => {
+                mod $repr_mod {
+                    #[allow(unused_imports)]
+                    use super::*;
+
+                    $(oracle!(@shape $shape $repr $pack, $align, [$($fields)*]);)*
+                }
+            };
+            (@shape $shape:ident $repr:tt $pack:expr, $align:expr, [
+                $first:ident: $first_ty:ty $(, $field:ident: $ty:ty)*
+            ]) => {
+                #[test]
+                fn $shape() {
+                    #[allow(dead_code)]
+                    #[repr $repr]
+                    struct S {
+                        $first: $first_ty,
+                        $($field: $ty,)*
+                    }
+
+                    let expected = layout(
+                        [ReprCField::of::<$first_ty>() $(, ReprCField::of::<$ty>())*],
+                        $pack,
+                        $align,
+                    );
+                    let sizes = [mem::size_of::<$first_ty>() $(, mem::size_of::<$ty>())*];
+                    let size = expected.size;
+                    assert_eq!(mem::size_of::<S>(), size);
+                    assert_eq!(mem::align_of::<S>(), expected.align);
+                    assert_eq!(span_of!(S, ..), 0..size);
+
+                    let first = expected.offsets[0];
+                    let mut fields = expected.offsets.iter().zip(sizes.iter());
+                    oracle!(@field S, $first, $first, fields, first, size);
+                    $(oracle!(@field S, $first, $field, fields, first, size);)*
+                }
+            };
+            (@field $parent:ident, $first:ident, $field:ident, $fields:ident, $first_offset:ident, $size:ident) => {{
+                let (&offset, &len) = $fields.next().unwrap();
+                let end = offset + len;
+                assert_eq!(offset_of!($parent, $field), offset);
+                assert_eq!(span_of!($parent, $field), offset..end);
+                assert_eq!(span_of!($parent, ..$field), 0..offset);
+                assert_eq!(span_of!($parent, ..=$field), 0..end);
+                assert_eq!(span_of!($parent, $field..), offset..$size);
+                assert_eq!(span_of!($parent, $first..$field), $first_offset..offset);
+                assert_eq!(span_of!($parent, $first..=$field), $first_offset..end);
+            }};
+        }
+
+        oracle! {
+            reprs {
+                c (C) !0, 1;
+                packed (C, packed) 1, 1;
+                packed2 (C, packed(2)) 2, 1;
+                align16 (C, align(16)) !0, 16;
+            }
+            shapes {
+                single [a: u64];
+                ascending [a: u8, b: u16, c: u32, d: u64];
+                descending [a: u64, b: u32, c: u16, d: u8];
+                arrays [a: [u8; 3], b: [u16; 3], c: u8];
+                nested [a: u8, b: Inner, c: u8];
+                tuples [a: u8, b: (u8, u32), c: u8];
+                pointers [a: u8, b: *const u8, c: u16];
+                zst_head [z: (), a: u16, b: u8];
+                zst_middle [a: u8, z: [u32; 0], b: u8];
+                zst_tail [a: u32, b: u8, z: ()];
+                aligned_zst_tail [a: u8, z: [u64; 0]];
+                marker_tail [a: u16, b: u8, z: PhantomData<u64>];
+                only_zsts [y: (), z: [u16; 0]];
+            }
+        }
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// `assert_eq!` with a message, for `local_inner_macros`.
+///
+/// Unlike `assert!`, `assert_eq!` is not a builtin macro, so it cannot be named under
+/// `#![no_implicit_prelude]`; this is spelled in terms of `assert!` instead.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__assert_eq {
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => assert!(
+                *left == *right,
+                "assertion failed: `(left == right)`\n  left: `{:?}`,\n right: `{:?}`: {}",
+                left,
+                right,
+                format_args!($($arg)+)
+            ),
+        }
+    };
+}
+
+/// Reexport for `local_inner_macros`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__assert {
+    ($($inner:tt)*) => {
+        assert! { $($inner)* }
+    }
+}
+
+/// Reexport for `local_inner_macros`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__concat {
+    ($($inner:tt)*) => {
+        concat! { $($inner)* }
+    }
+}
+
+/// Reexport for `local_inner_macros`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__stringify {
+    ($($inner:tt)*) => {
+        stringify! { $($inner)* }
+    }
+}
+
+/// Generates a module of tests checking that the macros of this crate agree with each other
+/// on the given fields of a struct.
+///
+/// For every listed field, the generated tests check that:
+///  * the field lies within the struct: `offset_of!` plus the size of the field does not
+///    exceed the size of the struct,
+///  * `raw_field!` projects to the address at `offset_of!` from the base pointer,
+///  * `container_of!` recovers the base pointer from the field pointer,
+///  * `span_of!` starts at `offset_of!` and is as long as the field.
+///
+/// The projections are checked for a value on the stack, for one that is not the first element
+/// of an array and, with the `std` cargo feature, for one in a `Box`.
+/// Fields may be nested paths, such as `header.len`, on rustc 1.82 and newer.
+///
+/// All checks are done on `MaybeUninit` values, so the struct does not need to be constructed,
+/// and the tests are fine to run under Miri.
+///
+/// The struct must be nameable from a child module, as the tests `use super::*`.
+///
+/// ## Examples
+/// ```
+/// #[macro_use]
+/// extern crate memoffset;
+///
+/// #[repr(C)]
+/// pub struct Foo {
+///     a: u32,
+///     b: [u8; 3],
+///     c: u64,
+/// }
+///
+/// roundtrip_tests!(mod foo_roundtrip for Foo { a, b, c });
+/// # fn main() {}
+/// ```
+#[cfg(maybe_uninit)]
+#[macro_export(local_inner_macros)]
+macro_rules! roundtrip_tests {
+    (mod $name:ident for $parent:path { $($($field:tt).+),+ $(,)* } $(,)*) => {
+        #[cfg(test)]
+        mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #[test]
+            fn fields_within_bounds() {
+                _memoffset__let_base_ptr!(base_ptr, $parent);
+                $(
+                    let size = $crate::__priv::size_of_pointee(raw_field!(base_ptr, $parent, $($field).+));
+                    _memoffset__assert!(
+                        offset_of!($parent, $($field).+) + size <= $crate::__priv::mem::size_of::<$parent>(),
+                        _memoffset__concat!("field `", _memoffset__stringify!($($field).+), "` is out of bounds")
+                    );
+                )+
+            }
+
+            #[test]
+            fn projection_matches_offset() {
+                _memoffset__roundtrip_bases!(bases, $parent);
+                for &base in &bases {
+                    $(
+                        _memoffset__assert_eq!(
+                            raw_field!(base, $parent, $($field).+) as usize - base as usize,
+                            offset_of!($parent, $($field).+),
+                            _memoffset__concat!("field `", _memoffset__stringify!($($field).+), "`")
+                        );
+                    )+
+                }
+            }
+
+            #[test]
+            fn container_of_inverts_projection() {
+                _memoffset__roundtrip_bases!(bases, $parent);
+                for &base in &bases {
+                    $(
+                        _memoffset__assert_eq!(
+                            container_of!(raw_field!(base, $parent, $($field).+), $parent, $($field).+),
+                            base,
+                            _memoffset__concat!("field `", _memoffset__stringify!($($field).+), "`")
+                        );
+                    )+
+                }
+            }
+
+            #[test]
+            fn span_matches_offset_and_size() {
+                _memoffset__let_base_ptr!(base_ptr, $parent);
+                $(
+                    let offset = offset_of!($parent, $($field).+);
+                    let size = $crate::__priv::size_of_pointee(raw_field!(base_ptr, $parent, $($field).+));
+                    _memoffset__assert_eq!(
+                        span_of!($parent, $($field).+),
+                        offset..offset + size,
+                        _memoffset__concat!("field `", _memoffset__stringify!($($field).+), "`")
+                    );
+                )+
+            }
+        }
+    };
+}
+
+/// Declares an array of pointers to uninitialized values of the given type for
+/// `roundtrip_tests!`: one on the stack, one that is the second element of an array and, with
+/// the `std` feature, one on the heap.
+#[cfg(all(maybe_uninit, feature = "std"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__roundtrip_bases {
+    ($name:ident, $parent:path) => {
+        let value = $crate::__priv::mem::MaybeUninit::<$parent>::uninit();
+        let array = $crate::__priv::mem::MaybeUninit::<[$parent; 2]>::uninit();
+        let boxed = $crate::__priv::Box::new($crate::__priv::mem::MaybeUninit::<$parent>::uninit());
+        let $name = [
+            value.as_ptr(),
+            (array.as_ptr() as *const $parent).wrapping_offset(1),
+            boxed.as_ptr(),
+        ];
+    };
+}
+/// Declares an array of pointers to uninitialized values of the given type for
+/// `roundtrip_tests!`: one on the stack, one that is the second element of an array and, with
+/// the `std` feature, one on the heap.
+#[cfg(all(maybe_uninit, not(feature = "std")))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__roundtrip_bases {
+    ($name:ident, $parent:path) => {
+        let value = $crate::__priv::mem::MaybeUninit::<$parent>::uninit();
+        let array = $crate::__priv::mem::MaybeUninit::<[$parent; 2]>::uninit();
+        let $name = [
+            value.as_ptr(),
+            (array.as_ptr() as *const $parent).wrapping_offset(1),
+        ];
+    };
+}
+
+#[cfg(maybe_uninit)]
+#[cfg(test)]
+mod tests {
+    #[repr(C)]
+    pub struct Simple {
+        a: u32,
+        b: [u8; 2],
+        c: i64,
+    }
+
+    #[repr(C, packed)]
+    pub struct Packed {
+        a: u32,
+        b: [u8; 2],
+        c: i64,
+    }
+
+    #[repr(C)]
+    pub struct Nested {
+        x: u64,
+        y: [u8; 56],
+        z: Simple,
+        egg: [[u8; 4]; 5],
+    }
+
+    #[repr(C)]
+    pub struct Tup(i32, u8, u16);
+
+    // Mirrors the shapes of the fixtures of the other test modules of the crate: arrays of
+    // structs, packed byte-array fields and tuple structs with struct fields.
+    #[repr(C)]
+    pub struct Ring {
+        head: u8,
+        slots: [Simple; 4],
+        bytes: [[u8; 3]; 2],
+    }
+
+    #[repr(C, packed)]
+    pub struct Frame {
+        kind: u8,
+        len: [u8; 3],
+        mac: [u8; 6],
+        seq: [u8; 8],
+    }
+
+    #[repr(C)]
+    pub struct Pair(u32, Tup);
+
+    roundtrip_tests!(mod simple for Simple { a, b, c });
+    roundtrip_tests!(mod packed for Packed { a, b, c });
+    roundtrip_tests!(mod nested for Nested { x, y, z, egg });
+    roundtrip_tests!(mod tup for Tup { 0, 1, 2 });
+    roundtrip_tests!(mod ring for Ring { head, slots, bytes });
+    roundtrip_tests!(mod frame for Frame { kind, len, mac, seq });
+    roundtrip_tests!(mod pair for Pair { 0, 1 });
+
+    #[cfg(nested_offset_of)]
+    #[repr(C)]
+    pub struct Wrapper(u8, Tup, Simple);
+
+    #[cfg(nested_offset_of)]
+    roundtrip_tests!(mod nested_paths for Nested { x, z.a, z.c, egg });
+    #[cfg(nested_offset_of)]
+    roundtrip_tests!(mod nested_tuple_paths for Wrapper { 0, 1.0, 1.2, 2.b });
+    #[cfg(nested_offset_of)]
+    roundtrip_tests!(mod pair_paths for Pair { 0, 1.0, 1.1, 1.2 });
+}
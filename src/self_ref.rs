@@ -0,0 +1,198 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// Builds a pinned, boxed struct containing a pointer to one of its own fields.
+///
+/// The struct is allocated uninitialized on the heap, and the listed fields are written in
+/// place. The pointer field is then set to the address of the target field, and the box is
+/// pinned, so the target never moves again.
+///
+/// The invocation looks like a struct literal that omits the pointer field, followed by
+/// `pointer_field => target_field`. Every other field must be listed, exactly once.
+/// The pointer field may have any type that a `*const` pointer to the target field can be cast
+/// to, such as `*const T`, `*mut T`, or a pointer to the first element of an array.
+///
+/// ## Examples
+/// ```
+/// use memoffset::init_self_ref;
+/// use std::marker::PhantomPinned;
+///
+/// struct Parser {
+///     buf: [u8; 8],
+///     cursor: *const u8,
+///     _pin: PhantomPinned,
+/// }
+///
+/// let parser = init_self_ref!(
+///     Parser { buf: *b"abcdefgh", _pin: PhantomPinned },
+///     cursor => buf
+/// );
+/// assert_eq!(unsafe { *parser.cursor }, b'a');
+///
+/// // Moving the box does not move the struct.
+/// let moved = parser;
+/// assert_eq!(moved.cursor, moved.buf.as_ptr());
+/// ```
+///
+/// Leaving out a field is an error:
+/// ```compile_fail
+/// use memoffset::init_self_ref;
+///
+/// struct Parser {
+///     buf: [u8; 8],
+///     len: usize,
+///     cursor: *const u8,
+/// }
+///
+/// let parser = init_self_ref!(Parser { buf: [0; 8] }, cursor => buf);
+/// ```
+///
+/// The struct should be `!Unpin` (for example by containing a `PhantomPinned`); otherwise it
+/// can be moved out of the `Pin`, leaving the pointer dangling.
+///
+/// If evaluating one of the field values panics, the allocation and the fields written so far
+/// are leaked.
+///
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
+#[macro_export(local_inner_macros)]
+macro_rules! init_self_ref {
+    ($parent:path { $($field:tt : $value:expr),* $(,)* }, $ptr:tt => $target:tt $(,)*) => {{
+        // Checks that every field is initialized exactly once.
+        #[allow(clippy::unneeded_field_pattern)]
+        let _ = |value: $parent| {
+            let $parent { $($field: _,)* $ptr: _ } = value;
+        };
+
+        let uninit = $crate::__priv::mem::MaybeUninit::<$parent>::uninit();
+        let base = $crate::__priv::Box::into_raw($crate::__priv::Box::new(uninit)) as *const $parent;
+        $(
+            let value = $value;
+            #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+            unsafe {
+                $crate::__priv::write_pointee(raw_field!(base, $parent, $field), value);
+            }
+        )*
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            let target = raw_field!(base, $parent, $target);
+            $crate::__priv::write_pointee(raw_field!(base, $parent, $ptr), target as _);
+            let boxed = $crate::__priv::Box::from_raw(base as *mut $parent);
+            <$crate::__priv::Pin<_> as $crate::__priv::From<_>>::from(boxed)
+        }
+    }};
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::boxed::Box;
+    use std::marker::PhantomPinned;
+    use std::pin::Pin;
+    use std::vec::Vec;
+
+    struct Cursor {
+        len: usize,
+        buf: [u8; 16],
+        pos: *const u8,
+        _pin: PhantomPinned,
+    }
+
+    fn cursor() -> Pin<Box<Cursor>> {
+        init_self_ref!(
+            Cursor {
+                len: 3,
+                buf: *b"0123456789abcdef",
+                _pin: PhantomPinned,
+            },
+            pos => buf
+        )
+    }
+
+    #[test]
+    fn points_into_itself() {
+        let c = cursor();
+        assert_eq!(c.pos, c.buf.as_ptr());
+        assert_eq!(c.len, 3);
+        assert_eq!(unsafe { *c.pos.add(10) }, b'a');
+    }
+
+    #[test]
+    fn survives_moves_of_the_box() {
+        let mut cursors = Vec::new();
+        for _ in 0..8 {
+            cursors.push(cursor());
+        }
+        // Growing the vector moves the boxes, but not the structs.
+        let c = cursors.swap_remove(0);
+        let moved = Box::new(c);
+        assert_eq!(moved.pos, moved.buf.as_ptr());
+        assert_eq!(unsafe { *moved.pos.add(15) }, b'f');
+    }
+
+    #[test]
+    fn packed_and_mutable() {
+        #[repr(C, packed)]
+        struct Node {
+            tag: u8,
+            value: u32,
+            this: *mut u32,
+            _pin: PhantomPinned,
+        }
+
+        let node = init_self_ref!(
+            Node {
+                tag: 1,
+                value: 42,
+                _pin: PhantomPinned,
+            },
+            this => value
+        );
+        let this = node.this;
+        assert_eq!(this as usize, &*node as *const Node as usize + 1);
+        assert_eq!(unsafe { this.read_unaligned() }, 42);
+        assert_eq!({ node.tag }, 1);
+    }
+
+    #[test]
+    fn generic() {
+        struct Slot<T> {
+            value: T,
+            ptr: *const T,
+            _pin: PhantomPinned,
+        }
+
+        let slot = init_self_ref!(
+            Slot<u16> {
+                value: 5,
+                _pin: PhantomPinned,
+            },
+            ptr => value
+        );
+        assert_eq!(unsafe { *slot.ptr }, 5);
+    }
+
+    #[test]
+    fn tuple_struct() {
+        struct Pair(u64, *const u64, PhantomPinned);
+
+        let pair = init_self_ref!(Pair { 0: 7, 2: PhantomPinned }, 1 => 0);
+        assert_eq!(unsafe { *pair.1 }, 7);
+    }
+}
@@ -0,0 +1,254 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Fallible validation of byte spans against buffers.
+//!
+//! These are the checks that the byte-buffer helpers of this crate perform before accessing a
+//! span of a buffer. On rustc 1.65 and newer, the functions are `const`.
+
+use core::fmt;
+use core::ops::Range;
+
+/// The reason a span was rejected by `check_span` or `check_span_aligned`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpanError {
+    /// The span does not fit in the buffer.
+    OutOfBounds {
+        /// The length the buffer would need to have.
+        needed: usize,
+        /// The length of the buffer.
+        got: usize,
+    },
+    /// Computing the span, or an address in it, overflows.
+    /// A reversed span, whose length would be negative, is reported as an overflow too.
+    Overflow,
+    /// The start of the span is not suitably aligned.
+    Misaligned {
+        /// The required alignment.
+        required: usize,
+    },
+}
+
+impl fmt::Display for SpanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SpanError::OutOfBounds { needed, got } => write!(
+                f,
+                "span needs a buffer of {} bytes, but the buffer is {} bytes long",
+                needed, got
+            ),
+            SpanError::Overflow => write!(f, "span arithmetic overflows"),
+            SpanError::Misaligned { required } => {
+                write!(f, "span is not aligned to {} bytes", required)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for SpanError {
+    fn format(&self, f: defmt::Formatter) {
+        match *self {
+            SpanError::OutOfBounds { needed, got } => defmt::write!(
+                f,
+                "span needs a buffer of {=usize} bytes, but the buffer is {=usize} bytes long",
+                needed,
+                got
+            ),
+            SpanError::Overflow => defmt::write!(f, "span arithmetic overflows"),
+            SpanError::Misaligned { required } => {
+                defmt::write!(f, "span is not aligned to {=usize} bytes", required)
+            }
+        }
+    }
+}
+
+/// Checks that `span` lies within a buffer of `buf_len` bytes.
+///
+/// ## Examples
+/// ```
+/// use memoffset::span::{check_span, SpanError};
+///
+/// assert_eq!(check_span(4..8, 8), Ok(()));
+/// assert_eq!(check_span(4..9, 8), Err(SpanError::OutOfBounds { needed: 9, got: 8 }));
+/// assert_eq!(check_span(4..2, 8), Err(SpanError::Overflow));
+/// ```
+#[cfg(stable_const)]
+pub const fn check_span(span: Range<usize>, buf_len: usize) -> Result<(), SpanError> {
+    if span.start > span.end {
+        Err(SpanError::Overflow)
+    } else if span.end > buf_len {
+        Err(SpanError::OutOfBounds {
+            needed: span.end,
+            got: buf_len,
+        })
+    } else {
+        Ok(())
+    }
+}
+/// Checks that `span` lies within a buffer of `buf_len` bytes.
+#[cfg(not(stable_const))]
+pub fn check_span(span: Range<usize>, buf_len: usize) -> Result<(), SpanError> {
+    if span.start > span.end {
+        Err(SpanError::Overflow)
+    } else if span.end > buf_len {
+        Err(SpanError::OutOfBounds {
+            needed: span.end,
+            got: buf_len,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks that the start of `span`, in a buffer starting at address `base_addr`, is suitably
+/// aligned for a `T`.
+///
+/// ## Examples
+/// ```
+/// use memoffset::span::{check_span_aligned, SpanError};
+///
+/// assert_eq!(check_span_aligned::<u32>(4..8, 0x1000), Ok(()));
+/// assert_eq!(
+///     check_span_aligned::<u32>(2..6, 0x1000),
+///     Err(SpanError::Misaligned { required: 4 })
+/// );
+/// ```
+#[cfg(stable_const)]
+pub const fn check_span_aligned<T>(span: Range<usize>, base_addr: usize) -> Result<(), SpanError> {
+    let required = core::mem::align_of::<T>();
+    match base_addr.checked_add(span.start) {
+        Some(addr) if addr & (required - 1) != 0 => Err(SpanError::Misaligned { required }),
+        Some(_) => Ok(()),
+        None => Err(SpanError::Overflow),
+    }
+}
+/// Checks that the start of `span`, in a buffer starting at address `base_addr`, is suitably
+/// aligned for a `T`.
+#[cfg(not(stable_const))]
+pub fn check_span_aligned<T>(span: Range<usize>, base_addr: usize) -> Result<(), SpanError> {
+    let required = core::mem::align_of::<T>();
+    match base_addr.checked_add(span.start) {
+        Some(addr) if addr & (required - 1) != 0 => Err(SpanError::Misaligned { required }),
+        Some(_) => Ok(()),
+        None => Err(SpanError::Overflow),
+    }
+}
+
+/// Computes the span of `size` bytes at `offset`.
+///
+/// ## Examples
+/// ```
+/// use memoffset::span::{span_at, SpanError};
+///
+/// assert_eq!(span_at(4, 2), Ok(4..6));
+/// assert_eq!(span_at(!0, 2), Err(SpanError::Overflow));
+/// ```
+#[cfg(stable_const)]
+pub const fn span_at(offset: usize, size: usize) -> Result<Range<usize>, SpanError> {
+    match offset.checked_add(size) {
+        Some(end) => Ok(offset..end),
+        None => Err(SpanError::Overflow),
+    }
+}
+/// Computes the span of `size` bytes at `offset`.
+#[cfg(not(stable_const))]
+pub fn span_at(offset: usize, size: usize) -> Result<Range<usize>, SpanError> {
+    match offset.checked_add(size) {
+        Some(end) => Ok(offset..end),
+        None => Err(SpanError::Overflow),
+    }
+}
+
+/// Checks that `span` lies within a buffer of `buf_len` bytes, and panics with the error
+/// otherwise.
+#[doc(hidden)]
+pub fn expect_span(span: Range<usize>, buf_len: usize) -> Range<usize> {
+    if let Err(err) = check_span(span.clone(), buf_len) {
+        panic!("{}", err);
+    }
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds() {
+        assert_eq!(check_span(0..0, 0), Ok(()));
+        assert_eq!(check_span(3..3, 3), Ok(()));
+        assert_eq!(check_span(0..16, 16), Ok(()));
+        assert_eq!(
+            check_span(0..17, 16),
+            Err(SpanError::OutOfBounds {
+                needed: 17,
+                got: 16
+            })
+        );
+        assert_eq!(
+            check_span(20..24, 16),
+            Err(SpanError::OutOfBounds {
+                needed: 24,
+                got: 16
+            })
+        );
+        assert_eq!(
+            check_span(Range { start: 5, end: 4 }, 16),
+            Err(SpanError::Overflow)
+        );
+    }
+
+    #[test]
+    fn alignment() {
+        assert_eq!(check_span_aligned::<u8>(3..4, 1), Ok(()));
+        assert_eq!(check_span_aligned::<u16>(2..4, 0x100), Ok(()));
+        assert_eq!(check_span_aligned::<u16>(1..3, 0x101), Ok(()));
+        assert_eq!(
+            check_span_aligned::<u16>(1..3, 0x100),
+            Err(SpanError::Misaligned { required: 2 })
+        );
+        assert_eq!(
+            check_span_aligned::<u16>(2..4, !0),
+            Err(SpanError::Overflow)
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(span_at(0, 0), Ok(0..0));
+        assert_eq!(span_at(!0 - 1, 1), Ok(!0 - 1..!0));
+        assert_eq!(span_at(!0, 1), Err(SpanError::Overflow));
+    }
+
+    #[test]
+    #[should_panic(expected = "span needs a buffer of 9 bytes, but the buffer is 8 bytes long")]
+    fn expect() {
+        assert_eq!(expect_span(0..8, 8), 0..8);
+        expect_span(1..9, 8);
+    }
+
+    #[cfg(stable_const)]
+    #[test]
+    fn constant() {
+        const CHECKED: Result<(), SpanError> = check_span(2..6, 4);
+        assert_eq!(CHECKED, Err(SpanError::OutOfBounds { needed: 6, got: 4 }));
+    }
+}
@@ -49,20 +49,76 @@ macro_rules! _memoffset__compile_error {
 ///
 /// // Open-ended ranges
 /// span_of!(Struct, .. end)
+/// span_of!(Struct, ..= end)
 /// span_of!(Struct, start ..)
+/// // The whole struct, `0 .. size_of::<Struct>()`
+/// span_of!(Struct, ..)
 /// ```
 ///
-/// ### Note
-/// This macro uses recursion in order to resolve the range expressions, so there is a limit to
-/// the complexity of the expression.
-/// In order to raise the limit, the compiler's recursion limit should be lifted.
+/// A third form covers a range of elements of an array field, written as in a slice index:
+///
+/// ```ignore
+/// span_of!(Struct, array[start .. end])
+/// span_of!(Struct, array[start ..= end])
+/// span_of!(Struct, array[..])
+/// ```
+///
+/// The bounds must be constants, given as literals or in parentheses.
+/// On rustc 1.79 and newer, a range that is reversed or out of the bounds of the array is a
+/// compile-time error; on older versions, it panics.
+///
+/// A fourth form gives the smallest range that covers a set of fields, listed in any order:
+///
+/// ```ignore
+/// span_of!(Struct, {member_a, member_d, member_f})
+/// ```
+///
+/// Each entry may also be an element of an array field, as in `array[3]`, or on rustc 1.82 and
+/// newer a nested path.
+/// The fields in between are covered too, so this is mostly useful for `repr(C)` structs, or to
+/// find where a group of fields ended up in a `repr(Rust)` struct.
+///
+/// The parent may also be an array type, with the bounds of the range given as indices in
+/// brackets: `span_of!([Vertex; 8], [2] ..= [5])`, `span_of!([Vertex; 8], [2] ..)` or just
+/// `span_of!([Vertex; 8], [2])`.
+/// These bounds are checked in the same way as those of a range of an array field.
+///
+/// On rustc 1.82 and newer, a field, or either end of a range, may also be given by a nested
+/// path, as with `offset_of!`: `span_of!(Struct, member.inner)` or
+/// `span_of!(Struct, member.inner ..= last)`.
+/// Fields of tuple structs are named by their index in every form, as in `span_of!(Pair, 0 .. 1)`,
+/// and so are nested ones, as in `span_of!(Wrapper, 1.0 ..= 1.2)`.
+///
+/// On rustc 1.77 and newer, the struct may also be named through an associated type, as with
+/// `offset_of!`.
+///
+/// On rustc 1.65 and newer, `span_of!` can be evaluated in `const` contexts; see
+/// `span_of_pair!` for the bounds as a tuple.
+///
+/// The span of a zero-sized field, such as a `PhantomData` marker, is empty, and in a `repr(C)`
+/// struct it starts where the next field starts.
+/// So an inclusive range ending at a zero-sized field is the same as the exclusive one.
+///
+/// A range with an open end, `start ..`, runs to the end of the struct and so includes its tail
+/// padding, while `start ..= last` stops at the end of the last field.
+/// The two differ whenever the struct is padded at the end, such as with `repr(align(N))`.
+///
+/// A range between two fields whose end comes before its start, such as `c .. a` where `c`
+/// follows `a`, is rejected with a message naming both fields: at compile time on rustc 1.79
+/// and newer, and with a panic in debug builds on older versions.
+/// A range from a field to itself, `a .. a`, is empty rather than reversed, and is accepted.
+///
+/// With the `checked` cargo feature, debug builds also check that the span does not end before
+/// it starts and lies within the struct, and panic with a message naming both otherwise.
+/// `span_of_unchecked!` is never checked.
 ///
 /// ### Safety
 /// The inter-field form mentioned above assumes that the first field is positioned before the
 /// second.
 /// This is only guaranteed for `repr(C)` structs.
-/// Usage with `repr(Rust)` structs may yield unexpected results, like downward-going ranges,
-/// spans that include unexpected fields, empty spans, or spans that include *unexpected* padding bytes.
+/// Usage with `repr(Rust)` structs may yield unexpected results, like ranges rejected as
+/// reversed, spans that include unexpected fields, empty spans, or spans that include
+/// *unexpected* padding bytes.
 ///
 /// ## Examples
 /// ```
@@ -88,143 +144,1194 @@ macro_rules! _memoffset__compile_error {
 /// assert_eq!(8..84,  span_of!(Blarg, y ..));
 /// assert_eq!(0..8,   span_of!(Blarg, x .. y));
 /// assert_eq!(0..64,  span_of!(Blarg, x ..= y));
+/// assert_eq!(16..20, span_of!(Blarg, y[8..12]));
+/// assert_eq!(72..80, span_of!(Blarg, egg[1..=2]));
+/// assert_eq!(168..336, span_of!([Blarg; 8], [2]..[4]));
+/// assert_eq!(8..68,  span_of!(Blarg, {z, y}));
+/// ```
+///
+/// Ranges that end before they start are rejected:
+///
+/// ```compile_fail
+/// # #[cfg(not(inline_const))] compile_error!("checked at run time");
+/// use memoffset::span_of;
+///
+/// #[repr(C)]
+/// struct Frame {
+///     len: u32,
+///     samples: [u16; 4],
+///     crc: u32,
+/// }
+///
+/// let span = span_of!(Frame, crc .. samples);
+/// ```
+///
+/// Element ranges that do not fit in the array are rejected:
+///
+/// ```compile_fail
+/// use memoffset::span_of;
+///
+/// #[repr(C)]
+/// struct Frame {
+///     len: u32,
+///     samples: [u16; 4],
+/// }
+///
+/// let span = span_of!(Frame, samples[2..5]);
 /// ```
 #[macro_export(local_inner_macros)]
 macro_rules! span_of {
-    (@helper  $root:ident, [] ..=) => {
-        _memoffset__compile_error!("Expected a range, found '..='")
+    (<$qself:ty as $trait:path>::$assoc:ident, $($exp:tt)+) => ({
+        _memoffset__span_of_impl!(qualified, <$qself as $trait>::$assoc, $($exp)+)
+    });
+    (<$qself:ty>::$assoc:ident, $($exp:tt)+) => ({
+        _memoffset__span_of_impl!(qualified, <$qself>::$assoc, $($exp)+)
+    });
+    ([$elem:ty; $len:expr], $($exp:tt)+) => ({
+        let (begin, end) = _memoffset__span_of_array!($elem, $len, $($exp)+);
+        begin..end
+    });
+    ($sty:path, $($exp:tt)+) => ({
+        // Macro implementation is delegated to another macro to have a
+        // single top-level macro to attach documentation to.
+        _memoffset__span_of_impl!(checked, $sty, $($exp)+)
+    });
+    (&mut $sty:ty, $($exp:tt)+) => {
+        _memoffset__reject_parent!("a reference", span_of, [], $sty, $($exp)+)
     };
-    (@helper $root:ident, [] ..) => {
-        _memoffset__compile_error!("Expected a range, found '..'")
+    (& $sty:ty, $($exp:tt)+) => {
+        _memoffset__reject_parent!("a reference", span_of, [], $sty, $($exp)+)
     };
-    // No explicit begin for range.
-    (@helper $root:ident, $parent:path, [] ..) => {{
-        ($root as usize,
-         $root as usize + $crate::__priv::size_of_pointee($root))
-    }};
-    (@helper $root:ident, $parent:path, [] ..= $end:tt) => {{
-        let end = raw_field!($root, $parent, $end);
-        ($root as usize, end as usize + $crate::__priv::size_of_pointee(end))
-    }};
-    (@helper $root:ident, $parent:path, [] .. $end:tt) => {{
-        ($root as usize, raw_field!($root, $parent, $end) as usize)
-    }};
-    // Explicit begin and end for range.
-    (@helper $root:ident, $parent:path, # $begin:tt [] ..= $end:tt) => {{
-        let begin = raw_field!($root, $parent, $begin);
-        let end = raw_field!($root, $parent, $end);
-        (begin as usize, end as usize + $crate::__priv::size_of_pointee(end))
-    }};
-    (@helper $root:ident, $parent:path, # $begin:tt [] .. $end:tt) => {{
-        (raw_field!($root, $parent, $begin) as usize,
-         raw_field!($root, $parent, $end) as usize)
-    }};
-    // No explicit end for range.
-    (@helper $root:ident, $parent:path, # $begin:tt [] ..) => {{
-        (raw_field!($root, $parent, $begin) as usize,
-         $root as usize + $crate::__priv::size_of_pointee($root))
-    }};
-    (@helper $root:ident, $parent:path, # $begin:tt [] ..=) => {{
-        _memoffset__compile_error!(
-            "Found inclusive range to the end of a struct. Did you mean '..' instead of '..='?")
-    }};
-    // Just one field.
-    (@helper $root:ident, $parent:path, # $field:tt []) => {{
-        let field = raw_field!($root, $parent, $field);
-        (field as usize, field as usize + $crate::__priv::size_of_pointee(field))
-    }};
-    // Parsing.
-    (@helper $root:ident, $parent:path, $(# $begin:tt)+ [] $tt:tt $($rest:tt)*) => {{
-        span_of!(@helper $root, $parent, $(#$begin)* #$tt [] $($rest)*)
-    }};
-    (@helper $root:ident, $parent:path, [] $tt:tt $($rest:tt)*) => {{
-        span_of!(@helper $root, $parent, #$tt [] $($rest)*)
-    }};
+    (*const $sty:ty, $($exp:tt)+) => {
+        _memoffset__reject_parent!("a pointer", span_of, [], $sty, $($exp)+)
+    };
+    (*mut $sty:ty, $($exp:tt)+) => {
+        _memoffset__reject_parent!("a pointer", span_of, [], $sty, $($exp)+)
+    };
+}
 
-    // Entry point.
+/// Produces a range instance representing the sub-slice containing the specified member,
+/// without checking that the fields are part of the struct.
+///
+/// This is the same as `span_of!`, except that it uses `raw_field_unchecked!` instead of
+/// `raw_field!`.
+///
+/// ## Safety
+/// See `raw_field_unchecked!`: if a field is reached through a `Deref` impl, this will read
+/// uninitialized memory.
+/// The caller has to make sure that it is not, so the macro must be used in an `unsafe` block.
+#[macro_export(local_inner_macros)]
+macro_rules! span_of_unchecked {
+    ($sty:ty, $($exp:tt)+) => ({
+        // Require `unsafe` even for spans that do not project to any field, such as `..`.
+        $crate::__priv::assume_direct_field();
+        _memoffset__span_of_impl!(unchecked, $sty, $($exp)+)
+    });
+}
+
+/// Produces a range instance representing the sub-slice containing the specified union member.
+///
+/// Takes the same span expressions as `span_of!`, although for a union only a single member,
+/// or a range of elements of an array member, is of much use: every member starts at offset 0,
+/// and the span of each ends after its own size.
+///
+/// ## Examples
+/// ```
+/// use memoffset::span_of_union;
+///
+/// #[repr(C)]
+/// union Register {
+///     bits: u32,
+///     low: u16,
+///     bytes: [u8; 4],
+/// }
+///
+/// assert_eq!(span_of_union!(Register, bits), 0..4);
+/// assert_eq!(span_of_union!(Register, low), 0..2);
+/// assert_eq!(span_of_union!(Register, bytes[1..3]), 1..3);
+/// ```
+///
+/// Members of a union nested in a struct can be reached with a nested path in `span_of!`
+/// instead, as in `span_of!(Frame, payload.as_bytes)`, on rustc 1.82 and newer.
+///
+/// ## Note
+/// Due to `macro_rules!` limitations, this macro will accept structs with a single field as well as unions.
+/// This is not a stable guarantee, and future versions of this crate might fail
+/// on any use of this macro with a struct, without a semver bump.
+#[macro_export(local_inner_macros)]
+macro_rules! span_of_union {
     ($sty:path, $($exp:tt)+) => ({
-        // Get a base pointer.
-        _memoffset__let_base_ptr!(root, $sty);
-        let base = root as usize;
-        let (begin, end) = span_of!(@helper root, $sty, [] $($exp)*);
-        begin-base..end-base
+        _memoffset__span_of_impl!(union, $sty, $($exp)+)
     });
 }
 
-#[cfg(test)]
-mod tests {
-    use core::mem;
+/// Produces a range instance representing the sub-slice containing the specified member, with
+/// bounds of the given integer type, such as `u32` for an FFI table.
+///
+/// Takes the same span expressions as `span_of!`, followed by the integer type.
+/// Panics if a bound does not fit in the integer type, instead of silently truncating it.
+///
+/// ## Examples
+/// ```
+/// use memoffset::span_of_as;
+///
+/// #[repr(C)]
+/// struct Foo {
+///     a: u32,
+///     b: [u8; 300],
+///     c: u8,
+/// }
+///
+/// assert_eq!(span_of_as!(Foo, b, u16), 4u16..304);
+/// assert_eq!(span_of_as!(Foo, a..=b, u32), 0u32..304);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! span_of_as {
+    ($sty:path, $($exp:tt)+) => {
+        _memoffset__span_of_as!($sty, [] $($exp)+)
+    };
+}
 
-    #[test]
-    fn span_simple() {
-        #[repr(C)]
-        struct Foo {
-            a: u32,
-            b: [u8; 2],
-            c: i64,
-        }
+/// Produces the `(start, end)` bounds of the sub-slice containing the specified member, as a
+/// tuple rather than a range.
+///
+/// Takes the same arguments as `span_of!`.
+/// Unlike a `Range`, the tuple is `Copy`, and its bounds can be used directly in `const`
+/// items, such as the lengths of arrays, on rustc 1.65 and newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::span_of_pair;
+///
+/// #[repr(C)]
+/// struct Message {
+///     header: [u8; 4],
+///     body: [u8; 12],
+///     checksum: u32,
+/// }
+///
+/// assert_eq!(span_of_pair!(Message, body), (4, 16));
+///
+/// # #[cfg(stable_const)]
+/// # {
+/// const CRC_RANGE: (usize, usize) = span_of_pair!(Message, header..checksum);
+/// const COVERED: [u8; CRC_RANGE.1 - CRC_RANGE.0] = [0; CRC_RANGE.1 - CRC_RANGE.0];
+///
+/// assert_eq!(CRC_RANGE, (0, 16));
+/// assert_eq!(COVERED.len(), 16);
+/// # }
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! span_of_pair {
+    ($($args:tt)+) => {{
+        let span = span_of!($($args)+);
+        (span.start, span.end)
+    }};
+}
 
-        assert_eq!(span_of!(Foo, a), 0..4);
-        assert_eq!(span_of!(Foo, b), 4..6);
-        assert_eq!(span_of!(Foo, c), 8..8 + 8);
-    }
+/// Produces the offset and length of the sub-slice containing the specified member, as an
+/// `(offset, len)` tuple, such as for `ptr::copy_nonoverlapping` or the offset and length
+/// fields of an ioctl request.
+///
+/// Takes the same arguments as `span_of!`, and can be used in `const` items on rustc 1.65 and
+/// newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::span_of_len;
+///
+/// #[repr(C)]
+/// struct Message {
+///     header: [u8; 4],
+///     body: [u8; 12],
+///     checksum: u32,
+/// }
+///
+/// assert_eq!(span_of_len!(Message, body), (4, 12));
+/// assert_eq!(span_of_len!(Message, header..=body), (0, 16));
+/// assert_eq!(span_of_len!(Message, body[2..]), (6, 10));
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! span_of_len {
+    ($($args:tt)+) => {{
+        let span = span_of!($($args)+);
+        (span.start, span.end - span.start)
+    }};
+}
 
-    #[test]
-    #[cfg_attr(miri, ignore)] // this creates unaligned references
-    fn span_simple_packed() {
-        #[repr(C, packed)]
-        struct Foo {
-            a: u32,
-            b: [u8; 2],
-            c: i64,
-        }
+/// Produces an array with the range of each of the specified members, in the order they are
+/// listed, such as for the segments of a vectored write.
+///
+/// Each member is written as in the covering form of `span_of!`: a field, an element of an
+/// array field, as in `array[3]`, or on rustc 1.82 and newer a nested path.
+/// Unlike `span_of!(Struct, {a, b})`, the ranges are not merged, so the bytes between them are
+/// not included.
+/// The array can be used to initialize a `static` or `const` item on rustc 1.65 and newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::spans_of;
+///
+/// #[repr(C)]
+/// struct Frame {
+///     len: u16,
+///     flags: u16,
+///     payload: [u8; 12],
+///     crc: u32,
+/// }
+///
+/// assert_eq!(spans_of!(Frame, [crc, len, payload[3]]), [16..20, 0..2, 7..8]);
+///
+/// # #[cfg(stable_const)]
+/// # {
+/// static SEGMENTS: [std::ops::Range<usize>; 2] = spans_of!(Frame, [len, payload]);
+///
+/// assert_eq!(SEGMENTS, [0..2, 4..16]);
+/// # }
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! spans_of {
+    ($sty:path, [$($($field:tt).+ $([$index:expr])*),+ $(,)*] $(,)*) => ({
+        _memoffset__let_base_ptr!(root, $sty);
+        [$({
+            let (begin, end) = _memoffset__span_of_entry!(checked root, $sty, $($field).+ $([$index])*);
+            _memoffset__check_span!(
+                $sty,
+                ($($field).+ $([$index])*),
+                begin,
+                end,
+                $crate::__priv::size_of_pointee(root)
+            );
+            begin..end
+        }),+]
+    });
+}
 
-        assert_eq!(span_of!(Foo, a), 0..4);
-        assert_eq!(span_of!(Foo, b), 4..6);
-        assert_eq!(span_of!(Foo, c), 6..6 + 8);
-    }
+/// Produces the number of bytes between the end of one field and the start of a field that
+/// follows it.
+///
+/// For two adjacent fields of a `repr(C)` struct, this is the padding between them, which makes
+/// it handy for layout tests such as `assert_eq!(padding_between!(S, a, b), 0)`.
+/// Either field may be a nested path on rustc 1.82 and newer.
+/// Any field in between counts towards the result as well.
+///
+/// If the second field does not come after the first, this is rejected with a message naming
+/// both fields: at compile time on rustc 1.79 and newer, and with a panic on older versions.
+///
+/// ## Examples
+/// ```
+/// use memoffset::padding_between;
+///
+/// #[repr(C)]
+/// struct Record {
+///     tag: u8,
+///     value: u32,
+///     flags: u16,
+/// }
+///
+/// assert_eq!(padding_between!(Record, tag, value), 3);
+/// assert_eq!(padding_between!(Record, value, flags), 0);
+/// ```
+///
+/// ```compile_fail
+/// # #[cfg(not(inline_const))] compile_error!("checked at run time");
+/// use memoffset::padding_between;
+///
+/// #[repr(C)]
+/// struct Record {
+///     tag: u8,
+///     value: u32,
+/// }
+///
+/// let padding = padding_between!(Record, value, tag);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! padding_between {
+    ($parent:path, $($first:tt).+, $($second:tt).+ $(,)*) => {{
+        _memoffset__let_base_ptr!(root, $parent);
+        _memoffset__padding_between!(root, $parent, ($($first).+), ($($second).+), {
+            let (_, end) = _memoffset__span_of_field!(checked root, $parent, $($first).+);
+            let (begin, _) = _memoffset__span_of_field!(checked root, $parent, $($second).+);
+            (end, begin)
+        })
+    }};
+}
 
-    #[test]
-    fn span_forms() {
-        #[repr(C)]
-        struct Florp {
-            a: u32,
-        }
+/// Produces the number of bytes between the end of a field and the end of the struct.
+///
+/// For the last field of a `repr(C)` struct, this is the padding at the end of the struct.
+/// The field may be a nested path on rustc 1.82 and newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::padding_after;
+///
+/// #[repr(C)]
+/// struct Record {
+///     value: u32,
+///     tag: u8,
+/// }
+///
+/// assert_eq!(padding_after!(Record, tag), 3);
+/// assert_eq!(padding_after!(Record, value), 4);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! padding_after {
+    ($parent:path, $($field:tt).+ $(,)*) => {{
+        _memoffset__let_base_ptr!(root, $parent);
+        let (_, end) = _memoffset__span_of_field!(checked root, $parent, $($field).+);
+        $crate::__priv::size_of_pointee(root) - end
+    }};
+}
 
-        #[repr(C)]
-        struct Blarg {
-            x: u64,
-            y: [u8; 56],
-            z: Florp,
-            egg: [[u8; 4]; 5],
+/// Checks that the second field given to `padding_between!` does not start before the first
+/// ends, at compile time if rustc supports inline `const` blocks.
+#[cfg(inline_const)]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__padding_between {
+    ($root:ident, $parent:tt, ($($first:tt)+), ($($second:tt)+), $span:block) => {{
+        // The span is computed from a base pointer of its own, inside the `const` block.
+        let _ = $root;
+        const {
+            $crate::_memoffset__let_base_ptr!($root, $parent);
+            let (end, begin) = $span;
+            assert!(
+                end <= begin,
+                concat!(
+                    "`",
+                    $(stringify!($second),)+
+                    "` of `",
+                    stringify!($parent),
+                    "` does not come after `",
+                    $(stringify!($first),)+
+                    "`"
+                )
+            );
+            begin - end
         }
+    }};
+}
+/// Checks that the second field given to `padding_between!` does not start before the first
+/// ends, at compile time if rustc supports inline `const` blocks.
+#[cfg(not(inline_const))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__padding_between {
+    ($root:ident, $parent:tt, ($($first:tt)+), ($($second:tt)+), $span:block) => {{
+        let (end, begin) = $span;
+        assert!(
+            end <= begin,
+            concat!(
+                "`",
+                $(stringify!($second),)+
+                "` of `",
+                stringify!($parent),
+                "` does not come after `",
+                $(stringify!($first),)+
+                "`"
+            )
+        );
+        begin - end
+    }};
+}
 
-        // Love me some brute force
-        assert_eq!(0..8, span_of!(Blarg, x));
-        assert_eq!(64..68, span_of!(Blarg, z));
-        assert_eq!(68..mem::size_of::<Blarg>(), span_of!(Blarg, egg));
-
-        assert_eq!(8..64, span_of!(Blarg, y..z));
-        assert_eq!(0..64, span_of!(Blarg, x..=y));
-    }
+/// Splits the integer type off the end of the arguments of `span_of_as!`.
+#[doc(hidden)]
+#[macro_export(local_inner_macros)]
+macro_rules! _memoffset__span_of_as {
+    ($sty:path, [$($exp:tt)*] , $ty:ty $(,)*) => {{
+        let span = span_of!($sty, $($exp)*);
+        let start = span.start as $ty;
+        let end = span.end as $ty;
+        $crate::__priv::assert_cast_fits(span.start, start as usize);
+        $crate::__priv::assert_cast_fits(span.end, end as usize);
+        start..end
+    }};
+    ($sty:path, [$($exp:tt)*] $tt:tt $($rest:tt)*) => {
+        _memoffset__span_of_as!($sty, [$($exp)* $tt] $($rest)*)
+    };
+    ($sty:path, [$($exp:tt)*]) => {
+        _memoffset__compile_error!("Expected the integer type after the span expression")
+    };
+}
 
-    #[test]
-    fn ig_test() {
-        #[repr(C)]
-        struct Member {
-            foo: u32,
-        }
+/// Resolves the element range of an array field, written as in a slice index, to a
+/// `(start, end)` byte range relative to the start of the field.
+#[doc(hidden)]
+#[macro_export(local_inner_macros)]
+macro_rules! _memoffset__element_span {
+    ($mode:ident, $field_ptr:ident, $parent:tt, $field:tt, [..]) => {
+        _memoffset__element_span_impl!($mode, $field_ptr, $parent, $field, 0, None)
+    };
+    ($mode:ident, $field_ptr:ident, $parent:tt, $field:tt, [.. $end:tt]) => {
+        _memoffset__element_span_impl!($mode, $field_ptr, $parent, $field, 0, Some($end))
+    };
+    ($mode:ident, $field_ptr:ident, $parent:tt, $field:tt, [..= $end:tt]) => {
+        _memoffset__element_span_impl!($mode, $field_ptr, $parent, $field, 0, Some($crate::__priv::end_of_element($end)))
+    };
+    ($mode:ident, $field_ptr:ident, $parent:tt, $field:tt, [$start:tt ..]) => {
+        _memoffset__element_span_impl!($mode, $field_ptr, $parent, $field, $start, None)
+    };
+    ($mode:ident, $field_ptr:ident, $parent:tt, $field:tt, [$start:tt .. $end:tt]) => {
+        _memoffset__element_span_impl!($mode, $field_ptr, $parent, $field, $start, Some($end))
+    };
+    ($mode:ident, $field_ptr:ident, $parent:tt, $field:tt, [$start:tt ..= $end:tt]) => {
+        _memoffset__element_span_impl!($mode, $field_ptr, $parent, $field, $start, Some($crate::__priv::end_of_element($end)))
+    };
+    ($mode:ident, $field_ptr:ident, $parent:tt, $field:tt, [$($range:tt)*]) => {
+        _memoffset__compile_error!(_memoffset__concat!(
+            "Expected a range of array elements, found '[",
+            _memoffset__stringify!($($range)*),
+            "]'"
+        ))
+    };
+}
 
-        #[repr(C)]
-        struct Test {
-            x: u64,
-            y: [u8; 56],
-            z: Member,
-            egg: [[u8; 4]; 4],
+/// Checks the bounds of an element range at compile time, if rustc supports inline `const`
+/// blocks; this requires the bounds to be constants.
+#[cfg(inline_const)]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__element_span_impl {
+    ($mode:ident, $field_ptr:ident, $parent:tt, $field:tt, $start:expr, $variant:ident $(($end:expr))*) => {
+        const {
+            $crate::_memoffset__let_base_ptr!(root, $parent);
+            let field = $crate::_memoffset__raw_field_mode!($mode, root, $parent, $field);
+            $crate::__priv::element_span(
+                field as *const [_],
+                $start,
+                $crate::__priv::Option::$variant $(($end))*,
+                $crate::__priv::size_of_pointee(field),
+            )
         }
+    };
+}
+#[cfg(not(inline_const))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__element_span_impl {
+    ($mode:ident, $field_ptr:ident, $parent:tt, $field:tt, $start:expr, $variant:ident $(($end:expr))*) => {
+        $crate::__priv::element_span(
+            $field_ptr as *const [_],
+            $start,
+            $crate::__priv::Option::$variant $(($end))*,
+            $crate::__priv::size_of_pointee($field_ptr),
+        )
+    };
+}
 
-        assert_eq!(span_of!(Test, ..x), 0..0);
-        assert_eq!(span_of!(Test, ..=x), 0..8);
+/// Resolves a range of elements of an array parent, written with each bound in brackets, to a
+/// `(start, end)` byte range.
+#[doc(hidden)]
+#[macro_export(local_inner_macros)]
+macro_rules! _memoffset__span_of_array {
+    ($elem:ty, $len:expr, .. $(,)*) => {
+        _memoffset__array_span_impl!($elem, $len, 0, None)
+    };
+    ($elem:ty, $len:expr, .. [$end:expr] $(,)*) => {
+        _memoffset__array_span_impl!($elem, $len, 0, Some($end))
+    };
+    ($elem:ty, $len:expr, ..= [$end:expr] $(,)*) => {
+        _memoffset__array_span_impl!($elem, $len, 0, Some($crate::__priv::end_of_element($end)))
+    };
+    ($elem:ty, $len:expr, [$start:expr] $(,)*) => {
+        _memoffset__array_span_impl!($elem, $len, $start, Some($crate::__priv::end_of_element($start)))
+    };
+    ($elem:ty, $len:expr, [$start:expr] .. $(,)*) => {
+        _memoffset__array_span_impl!($elem, $len, $start, None)
+    };
+    ($elem:ty, $len:expr, [$start:expr] .. [$end:expr] $(,)*) => {
+        _memoffset__array_span_impl!($elem, $len, $start, Some($end))
+    };
+    ($elem:ty, $len:expr, [$start:expr] ..= [$end:expr] $(,)*) => {
+        _memoffset__array_span_impl!($elem, $len, $start, Some($crate::__priv::end_of_element($end)))
+    };
+    ($elem:ty, $len:expr, $($exp:tt)+) => {
+        _memoffset__compile_error!(_memoffset__concat!(
+            "Expected a range of array elements such as '[1]..=[3]', found '",
+            _memoffset__stringify!($($exp)+),
+            "'"
+        ))
+    };
+}
+
+/// Checks the bounds of an element range of an array parent at compile time, if rustc
+/// supports inline `const` blocks; this requires the bounds to be constants.
+#[cfg(inline_const)]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__array_span_impl {
+    ($elem:ty, $len:expr, $start:expr, $variant:ident $(($end:expr))*) => {
+        const {
+            $crate::__priv::array_span::<$elem>(
+                $len,
+                $start,
+                $crate::__priv::Option::$variant $(($end))*,
+            )
+        }
+    };
+}
+#[cfg(not(inline_const))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__array_span_impl {
+    ($elem:ty, $len:expr, $start:expr, $variant:ident $(($end:expr))*) => {
+        $crate::__priv::array_span::<$elem>(
+            $len,
+            $start,
+            $crate::__priv::Option::$variant $(($end))*,
+        )
+    };
+}
+
+/// Projects to a field with or without the field check, depending on the mode.
+#[doc(hidden)]
+#[macro_export(local_inner_macros)]
+macro_rules! _memoffset__raw_field_mode {
+    (checked, $base:expr, $parent:path, $($field:tt).+) => {
+        raw_field!($base, $parent, $($field).+)
+    };
+    (unchecked, $base:expr, $parent:ty, $($field:tt).+) => {
+        raw_field_unchecked!($base, $parent, $($field).+)
+    };
+    (union, $base:expr, $parent:path, $field:tt) => {
+        raw_field_union!($base, $parent, $field)
+    };
+    (qualified, $base:expr, $parent:ty, $field:tt) => {{
+        _memoffset__field_check_qualified!($parent, $field);
+        #[allow(unused_unsafe)] // for when the macro is used in an unsafe block
+        unsafe {
+            raw_field_unchecked!($base, $parent, $field)
+        }
+    }};
+}
+
+/// Resolves one entry of a set of fields, which may be an element of an array field, to its
+/// `(start, end)` byte range.
+#[doc(hidden)]
+#[macro_export(local_inner_macros)]
+macro_rules! _memoffset__span_of_entry {
+    ($mode:ident $root:ident, $parent:tt, $($field:tt).+) => {
+        _memoffset__span_of_field!($mode $root, $parent, $($field).+)
+    };
+    ($mode:ident $root:ident, $parent:tt, $($field:tt).+ [$index:expr]) => {{
+        let field = _memoffset__raw_field_mode!($mode, $root, $parent, $($field).+);
+        let index = $index;
+        let (begin, end) = $crate::__priv::element_span(
+            field as *const [_],
+            index,
+            $crate::__priv::Option::Some($crate::__priv::end_of_element(index)),
+            $crate::__priv::size_of_pointee(field),
+        );
+        let offset = _memoffset_offset_from_unsafe!(field, $root);
+        (offset + begin, offset + end)
+    }};
+}
+
+/// Checks that a range between two fields does not end before it starts, at compile time if
+/// rustc supports inline `const` blocks.
+#[cfg(inline_const)]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__span_of_ordered {
+    ($root:ident, $parent:tt, ($($begin:tt)+) $op:tt ($($end:tt)+), $span:block) => {{
+        // The span is computed from a base pointer of its own, inside the `const` block.
+        let _ = $root;
+        const {
+            $crate::_memoffset__let_base_ptr!($root, $parent);
+            let (begin, end) = $span;
+            assert!(
+                begin <= end,
+                concat!(
+                    "span `",
+                    $(stringify!($begin),)+
+                    stringify!($op),
+                    $(stringify!($end),)+
+                    "` of `",
+                    stringify!($parent),
+                    "` ends before it starts"
+                )
+            );
+            (begin, end)
+        }
+    }};
+}
+/// Checks that a range between two fields does not end before it starts, at compile time if
+/// rustc supports inline `const` blocks.
+#[cfg(not(inline_const))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _memoffset__span_of_ordered {
+    ($root:ident, $parent:tt, ($($begin:tt)+) $op:tt ($($end:tt)+), $span:block) => {{
+        let (begin, end) = $span;
+        if cfg!(debug_assertions) {
+            assert!(
+                begin <= end,
+                concat!(
+                    "span `",
+                    $(stringify!($begin),)+
+                    stringify!($op),
+                    $(stringify!($end),)+
+                    "` of `",
+                    stringify!($parent),
+                    "` ends before it starts"
+                )
+            );
+        }
+        (begin, end)
+    }};
+}
+
+/// Resolves a field, or one end of a range, to a pointer into the parent.
+#[doc(hidden)]
+#[macro_export(local_inner_macros)]
+macro_rules! _memoffset__span_of_field {
+    ($mode:ident $root:ident, $parent:tt, $($field:tt).+) => {{
+        let field = _memoffset__raw_field_mode!($mode, $root, $parent, $($field).+);
+        let offset = _memoffset_offset_from_unsafe!(field, $root);
+        (offset, offset + $crate::__priv::size_of_pointee(field))
+    }};
+}
+
+// Every form is matched by a single rule, without munching the expression token by token, so
+// that long field paths do not run into the recursion limit.
+#[doc(hidden)]
+#[macro_export(local_inner_macros)]
+macro_rules! _memoffset__span_of_impl {
+    // The smallest range covering a set of fields.
+    (@range $mode:ident $root:ident, $parent:tt, { $($($field:tt).+ $([$index:expr])*),+ $(,)* } $(,)*) => {{
+        $crate::__priv::covering_span(&[
+            $(_memoffset__span_of_entry!($mode $root, $parent, $($field).+ $([$index])*)),+
+        ])
+    }};
+    // No explicit begin for range.
+    (@range $mode:ident $root:ident, $parent:tt, .. $(,)*) => {{
+        (0, $crate::__priv::size_of_pointee($root))
+    }};
+    (@range $mode:ident $root:ident, $parent:tt, ..= $(,)*) => {{
+        _memoffset__compile_error!("Expected a range, found '..='")
+    }};
+    (@range $mode:ident $root:ident, $parent:tt, ..= $($end:tt).+ $(,)*) => {{
+        let (_, end) = _memoffset__span_of_field!($mode $root, $parent, $($end).+);
+        (0, end)
+    }};
+    (@range $mode:ident $root:ident, $parent:tt, .. $($end:tt).+ $(,)*) => {{
+        let (end, _) = _memoffset__span_of_field!($mode $root, $parent, $($end).+);
+        (0, end)
+    }};
+    // No explicit end for range.
+    (@range $mode:ident $root:ident, $parent:tt, $($begin:tt).+ .. $(,)*) => {{
+        let (begin, _) = _memoffset__span_of_field!($mode $root, $parent, $($begin).+);
+        (begin, $crate::__priv::size_of_pointee($root))
+    }};
+    (@range $mode:ident $root:ident, $parent:tt, $($begin:tt).+ ..= $(,)*) => {{
+        _memoffset__compile_error!(
+            "Found inclusive range to the end of a struct. Did you mean '..' instead of '..='?")
+    }};
+    // Explicit begin and end for range.
+    (@range $mode:ident $root:ident, $parent:tt, $($begin:tt).+ ..= $($end:tt).+ $(,)*) => {
+        _memoffset__span_of_ordered!($root, $parent, ($($begin).+) ..= ($($end).+), {
+            let (begin, _) = _memoffset__span_of_field!($mode $root, $parent, $($begin).+);
+            let (_, end) = _memoffset__span_of_field!($mode $root, $parent, $($end).+);
+            (begin, end)
+        })
+    };
+    (@range $mode:ident $root:ident, $parent:tt, $($begin:tt).+ .. $($end:tt).+ $(,)*) => {
+        _memoffset__span_of_ordered!($root, $parent, ($($begin).+) .. ($($end).+), {
+            let (begin, _) = _memoffset__span_of_field!($mode $root, $parent, $($begin).+);
+            let (end, _) = _memoffset__span_of_field!($mode $root, $parent, $($end).+);
+            (begin, end)
+        })
+    };
+    // A range of elements of an array field.
+    (@range $mode:ident $root:ident, $parent:tt, $field:tt [$($range:tt)*] $(,)*) => {{
+        let field = _memoffset__raw_field_mode!($mode, $root, $parent, $field);
+        let (begin, end) = _memoffset__element_span!($mode, field, $parent, $field, [$($range)*]);
+        let offset = _memoffset_offset_from_unsafe!(field, $root);
+        (offset + begin, offset + end)
+    }};
+    (@range $mode:ident $root:ident, $parent:tt, $field:tt [$($range:tt)*] $($rest:tt)+) => {{
+        _memoffset__compile_error!("A range of array elements must be the whole span expression")
+    }};
+    // Just one field, which may be a nested path.
+    (@range $mode:ident $root:ident, $parent:tt, $($field:tt).+ $(,)*) => {{
+        _memoffset__span_of_field!($mode $root, $parent, $($field).+)
+    }};
+    (@range $mode:ident $root:ident, $parent:tt, $($exp:tt)*) => {{
+        _memoffset__compile_error!(_memoffset__concat!(
+            "Expected a field or a range of fields, found '",
+            _memoffset__stringify!($($exp)*),
+            "'"
+        ))
+    }};
+
+    // Entry point.
+    (qualified, $sty:ty, $($exp:tt)+) => ({
+        _memoffset__let_base_ptr!(root, $sty);
+        let (begin, end) = _memoffset__span_of_impl!(@range qualified root, $sty, $($exp)*);
+        _memoffset__check_span!($sty, ($($exp)*), begin, end, $crate::__priv::size_of_pointee(root));
+        begin..end
+    });
+    (union, $sty:path, $($exp:tt)+) => ({
+        // Get a base pointer.
+        _memoffset__let_base_ptr!(root, $sty);
+        let (begin, end) = _memoffset__span_of_impl!(@range union root, $sty, $($exp)*);
+        _memoffset__check_span!($sty, ($($exp)*), begin, end, $crate::__priv::size_of_pointee(root));
+        begin..end
+    });
+    (unchecked, $sty:ty, $($exp:tt)+) => ({
+        // Get a base pointer.
+        _memoffset__let_base_ptr!(root, $sty);
+        let (begin, end) = _memoffset__span_of_impl!(@range unchecked root, $sty, $($exp)*);
+        begin..end
+    });
+    (checked, $sty:path, $($exp:tt)+) => ({
+        // Get a base pointer.
+        _memoffset__let_base_ptr!(root, $sty);
+        let (begin, end) = _memoffset__span_of_impl!(@range checked root, $sty, $($exp)*);
+        _memoffset__check_span!($sty, ($($exp)*), begin, end, $crate::__priv::size_of_pointee(root));
+        begin..end
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use core::mem;
+
+    #[test]
+    fn span_simple() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: [u8; 2],
+            c: i64,
+        }
+
+        assert_eq!(span_of!(Foo, a), 0..4);
+        assert_eq!(span_of!(Foo, b), 4..6);
+        assert_eq!(span_of!(Foo, c), 8..8 + 8);
+    }
+
+    #[test]
+    fn span_path() {
+        mod sub {
+            #[repr(C)]
+            pub struct Foo {
+                pub x: u32,
+                pub y: u16,
+            }
+
+            #[repr(C)]
+            pub struct Pair(pub u8, pub u32);
+
+            pub mod inner {
+                pub fn y_span() -> ::core::ops::Range<usize> {
+                    span_of!(super::Foo, y)
+                }
+            }
+        }
+
+        assert_eq!(span_of!(sub::Foo, x..y), 0..4);
+        assert_eq!(span_of!(sub::Pair, 1), 4..8);
+        assert_eq!(sub::inner::y_span(), 4..6);
+    }
+
+    #[test]
+    fn zero_sized_fields() {
+        use core::marker::PhantomData;
+
+        #[repr(C)]
+        struct Tag;
+
+        #[repr(C)]
+        struct Framed {
+            start: Tag,
+            len: u16,
+            marker: PhantomData<u64>,
+            value: u32,
+            end: (),
+        }
+
+        // A zero-sized field starts where the next field starts, after any padding.
+        assert_eq!(span_of!(Framed, start), 0..0);
+        assert_eq!(span_of!(Framed, marker), 2..2);
+        assert_eq!(offset_of!(Framed, value), 4);
+        assert_eq!(span_of!(Framed, end), 8..8);
+
+        // Inclusive ranges ending at a zero-sized field equal the exclusive ones.
+        assert_eq!(
+            span_of!(Framed, len..=marker),
+            span_of!(Framed, len..marker)
+        );
+        assert_eq!(span_of!(Framed, ..=end), span_of!(Framed, ..end));
+        assert_eq!(span_of!(Framed, start..=end), 0..8);
+        assert_eq!(span_of!(Framed, marker..=marker), 2..2);
+        assert_eq!(span_of!(Framed, marker..), 2..mem::size_of::<Framed>());
+    }
+
+    #[test]
+    fn zero_sized_struct() {
+        use core::marker::PhantomData;
+
+        #[repr(C)]
+        struct Empty {
+            a: (),
+            b: PhantomData<u32>,
+            c: [u64; 0],
+        }
+
+        assert_eq!(mem::size_of::<Empty>(), 0);
+        assert_eq!(offset_of!(Empty, a), 0);
+        assert_eq!(offset_of!(Empty, b), 0);
+        assert_eq!(offset_of!(Empty, c), 0);
+        assert_eq!(span_of!(Empty, a..=c), 0..0);
+        assert_eq!(span_of!(Empty, ..), 0..0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn drop_and_non_send_fields() {
+        use core::marker::PhantomPinned;
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        use std::boxed::Box;
+        use std::rc::Rc;
+        use std::string::String;
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        #[repr(C)]
+        struct Owner {
+            id: u32,
+            name: String,
+            callback: Box<dyn Fn() -> u32>,
+            shared: Rc<u8>,
+            pin: PhantomPinned,
+        }
+
+        impl Drop for Owner {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let word = mem::size_of::<usize>();
+        let name = offset_of!(Owner, name);
+        assert_eq!(name, word);
+        assert_eq!(span_of!(Owner, name), name..name + 3 * word);
+        assert_eq!(offset_of!(Owner, callback), name + 3 * word);
+        assert_eq!(span_of!(Owner, callback), name + 3 * word..name + 5 * word);
+        assert_eq!(span_of!(Owner, shared..), name + 5 * word..name + 6 * word);
+        assert_eq!(span_of!(Owner, pin), name + 6 * word..name + 6 * word);
+
+        let owner = Owner {
+            id: 1,
+            name: String::from("owner"),
+            callback: Box::new(|| 2),
+            shared: Rc::new(3),
+            pin: PhantomPinned,
+        };
+        let base = &owner as *const Owner;
+        let callback = raw_field!(base, Owner, callback);
+        assert_eq!(unsafe { (*callback)() }, 2);
+        assert_eq!(container_of!(callback, Owner, callback), base);
+        assert_eq!(unsafe { &*raw_field!(base, Owner, name) }, "owner");
+        assert_eq!((owner.id, *owner.shared), (1, 3));
+
+        // No value of the parent type is ever created, let alone dropped, by the macros.
+        assert_eq!(DROPS.load(Ordering::SeqCst), 0);
+        drop(owner);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+    }
+
+    // Const generics, like `addr_of!`, require rustc 1.51.
+    #[cfg(raw_ref_macros)]
+    #[test]
+    fn span_const_generic() {
+        #[repr(C)]
+        struct Block<const N: usize> {
+            header: u32,
+            data: [u8; N],
+        }
+
+        fn data_span<const N: usize>() -> (usize, ::core::ops::Range<usize>) {
+            (offset_of!(Block<N>, data), span_of!(Block<N>, data))
+        }
+
+        assert_eq!(data_span::<0>(), (4, 4..4));
+        assert_eq!(data_span::<3>(), (4, 4..7));
+        assert_eq!(data_span::<64>(), (4, 4..68));
+        assert_eq!(span_of!(Block<16>, header..=data), 0..20);
+        assert_eq!(span_of!(Block<{ 8 * 2 }>, data[4..]), 8..20);
+
+        #[repr(C)]
+        struct Envelope<H, P, const N: usize> {
+            header: H,
+            body: [P; N],
+        }
+
+        assert_eq!(span_of!(Envelope<u16, u8, 3>, body), 2..5);
+        assert_eq!(span_of!(Envelope<u32, Block<2>, { 1 + 1 }>, ..=body), 0..20);
+        assert_eq!(span_of!(Envelope<u8, u16, 4>, body[1..]), 4..10);
+    }
+
+    #[test]
+    #[cfg_attr(all(miri, not(raw_ref_macros)), ignore)] // this creates unaligned references
+    fn span_simple_packed() {
+        #[repr(C, packed)]
+        struct Foo {
+            a: u32,
+            b: [u8; 2],
+            c: i64,
+        }
+
+        assert_eq!(span_of!(Foo, a), 0..4);
+        assert_eq!(span_of!(Foo, b), 4..6);
+        assert_eq!(span_of!(Foo, c), 6..6 + 8);
+    }
+
+    // Without `addr_of!`, projecting to a field creates a reference, which may be unaligned.
+    #[cfg(raw_ref_macros)]
+    #[test]
+    fn span_packed_n() {
+        fn round_up(offset: usize, align: usize) -> usize {
+            (offset + align - 1) & !(align - 1)
+        }
+
+        macro_rules! check_packed {
+            ($($n:tt)*) => {$({
+                #[repr(C, packed($n))]
+                struct Packed {
+                    a: u8,
+                    b: u64,
+                    c: u16,
+                    d: u32,
+                }
+
+                #[repr(C, align(16))]
+                struct Outer {
+                    tag: u8,
+                    packed: Packed,
+                    tail: u8,
+                }
+
+                let pack: usize = $n;
+                let b = round_up(1, mem::align_of::<u64>().min(pack));
+                let c = round_up(b + 8, mem::align_of::<u16>().min(pack));
+                let d = round_up(c + 2, mem::align_of::<u32>().min(pack));
+                assert_eq!(span_of!(Packed, a), 0..1);
+                assert_eq!(span_of!(Packed, b), b..b + 8);
+                assert_eq!(span_of!(Packed, c..=d), c..d + 4);
+                assert_eq!(span_of!(Packed, ..), 0..mem::size_of::<Packed>());
+
+                let packed = offset_of!(Outer, packed);
+                assert_eq!(packed, round_up(1, mem::align_of::<Packed>()));
+                assert_eq!(span_of!(Outer, packed), packed..packed + mem::size_of::<Packed>());
+                assert_eq!(offset_of!(Outer, tail), packed + mem::size_of::<Packed>());
+                assert_eq!(mem::size_of::<Outer>() % 16, 0);
+
+                let outer = Outer {
+                    tag: 1,
+                    packed: Packed { a: 2, b: 3, c: 4, d: 5 },
+                    tail: 6,
+                };
+                let base = &outer.packed as *const Packed;
+                let b_ptr = raw_field!(base, Packed, b);
+                let d_ptr = raw_field!(base, Packed, d);
+                assert_eq!(b_ptr as usize - base as usize, b);
+                assert_eq!(unsafe { b_ptr.read_unaligned() }, 3);
+                assert_eq!(unsafe { d_ptr.read_unaligned() }, 5);
+                assert_eq!(container_of!(d_ptr, Packed, d), base);
+                assert_eq!(
+                    raw_field!(&outer as *const Outer, Outer, tail) as usize - base as usize,
+                    mem::size_of::<Packed>()
+                );
+                assert_eq!((outer.tag, outer.tail), (1, 6));
+            })*};
+        }
+
+        check_packed!(1 2 4 8);
+    }
+
+    // Without `addr_of!`, projecting to a field creates a reference, which may be unaligned.
+    #[cfg(raw_ref_macros)]
+    #[test]
+    fn span_align_n() {
+        fn round_up(offset: usize, align: usize) -> usize {
+            (offset + align - 1) & !(align - 1)
+        }
+
+        #[repr(C, packed)]
+        struct Packed {
+            a: u8,
+            b: u32,
+        }
+
+        macro_rules! check_aligned {
+            ($($n:tt)*) => {$({
+                #[repr(C, align($n))]
+                struct Aligned {
+                    tag: u8,
+                    packed: Packed,
+                    samples: [[u16; 3]; 2],
+                    marker: (),
+                    last: u8,
+                }
+
+                #[repr(C)]
+                struct Outer {
+                    head: u8,
+                    aligned: [Aligned; 2],
+                    tail: (),
+                }
+
+                let align: usize = $n;
+                let size = round_up(19, align);
+                assert_eq!(mem::align_of::<Aligned>(), align);
+                assert_eq!(mem::size_of::<Aligned>(), size);
+
+                assert_eq!(span_of!(Aligned, tag), 0..1);
+                assert_eq!(span_of!(Aligned, packed), 1..6);
+                assert_eq!(span_of!(Aligned, samples), 6..18);
+                assert_eq!(span_of!(Aligned, samples[1..]), 12..18);
+                assert_eq!(span_of!(Aligned, marker), 18..18);
+                assert_eq!(span_of!(Aligned, last), 18..19);
+                assert_eq!(span_of!(Aligned, ..), 0..size);
+
+                // An open end runs to the end of the struct, including the tail padding,
+                // while an inclusive end stops at the end of the last field.
+                assert_eq!(span_of!(Aligned, last..), 18..size);
+                assert_eq!(span_of!(Aligned, ..=last), 0..19);
+                assert_eq!(span_of!(Aligned, packed..), 1..size);
+                assert_eq!(span_of!(Aligned, packed..=last), 1..19);
+                assert_eq!(span_of!(Aligned, ..=marker), span_of!(Aligned, ..last));
+
+                assert_eq!(offset_of!(Outer, aligned), align);
+                assert_eq!(span_of!(Outer, aligned[1..]), align + size..align + 2 * size);
+                assert_eq!(span_of!(Outer, aligned..), align..mem::size_of::<Outer>());
+                assert_eq!(offset_of!(Outer, tail), align + 2 * size);
+                assert_eq!(mem::size_of::<Outer>(), align + 2 * size);
+
+                let outer = Outer {
+                    head: 1,
+                    aligned: [
+                        Aligned { tag: 2, packed: Packed { a: 3, b: 4 }, samples: [[5; 3]; 2], marker: (), last: 6 },
+                        Aligned { tag: 7, packed: Packed { a: 8, b: 9 }, samples: [[10; 3]; 2], marker: (), last: 11 },
+                    ],
+                    tail: (),
+                };
+                let second = &outer.aligned[1] as *const Aligned;
+                assert_eq!(second as usize % align, 0);
+                let packed = raw_field!(second, Aligned, packed);
+                let b = raw_field!(packed, Packed, b);
+                assert_eq!(b as usize - second as usize, 2);
+                assert_eq!(unsafe { b.read_unaligned() }, 9);
+                let last = raw_field!(second, Aligned, last);
+                assert_eq!(unsafe { *last }, 11);
+                assert_eq!(container_of!(last, Aligned, last), second);
+                assert_eq!(outer.head, 1);
+            })*};
+        }
+
+        check_aligned!(2 4 8 16 32 64 128);
+    }
+
+    #[test]
+    fn span_self_parent() {
+        #[repr(C)]
+        struct Container<T> {
+            tag: u8,
+            len: u16,
+            value: T,
+        }
+
+        impl<T> Container<T> {
+            fn header_span() -> ::core::ops::Range<usize> {
+                span_of!(Self, tag..value)
+            }
+
+            fn value_ptr(this: *const Self) -> *const T {
+                raw_field!(this, Self, value)
+            }
+
+            fn from_value(value: *const T) -> *const Self {
+                container_of!(value, Self, value)
+            }
+        }
+
+        let c = Container {
+            tag: 1,
+            len: 2,
+            value: 3u32,
+        };
+        let base = &c as *const Container<u32>;
+        assert_eq!(Container::<u32>::header_span(), 0..4);
+        assert_eq!(Container::<u64>::header_span(), 0..mem::align_of::<u64>());
+        let value = Container::value_ptr(base);
+        assert_eq!(unsafe { *value }, 3);
+        assert_eq!(Container::from_value(value), base);
+        assert_eq!((c.tag, c.len), (1, 2));
+    }
+
+    #[test]
+    fn span_forms() {
+        #[repr(C)]
+        struct Florp {
+            a: u32,
+        }
+
+        #[repr(C)]
+        struct Blarg {
+            x: u64,
+            y: [u8; 56],
+            z: Florp,
+            egg: [[u8; 4]; 5],
+        }
+
+        // Love me some brute force
+        assert_eq!(0..8, span_of!(Blarg, x));
+        assert_eq!(64..68, span_of!(Blarg, z));
+        assert_eq!(68..mem::size_of::<Blarg>(), span_of!(Blarg, egg));
+
+        assert_eq!(8..64, span_of!(Blarg, y..z));
+        assert_eq!(0..64, span_of!(Blarg, x..=y));
+    }
+
+    #[test]
+    fn span_trailing_comma() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: [u8; 4],
+            c: u64,
+        }
+
+        assert_eq!(span_of!(Foo, b,), 4..8);
+        assert_eq!(span_of!(Foo, a..c,), 0..8);
+        assert_eq!(span_of!(Foo, a..=c,), 0..16);
+        assert_eq!(span_of!(Foo, b..,), 4..16);
+        assert_eq!(span_of!(Foo, ..c,), 0..8);
+        assert_eq!(span_of!(Foo, ..,), 0..16);
+        assert_eq!(unsafe { span_of_unchecked!(Foo, ..=b,) }, 0..8);
+    }
+
+    #[test]
+    fn ig_test() {
+        #[repr(C)]
+        struct Member {
+            foo: u32,
+        }
+
+        #[repr(C)]
+        struct Test {
+            x: u64,
+            y: [u8; 56],
+            z: Member,
+            egg: [[u8; 4]; 4],
+        }
+
+        assert_eq!(span_of!(Test, ..x), 0..0);
+        assert_eq!(span_of!(Test, ..=x), 0..8);
         assert_eq!(span_of!(Test, ..y), 0..8);
         assert_eq!(span_of!(Test, ..=y), 0..64);
         assert_eq!(span_of!(Test, ..z), 0..64);
@@ -233,29 +1340,817 @@ mod tests {
         assert_eq!(span_of!(Test, ..=egg), 0..84);
         assert_eq!(span_of!(Test, ..), 0..mem::size_of::<Test>());
         assert_eq!(
-            span_of!(Test, x..),
-            offset_of!(Test, x)..mem::size_of::<Test>()
+            span_of!(Test, x..),
+            offset_of!(Test, x)..mem::size_of::<Test>()
+        );
+        assert_eq!(
+            span_of!(Test, y..),
+            offset_of!(Test, y)..mem::size_of::<Test>()
+        );
+
+        assert_eq!(
+            span_of!(Test, z..),
+            offset_of!(Test, z)..mem::size_of::<Test>()
+        );
+        assert_eq!(
+            span_of!(Test, egg..),
+            offset_of!(Test, egg)..mem::size_of::<Test>()
+        );
+        assert_eq!(
+            span_of!(Test, x..y),
+            offset_of!(Test, x)..offset_of!(Test, y)
+        );
+        assert_eq!(
+            span_of!(Test, x..=y),
+            offset_of!(Test, x)..offset_of!(Test, y) + mem::size_of::<[u8; 56]>()
+        );
+    }
+
+    #[test]
+    fn span_elements() {
+        #[repr(C)]
+        struct Frame {
+            len: u32,
+            samples: [u16; 8],
+            mac: [[u8; 2]; 3],
+        }
+
+        assert_eq!(span_of!(Frame, samples[2..5]), 8..14);
+        assert_eq!(span_of!(Frame, samples[2..=4]), 8..14);
+        assert_eq!(span_of!(Frame, samples[..]), span_of!(Frame, samples));
+        assert_eq!(span_of!(Frame, samples[..2]), 4..8);
+        assert_eq!(span_of!(Frame, samples[..=0]), 4..6);
+        assert_eq!(span_of!(Frame, samples[6..]), 16..20);
+        assert_eq!(span_of!(Frame, samples[3..3]), 10..10);
+        assert_eq!(span_of!(Frame, samples[8..]), 20..20);
+        assert_eq!(span_of!(Frame, mac[1..]), 22..26);
+        assert_eq!(unsafe { span_of_unchecked!(Frame, mac[(1 + 1)..]) }, 24..26);
+    }
+
+    #[cfg(not(inline_const))]
+    #[test]
+    #[should_panic]
+    fn span_elements_out_of_bounds() {
+        #[repr(C)]
+        struct Frame {
+            samples: [u16; 4],
+        }
+
+        let _ = span_of!(Frame, samples[2..5]);
+    }
+
+    #[test]
+    fn span_array_parent() {
+        #[repr(C)]
+        struct Descriptor {
+            addr: u64,
+            len: u32,
+            flags: u16,
+        }
+
+        assert_eq!(span_of!([Descriptor; 8], [2]), 32..48);
+        assert_eq!(span_of!([Descriptor; 8], [2]..[5]), 32..80);
+        assert_eq!(span_of!([Descriptor; 8], [2]..=[5]), 32..96);
+        assert_eq!(span_of!([Descriptor; 8], [6]..), 96..128);
+        assert_eq!(span_of!([Descriptor; 8], ..[1]), 0..16);
+        assert_eq!(span_of!([Descriptor; 8], ..=[1],), 0..32);
+        assert_eq!(span_of!([Descriptor; 8], ..), 0..128);
+        assert_eq!(span_of!([Descriptor; 8], [8]..), 128..128);
+        assert_eq!(span_of!([(u8, u16); 4], [1]..[3]), 4..12);
+    }
+
+    #[cfg(not(inline_const))]
+    #[test]
+    #[should_panic]
+    fn span_array_parent_out_of_bounds() {
+        let _ = span_of!([u32; 4], [2]..=[4]);
+    }
+
+    #[cfg(not(inline_const))]
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn span_array_parent_overflow() {
+        let _ = span_of!([(); 4], ..=[!0]);
+    }
+
+    #[test]
+    fn span_pair() {
+        #[repr(C)]
+        struct Message {
+            kind: u8,
+            header: [u8; 3],
+            body: [u32; 4],
+            checksum: u16,
+        }
+
+        assert_eq!(span_of_pair!(Message, body), (4, 20));
+        assert_eq!(span_of_pair!(Message, header..checksum), (1, 20));
+        assert_eq!(span_of_pair!(Message, body[1..=2],), (8, 16));
+        assert_eq!(span_of_pair!([Message; 2], [1]), (24, 48));
+    }
+
+    #[test]
+    fn span_len() {
+        #[repr(C)]
+        struct Message {
+            kind: u8,
+            header: [u8; 3],
+            body: [u32; 4],
+            checksum: u16,
+        }
+
+        assert_eq!(span_of_len!(Message, kind), (0, 1));
+        assert_eq!(span_of_len!(Message, header..checksum), (1, 19));
+        assert_eq!(span_of_len!(Message, body[1..=2],), (8, 8));
+        assert_eq!(span_of_len!(Message, body..body), (4, 0));
+        assert_eq!(span_of_len!(Message, checksum..), (20, 4));
+        assert_eq!(span_of_len!([Message; 2], [1]), (24, 24));
+    }
+
+    #[cfg(stable_const)]
+    #[test]
+    fn const_spans() {
+        #[repr(C)]
+        struct Message {
+            kind: u8,
+            header: [u8; 3],
+            body: [u32; 4],
+            checksum: u16,
+        }
+
+        const BODY: ::core::ops::Range<usize> = span_of!(Message, body);
+        const CRC_RANGE: (usize, usize) = span_of_pair!(Message, ..checksum);
+        const TAIL: (usize, usize) = span_of_pair!(Message, checksum..);
+        const BODY_LEN: (usize, usize) = span_of_len!(Message, body);
+        let words = [0u32; BODY_LEN.1 / 4];
+        const COVERED: [u8; CRC_RANGE.1 - CRC_RANGE.0] = [0; CRC_RANGE.1 - CRC_RANGE.0];
+        let padding = [0u8; TAIL.1 - TAIL.0 - 2];
+
+        assert_eq!(BODY, 4..20);
+        assert_eq!(CRC_RANGE, (0, 20));
+        assert_eq!(COVERED.len(), 20);
+        assert_eq!(padding.len(), 2);
+        assert_eq!(words.len(), 4);
+    }
+
+    #[test]
+    fn spans_in_order() {
+        #[repr(C)]
+        struct Request {
+            opcode: u8,
+            flags: u8,
+            len: u16,
+            data: [u32; 4],
+            tag: u64,
+        }
+
+        assert_eq!(spans_of!(Request, [len])[0], 2..4);
+        assert_eq!(
+            spans_of!(Request, [tag, opcode, data[2], len,]),
+            [24..32, 0..1, 12..16, 2..4]
+        );
+        assert_eq!(
+            spans_of!(Request, [data[0], data[3]]),
+            [span_of!(Request, data[..1]), span_of!(Request, data[3..])]
+        );
+
+        let index = 1;
+        assert_eq!(spans_of!(Request, [data[index + 1]])[0], 12..16);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn spans_nested() {
+        #[repr(C)]
+        struct Header {
+            kind: u16,
+            len: u16,
+        }
+
+        #[repr(C)]
+        struct Packet {
+            header: Header,
+            payload: [u8; 8],
+        }
+
+        assert_eq!(
+            spans_of!(Packet, [header.len, payload[7], header.kind]),
+            [2..4, 11..12, 0..2]
         );
+    }
+
+    #[cfg(stable_const)]
+    #[test]
+    fn const_spans_of() {
+        #[repr(C)]
+        struct Message {
+            kind: u8,
+            header: [u8; 3],
+            body: [u32; 4],
+        }
+
+        static SEGMENTS: [::core::ops::Range<usize>; 3] =
+            spans_of!(Message, [body, kind, header[2]]);
+
+        assert_eq!(SEGMENTS, [4..20, 0..1, 3..4]);
+    }
+
+    #[test]
+    fn padding() {
+        #[repr(C)]
+        struct Foo {
+            a: u8,
+            b: u32,
+            c: u16,
+            d: [u8; 3],
+        }
+
+        assert_eq!(padding_between!(Foo, a, b), 3);
+        assert_eq!(padding_between!(Foo, b, c), 0);
+        assert_eq!(padding_between!(Foo, c, d,), 0);
+        assert_eq!(padding_between!(Foo, a, c), 7);
+        assert_eq!(padding_after!(Foo, d), 3);
+        assert_eq!(padding_after!(Foo, a,), 15);
+
+        #[repr(C, packed)]
+        struct Packed {
+            a: u8,
+            b: u32,
+        }
+
+        assert_eq!(padding_between!(Packed, a, b), 0);
+        assert_eq!(padding_after!(Packed, b), 0);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn padding_nested() {
+        #[repr(C)]
+        struct Inner {
+            x: u16,
+            y: u8,
+        }
+
+        #[repr(C)]
+        struct Outer {
+            tag: u8,
+            inner: Inner,
+            len: u32,
+        }
+
+        assert_eq!(padding_between!(Outer, tag, inner.x), 1);
+        assert_eq!(padding_between!(Outer, inner.y, len), 3);
+        assert_eq!(padding_after!(Outer, inner.y), 7);
+    }
+
+    #[cfg(stable_const)]
+    #[test]
+    fn const_padding() {
+        #[repr(C)]
+        struct Foo {
+            a: u16,
+            b: u64,
+            c: u8,
+        }
+
+        const GAP: usize = padding_between!(Foo, a, b);
+        const TAIL: usize = padding_after!(Foo, c);
+
+        assert_eq!(GAP, 6);
+        assert_eq!(TAIL, 7);
+    }
+
+    #[cfg(not(inline_const))]
+    #[test]
+    #[should_panic(expected = "`a` of `Foo` does not come after `b`")]
+    fn padding_reversed() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: u16,
+        }
+
+        let _ = padding_between!(Foo, b, a);
+    }
+
+    #[test]
+    fn span_forwarded_ranges() {
+        #[repr(C)]
+        struct Record {
+            id: u32,
+            name: [u8; 10],
+            crc: u16,
+        }
+
+        // A range expression forwarded by another macro, such as in a list of regions.
+        macro_rules! regions {
+            ($(($($range:tt)+)),+) => {
+                [$(span_of!(Record, $($range)+)),+]
+            };
+        }
+
         assert_eq!(
-            span_of!(Test, y..),
-            offset_of!(Test, y)..mem::size_of::<Test>()
+            regions!(
+                (..),
+                (..name),
+                (..=name),
+                (name..),
+                (id..crc),
+                (id..=crc),
+                (crc)
+            ),
+            [0..16, 0..4, 0..14, 4..16, 0..14, 0..16, 14..16]
         );
+    }
+
+    #[test]
+    fn span_union() {
+        #[repr(C)]
+        union Register {
+            bits: u32,
+            low: u16,
+            bytes: [u8; 4],
+            wide: u64,
+        }
+
+        assert_eq!(span_of_union!(Register, bits), 0..4);
+        assert_eq!(span_of_union!(Register, low), 0..2);
+        assert_eq!(span_of_union!(Register, bytes), 0..4);
+        assert_eq!(span_of_union!(Register, wide,), 0..8);
+        assert_eq!(span_of_union!(Register, bytes[2..]), 2..4);
+        assert_eq!(span_of_union!(Register, ..), 0..mem::size_of::<Register>());
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn span_nested_union() {
+        #[repr(C)]
+        union Payload {
+            word: u32,
+            bytes: [u8; 6],
+            flag: u8,
+        }
+
+        #[repr(C)]
+        struct Frame {
+            len: u16,
+            payload: Payload,
+            crc: u16,
+        }
+
+        assert_eq!(span_of!(Frame, payload.word), 4..8);
+        assert_eq!(span_of!(Frame, payload.bytes), 4..10);
+        assert_eq!(span_of!(Frame, payload.flag), 4..5);
+        assert_eq!(span_of!(Frame, payload), 4..12);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn span_long_path() {
+        #[repr(C)]
+        struct Link<T> {
+            value: u16,
+            next: T,
+        }
+
+        type Chain2<T> = Link<Link<T>>;
+        type Chain4<T> = Chain2<Chain2<T>>;
+        type Chain8<T> = Chain4<Chain4<T>>;
+        type Chain40 = Chain8<Chain8<Chain8<Chain8<Chain8<Link<()>>>>>>;
+
+        // 41 segments, well past what the default recursion limit allowed when the expression
+        // was parsed one token at a time.
+        let field = span_of! {
+            Chain40,
+            next.next.next.next.next.next.next.next.next.next
+                .next.next.next.next.next.next.next.next.next.next
+                .next.next.next.next.next.next.next.next.next.next
+                .next.next.next.next.next.next.next.next.next.next
+                .value
+        };
+        let to_field = span_of! {
+            Chain40,
+            value ..=
+            next.next.next.next.next.next.next.next.next.next
+                .next.next.next.next.next.next.next.next.next.next
+                .next.next.next.next.next.next.next.next.next.next
+                .next.next.next.next.next.next.next.next.next.next
+                .value
+        };
+        let from_field = span_of! {
+            Chain40,
+            next.next.next.next.next.next.next.next.next.next
+                .next.next.next.next.next.next.next.next.next.next
+                .next.next.next.next.next.next.next.next.next.next
+                .next.next.next.next.next.next.next.next.next.next
+                .value ..
+        };
+
+        assert_eq!(field, 80..82);
+        assert_eq!(to_field, 0..82);
+        assert_eq!(from_field, 80..82);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn span_nested() {
+        #[repr(C)]
+        struct Point(u16, u16);
+
+        #[repr(C)]
+        struct Inner {
+            tag: u8,
+            point: Point,
+            len: u32,
+        }
+
+        #[repr(C)]
+        struct Outer {
+            id: u64,
+            inner: Inner,
+        }
 
         assert_eq!(
-            span_of!(Test, z..),
-            offset_of!(Test, z)..mem::size_of::<Test>()
+            span_of!(Outer, inner.len).start,
+            offset_of!(Outer, inner.len)
         );
         assert_eq!(
-            span_of!(Test, egg..),
-            offset_of!(Test, egg)..mem::size_of::<Test>()
+            span_of!(Outer, inner.point.1).start,
+            offset_of!(Outer, inner.point.1)
         );
+        assert_eq!(span_of!(Outer, inner.point.0), 10..12);
+        assert_eq!(span_of!(Outer, inner.point), 10..14);
         assert_eq!(
-            span_of!(Test, x..y),
-            offset_of!(Test, x)..offset_of!(Test, y)
+            unsafe { span_of_unchecked!(Outer, inner.point) },
+            span_of!(Outer, inner.point)
+        );
+    }
+
+    #[test]
+    fn span_tuple_fields() {
+        #[repr(C)]
+        struct Tup(u8, u32, u16, [u8; 6]);
+
+        assert_eq!(span_of!(Tup, 0), 0..1);
+        assert_eq!(span_of!(Tup, 1,), 4..8);
+        assert_eq!(span_of!(Tup, 0..2), 0..8);
+        assert_eq!(span_of!(Tup, 0..=2), 0..10);
+        assert_eq!(span_of!(Tup, ..2), 0..8);
+        assert_eq!(span_of!(Tup, ..=2), 0..10);
+        assert_eq!(span_of!(Tup, 1..), 4..16);
+        assert_eq!(span_of!(Tup, 3[1..3]), 11..13);
+        assert_eq!(span_of!(Tup, {2, 0}), 0..10);
+        assert_eq!(unsafe { span_of_unchecked!(Tup, 1..3) }, 4..10);
+        assert_eq!(spans_of!(Tup, [2, 3[5]]), [8..10, 15..16]);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn span_nested_tuple_fields() {
+        #[repr(C)]
+        struct Header(u16, u8);
+
+        #[repr(C)]
+        struct Named {
+            header: Header,
+            len: u32,
+        }
+
+        #[repr(C)]
+        struct Outer(u32, Header, Named);
+
+        // `1.0` and `1.1` are single float literal tokens.
+        assert_eq!(span_of!(Outer, 1.0), 4..6);
+        assert_eq!(span_of!(Outer, 1.1), 6..7);
+        assert_eq!(span_of!(Outer, 2.header.1), 10..11);
+        assert_eq!(span_of!(Outer, 1.0..1.1), 4..6);
+        assert_eq!(span_of!(Outer, 1.0..=1.1), 4..7);
+        assert_eq!(span_of!(Outer, 0..=2.header.0), 0..10);
+        assert_eq!(span_of!(Outer, 1.1..2), 6..8);
+        assert_eq!(span_of!(Outer, ..1.1), 0..6);
+        assert_eq!(span_of!(Outer, ..=1.1), 0..7);
+        assert_eq!(span_of!(Outer, 2.header.1..), 10..16);
+        assert_eq!(span_of!(Outer, {2.len, 1.1}), 6..16);
+        assert_eq!(unsafe { span_of_unchecked!(Outer, 1.1..=2.len) }, 6..16);
+        assert_eq!(spans_of!(Outer, [2.header.0, 1.1]), [8..10, 6..7]);
+        assert_eq!(padding_between!(Outer, 1.1, 2.header.0), 1);
+    }
+
+    #[test]
+    fn span_generic_aliases() {
+        mod proto {
+            #[repr(C)]
+            pub struct Header {
+                pub version: u8,
+                pub len: u32,
+            }
+
+            #[repr(C)]
+            pub struct Message<T, P = ()> {
+                pub header: Header,
+                pub body: [T; 4],
+                pub policy: core::marker::PhantomData<P>,
+            }
+
+            pub type V2<T> = Message<T, u8>;
+        }
+
+        type V1<T> = proto::Message<T>;
+        type Lenient<T, P = ()> = proto::Message<T, P>;
+
+        assert_eq!(span_of!(V1<u16>, header..body), 0..8);
+        assert_eq!(span_of!(V1::<u16>, header..=body), 0..16);
+        assert_eq!(span_of!(V1<u16>, body[1..3]), 10..14);
+        assert_eq!(span_of!(proto::V2<u32>, body[..]), 8..24);
+        assert_eq!(span_of!(Lenient<u64, u16>, body[2..]), 24..40);
+        assert_eq!(unsafe { span_of_unchecked!(Lenient<u8>, body..) }, 8..12);
+
+        #[cfg(nested_offset_of)]
+        {
+            assert_eq!(span_of!(V1<u8>, header.len), 4..8);
+            assert_eq!(span_of!(proto::V2<u8>, header.version), 0..1);
+        }
+    }
+
+    #[test]
+    fn span_generic_arguments() {
+        mod proto {
+            #[repr(C)]
+            pub struct Pair<A, B>(pub A, pub B);
+        }
+
+        #[repr(C)]
+        struct Envelope<H, P> {
+            header: H,
+            body: [P; 2],
+            crc: u16,
+        }
+
+        #[repr(C)]
+        union Either<A: Copy, B: Copy> {
+            left: A,
+            right: B,
+        }
+
+        type Nested = Envelope<proto::Pair<u8, u16>, proto::Pair<u32, Envelope<u8, u8>>>;
+
+        assert_eq!(span_of!(Envelope<u32, u16>, body), 4..8);
+        assert_eq!(span_of!(Envelope<u32, u16>, header..crc), 0..8);
+        assert_eq!(span_of!(Envelope<u32, u16>, body..=crc,), 4..10);
+        assert_eq!(span_of!(Envelope<u32, u16>, ..body), 0..4);
+        assert_eq!(span_of!(Envelope<u32, u16>, body..), 4..12);
+        assert_eq!(span_of!(Envelope<u32, u16>, body[1..]), 6..8);
+        assert_eq!(span_of!(proto::Pair<u8, proto::Pair<u16, u64>>, 1), 8..24);
+        assert_eq!(span_of!(Envelope<proto::Pair<u8, u16>, u8>, ..=body), 0..6);
+        assert_eq!(span_of!(Nested, body[1..]), span_of!(Nested, body[1..2]));
+        assert_eq!(span_of!([Envelope<u8, u8>; 4], [1]..[3]), 6..18);
+        assert_eq!(
+            unsafe { span_of_unchecked!(Envelope<u64, u8>, body..crc) },
+            8..10
+        );
+        assert_eq!(span_of_as!(Envelope<u64, u8>, crc, u8), 10u8..12);
+        assert_eq!(span_of_pair!(Envelope<u64, u8>, header..=body), (0, 10));
+        assert_eq!(span_of_union!(Either<u8, [u16; 3]>, right), 0..6);
+    }
+
+    #[test]
+    fn span_as() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: [u8; 300],
+            c: u8,
+        }
+
+        assert_eq!(span_of_as!(Foo, a, u8), 0u8..4);
+        assert_eq!(span_of_as!(Foo, b..=c, u16), 4u16..305);
+        assert_eq!(span_of_as!(Foo, .., u32,), 0u32..308);
+        assert_eq!(span_of_as!(Foo, b.., usize), span_of!(Foo, b..));
+    }
+
+    #[test]
+    #[should_panic]
+    fn span_as_overflow() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: [u8; 300],
+        }
+
+        let _ = span_of_as!(Foo, b, u8);
+    }
+
+    // On 16-bit targets, spans of large packed structs run up against the width of `u16`.
+    #[test]
+    fn span_as_u16_boundary() {
+        #[repr(C, packed)]
+        struct Image {
+            header: u8,
+            data: [u8; 0xFFFE],
+            tail: u8,
+        }
+
+        assert_eq!(span_of_as!(Image, header..=data, u16), 0u16..0xFFFF);
+        assert_eq!(offset_of_as!(Image, tail, u16), 0xFFFF);
+    }
+
+    #[test]
+    #[should_panic]
+    fn span_as_u16_overflow() {
+        #[repr(C, packed)]
+        struct Image {
+            header: u8,
+            data: [u8; 0xFFFE],
+            tail: u8,
+        }
+
+        let _ = span_of_as!(Image, tail, u16);
+    }
+
+    #[cfg(not(inline_const))]
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn span_elements_overflow() {
+        #[repr(C)]
+        struct Frame {
+            samples: [u16; 4],
+        }
+
+        let _ = span_of!(Frame, samples[1..=(!0)]);
+    }
+
+    #[test]
+    fn span_covering() {
+        #[repr(C)]
+        struct Packet {
+            magic: u32,
+            flags: u16,
+            seq: u32,
+            payload: [u8; 8],
+            crc: u32,
+        }
+
+        assert_eq!(span_of!(Packet, {crc, seq, flags}), 4..24);
+        assert_eq!(span_of!(Packet, {payload[7], magic,}), 0..20);
+        assert_eq!(span_of!(Packet, {payload[5], payload[2]}), 14..18);
+        assert_eq!(span_of!(Packet, { seq }), span_of!(Packet, seq));
+        assert_eq!(unsafe { span_of_unchecked!(Packet, {crc, flags}) }, 4..24);
+
+        // The fields of a `repr(Rust)` struct may be laid out in any order.
+        struct Reordered {
+            a: u8,
+            b: u64,
+            c: u16,
+        }
+
+        let spans = [
+            span_of!(Reordered, a),
+            span_of!(Reordered, b),
+            span_of!(Reordered, c),
+        ];
+        let start = spans.iter().map(|span| span.start).min().unwrap();
+        let end = spans.iter().map(|span| span.end).max().unwrap();
+        assert_eq!(span_of!(Reordered, {c, b, a}), start..end);
+        assert_eq!(span_of!(Reordered, {a, b, c}), start..end);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn span_covering_nested() {
+        #[repr(C)]
+        struct Inner {
+            tag: u8,
+            len: u32,
+        }
+
+        #[repr(C)]
+        struct Outer {
+            id: u16,
+            inner: Inner,
+            tail: [u16; 2],
+        }
+
+        assert_eq!(span_of!(Outer, {tail[1], inner.len}), 8..16);
+        assert_eq!(span_of!(Outer, {inner.tag, id}), 0..5);
+    }
+
+    #[test]
+    fn span_empty_ranges() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: u16,
+            c: u64,
+        }
+
+        assert_eq!(span_of!(Foo, b..b), 4..4);
+        assert_eq!(span_of!(Foo, b..=b), 4..6);
+        assert_eq!(span_of!(Foo, c..c), 8..8);
+        assert_eq!(unsafe { span_of_unchecked!(Foo, a..a) }, 0..0);
+    }
+
+    #[cfg(all(not(inline_const), debug_assertions))]
+    #[test]
+    #[should_panic(expected = "span `c..a` of `Foo` ends before it starts")]
+    fn span_reversed() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: u16,
+            c: u64,
+        }
+
+        let _ = span_of!(Foo, c..a);
+    }
+
+    #[cfg(all(feature = "checked", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "span `c..a` of `Foo` ends before it starts")]
+    fn checked_span_reversed() {
+        _memoffset__check_span!(Foo, (c..a), 8, 0, 16);
+    }
+
+    #[cfg(all(feature = "checked", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "span `a..` is not inside its parent `Foo`")]
+    fn checked_span_outside_parent() {
+        _memoffset__check_span!(Foo, (a..), 0, 24, 16);
+    }
+
+    #[test]
+    fn span_unchecked() {
+        #[repr(C)]
+        struct Foo {
+            a: u32,
+            b: [u8; 2],
+            c: i64,
+        }
+
+        assert_eq!(unsafe { span_of_unchecked!(Foo, a) }, span_of!(Foo, a));
+        assert_eq!(
+            unsafe { span_of_unchecked!(Foo, a..c) },
+            span_of!(Foo, a..c)
         );
         assert_eq!(
-            span_of!(Test, x..=y),
-            offset_of!(Test, x)..offset_of!(Test, y) + mem::size_of::<[u8; 56]>()
+            unsafe { span_of_unchecked!(Foo, ..=b) },
+            span_of!(Foo, ..=b)
         );
+        assert_eq!(unsafe { span_of_unchecked!(Foo, b..) }, span_of!(Foo, b..));
+    }
+
+    #[test]
+    fn span_unchecked_union() {
+        #[repr(C)]
+        union Foo {
+            a: u32,
+            b: [u8; 2],
+        }
+
+        assert_eq!(unsafe { span_of_unchecked!(Foo, a) }, 0..4);
+        assert_eq!(unsafe { span_of_unchecked!(Foo, b) }, 0..2);
+    }
+
+    #[cfg(stable_offset_of)]
+    #[test]
+    fn qualified_parent() {
+        #[repr(C)]
+        struct Header<Ext> {
+            ext: Ext,
+            seq: u32,
+            len: u16,
+        }
+
+        trait Proto {
+            type Ext;
+            type Header;
+        }
+
+        struct Small;
+        impl Proto for Small {
+            type Ext = u8;
+            type Header = Header<u8>;
+        }
+
+        struct Large;
+        impl Proto for Large {
+            type Ext = [u64; 2];
+            type Header = Header<[u64; 2]>;
+        }
+
+        fn spans<T: Proto<Header = Header<<T as Proto>::Ext>>>(
+        ) -> (core::ops::Range<usize>, core::ops::Range<usize>) {
+            (
+                span_of!(<T as Proto>::Header, seq..=len),
+                span_of!(T::Header, ext),
+            )
+        }
+
+        fn seq_span<T: Proto<Header = Header<<T as Proto>::Ext>>>() -> core::ops::Range<usize> {
+            span_of!(<T>::Header, seq)
+        }
+
+        assert_eq!(spans::<Small>(), (4..10, 0..1));
+        assert_eq!(spans::<Large>(), (16..22, 0..16));
+        assert_eq!(seq_span::<Large>(), 16..20);
     }
 }
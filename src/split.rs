@@ -0,0 +1,359 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// Checks that the given fields of a struct are listed in layout order and do not overlap.
+/// This is a compile-time check if rustc supports inline `const` blocks, which unlike `const`
+/// items can refer to the generic parameters of the surrounding function.
+#[cfg(inline_const)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__assert_fields_ordered {
+    ($parent:path, $($field:tt),+) => {
+        const { assert!(
+            $crate::__priv::spans_ordered(&[$($crate::_memoffset__field_range!($parent, $field)),+]),
+            concat!(
+                "the fields of `", stringify!($parent),
+                "` must be listed in layout order and must not overlap"
+            )
+        ) };
+    };
+}
+#[cfg(not(inline_const))]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__assert_fields_ordered {
+    ($parent:path, $($field:tt),+) => {
+        _memoffset__assert!(
+            $crate::__priv::spans_ordered(&[$(_memoffset__field_range!($parent, $field)),+]),
+            _memoffset__concat!(
+                "the fields of `", _memoffset__stringify!($parent),
+                "` must be listed in layout order and must not overlap"
+            )
+        );
+    };
+}
+
+/// Splits a byte buffer holding a struct into disjoint mutable sub-slices, one per listed field.
+///
+/// The fields must be listed in layout order and must not overlap; on rustc 1.79 and newer
+/// this is checked at compile time.
+/// The buffer must be at least as long as the struct; this is checked once, up front.
+/// The result is an array with one slice per listed field.
+///
+/// ## Examples
+/// ```
+/// use memoffset::split_fields_mut;
+///
+/// #[repr(C)]
+/// struct Packet {
+///     hdr: [u8; 4],
+///     len: u16,
+///     payload: [u8; 10],
+/// }
+///
+/// let mut buf = [0u8; 16];
+/// let [hdr, payload] = split_fields_mut!(&mut buf, Packet, [hdr, payload]);
+/// hdr.copy_from_slice(b"PKT!");
+/// payload[0] = 0xFF;
+///
+/// assert_eq!(&buf[..4], b"PKT!");
+/// assert_eq!(buf[6], 0xFF);
+/// ```
+///
+/// Fields that overlap, like the members of a union, are rejected:
+///
+/// ```compile_fail
+/// use memoffset::split_fields_mut;
+///
+/// #[repr(C)]
+/// union Word {
+///     int: u32,
+///     bytes: [u8; 4],
+/// }
+///
+/// let mut buf = [0u8; 4];
+/// let [int, bytes] = split_fields_mut!(&mut buf, Word, [int, bytes]);
+/// ```
+///
+/// So are fields listed out of order, also in a generic function; there the check happens when
+/// the function is instantiated, or at runtime on rustc versions below 1.79:
+///
+#[cfg_attr(inline_const, doc = "```compile_fail")]
+#[cfg_attr(not(inline_const), doc = "```should_panic")]
+/// use memoffset::split_fields_mut;
+///
+/// #[repr(C)]
+/// struct Pair<T> {
+///     a: T,
+///     b: T,
+/// }
+///
+/// fn split<T>(buf: &mut [u8]) {
+///     let [b, a] = split_fields_mut!(buf, Pair<T>, [b, a]);
+/// }
+///
+/// split::<u32>(&mut [0; 8]);
+/// ```
+///
+/// ## Panics
+/// Panics if the buffer is shorter than the struct.
+/// On rustc versions below 1.79, also panics if the fields are not listed in layout order or
+/// overlap each other.
+#[macro_export(local_inner_macros)]
+macro_rules! split_fields_mut {
+    ($buf:expr, $parent:path, [$($field:tt),+ $(,)*] $(,)*) => {{
+        _memoffset__assert_fields_ordered!($parent, $($field),+);
+        let buf: &mut [u8] = $buf;
+        $crate::span::expect_span(0..$crate::__priv::mem::size_of::<$parent>(), buf.len());
+        let mut rest = buf;
+        let mut consumed = 0;
+        let split = [$({
+            let (start, end) = _memoffset__field_range!($parent, $field);
+            let taken = rest;
+            let (_, tail) = taken.split_at_mut(start - consumed);
+            let (field, tail) = tail.split_at_mut(end - start);
+            rest = tail;
+            consumed = end;
+            field
+        }),+];
+        // Nothing comes after the last field.
+        let _ = (rest, consumed);
+        split
+    }};
+}
+
+/// Computes the `(start, end)` byte range of a possibly nested field, without requiring a value
+/// of the parent type.
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__nested_field_range {
+    ($parent:path, $($field:tt).+) => {{
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        let field_ptr = raw_field!(base_ptr, $parent, $($field).+);
+        let offset = _memoffset_offset_from_unsafe!(field_ptr, base_ptr);
+        (offset, offset + $crate::__priv::size_of_pointee(field_ptr))
+    }};
+}
+
+/// Splits a byte buffer holding a struct into three slices: the bytes before the given field,
+/// the bytes of the field, and all bytes after it, up to the end of the buffer.
+///
+/// Returns `None` if the buffer is shorter than the struct.
+/// On rustc 1.82 and newer, the field may be a nested path, as with `raw_field!`.
+///
+/// ## Examples
+/// ```
+/// use memoffset::split_at_field;
+///
+/// #[repr(C)]
+/// struct Frame {
+///     header: [u8; 4],
+///     payload: [u8; 8],
+///     crc: u32,
+/// }
+///
+/// let buf = [0xAAu8; 20];
+/// let (header, payload, rest) = split_at_field!(&buf, Frame, payload).unwrap();
+/// assert_eq!((header.len(), payload.len(), rest.len()), (4, 8, 8));
+///
+/// assert!(split_at_field!(&buf[..15], Frame, payload).is_none());
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! split_at_field {
+    ($buf:expr, $parent:path, $($field:tt).+ $(,)*) => {{
+        let buf: &[u8] = $buf;
+        let (start, end) = _memoffset__nested_field_range!($parent, $($field).+);
+        match $crate::span::check_span(0..$crate::__priv::mem::size_of::<$parent>(), buf.len()) {
+            $crate::__priv::Result::Ok(()) => {
+                let (before, rest) = buf.split_at(start);
+                let (field, after) = rest.split_at(end - start);
+                $crate::__priv::Option::Some((before, field, after))
+            }
+            $crate::__priv::Result::Err(_) => $crate::__priv::Option::None,
+        }
+    }};
+}
+
+/// Mutable version of `split_at_field!`, splitting the buffer into three disjoint mutable slices.
+///
+/// ## Examples
+/// ```
+/// use memoffset::split_at_field_mut;
+///
+/// #[repr(C)]
+/// struct Frame {
+///     header: [u8; 4],
+///     payload: [u8; 8],
+///     crc: u32,
+/// }
+///
+/// let mut buf = [0u8; 16];
+/// let (header, payload, crc) = split_at_field_mut!(&mut buf, Frame, payload).unwrap();
+/// header.copy_from_slice(b"FRM!");
+/// payload[0] = 1;
+/// crc[3] = 2;
+/// assert_eq!(&buf[..5], b"FRM!\x01");
+/// assert_eq!(buf[15], 2);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! split_at_field_mut {
+    ($buf:expr, $parent:path, $($field:tt).+ $(,)*) => {{
+        let buf: &mut [u8] = $buf;
+        let (start, end) = _memoffset__nested_field_range!($parent, $($field).+);
+        match $crate::span::check_span(0..$crate::__priv::mem::size_of::<$parent>(), buf.len()) {
+            $crate::__priv::Result::Ok(()) => {
+                let (before, rest) = buf.split_at_mut(start);
+                let (field, after) = rest.split_at_mut(end - start);
+                $crate::__priv::Option::Some((before, field, after))
+            }
+            $crate::__priv::Result::Err(_) => $crate::__priv::Option::None,
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    #[repr(C)]
+    struct Packet {
+        magic: u32,
+        flags: u8,
+        len: u16,
+        payload: [u8; 8],
+        crc: u32,
+    }
+
+    #[test]
+    fn split_and_mutate() {
+        let mut buf = [0u8; 24];
+        {
+            let [magic, len, payload, crc] =
+                split_fields_mut!(&mut buf, Packet, [magic, len, payload, crc]);
+            assert_eq!(magic.len(), 4);
+            assert_eq!(len.len(), 2);
+            assert_eq!(payload.len(), 8);
+            assert_eq!(crc.len(), 4);
+
+            magic.copy_from_slice(&[1; 4]);
+            len.copy_from_slice(&[2; 2]);
+            payload.copy_from_slice(&[3; 8]);
+            crc.copy_from_slice(&[4; 4]);
+        }
+
+        assert_eq!(&buf[0..4], &[1; 4]);
+        // `flags` and the padding after it were not handed out.
+        assert_eq!(&buf[4..6], &[0; 2]);
+        assert_eq!(&buf[6..8], &[2; 2]);
+        assert_eq!(&buf[8..16], &[3; 8]);
+        assert_eq!(&buf[16..20], &[4; 4]);
+        assert_eq!(&buf[20..], &[0; 4]);
+    }
+
+    #[test]
+    fn split_at() {
+        let mut buf = [0u8; 26];
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        let (before, len, after) = split_at_field!(&buf, Packet, len).unwrap();
+        assert_eq!((before.len(), len.len(), after.len()), (6, 2, 18));
+        assert_eq!(len, &buf[6..8]);
+        let mut reassembled = [0u8; 26];
+        let mut pos = 0;
+        for piece in [before, len, after].iter() {
+            reassembled[pos..pos + piece.len()].copy_from_slice(piece);
+            pos += piece.len();
+        }
+        assert_eq!(reassembled, buf);
+
+        let (before, crc, after) = split_at_field!(&buf[..24], Packet, crc).unwrap();
+        assert_eq!((before.len(), crc.len(), after.len()), (16, 4, 4));
+
+        let (before, magic, after) = split_at_field_mut!(&mut buf, Packet, magic).unwrap();
+        assert!(before.is_empty());
+        magic.copy_from_slice(&[0xFF; 4]);
+        after[0] = 0xEE;
+        assert_eq!(&buf[..5], &[0xFF, 0xFF, 0xFF, 0xFF, 0xEE]);
+    }
+
+    #[test]
+    fn split_at_short_buffer() {
+        let mut buf = [0u8; 19];
+        assert!(split_at_field!(&buf, Packet, magic).is_none());
+        assert!(split_at_field_mut!(&mut buf, Packet, crc).is_none());
+        assert!(split_at_field!(&[], Packet, magic).is_none());
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn split_at_nested() {
+        #[repr(C)]
+        struct Outer {
+            tag: u32,
+            packet: Packet,
+        }
+
+        let mut buf = [0u8; 28];
+        let (before, payload, after) =
+            split_at_field_mut!(&mut buf, Outer, packet.payload).unwrap();
+        assert_eq!((before.len(), payload.len(), after.len()), (12, 8, 8));
+        payload.copy_from_slice(&[7; 8]);
+        assert_eq!(&buf[12..20], &[7; 8]);
+    }
+
+    #[test]
+    fn generic() {
+        #[repr(C)]
+        struct Pair<T> {
+            a: T,
+            b: T,
+        }
+
+        fn split<T>(buf: &mut [u8]) -> usize {
+            let [a, b] = split_fields_mut!(buf, Pair<T>, [a, b]);
+            a.len() + b.len()
+        }
+
+        assert_eq!(split::<u32>(&mut [0; 8]), 8);
+        assert_eq!(split::<u64>(&mut [0; 16]), 16);
+    }
+
+    #[test]
+    fn single_field() {
+        let mut buf = [0u8; 24];
+        let [payload] = split_fields_mut!(&mut buf[..], Packet, [payload,]);
+        assert_eq!(payload.len(), 8);
+    }
+
+    #[test]
+    fn longer_buffer() {
+        let mut buf = [0u8; 64];
+        let [crc] = split_fields_mut!(&mut buf, Packet, [crc]);
+        crc[3] = 1;
+        assert_eq!(buf[19], 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn short_buffer() {
+        let mut buf = [0u8; 19];
+        let _ = split_fields_mut!(&mut buf, Packet, [magic, crc]);
+    }
+}
@@ -0,0 +1,266 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// Checks that a field is at most 8 bytes long.
+/// This is a compile-time check if rustc supports inline `const` blocks.
+#[cfg(inline_const)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _memoffset__assert_uint_field {
+    ($parent:path, $($field:tt).+) => {
+        const {
+            assert!(
+                $crate::_memoffset__field_size!($parent, $($field).+) <= 8,
+                concat!("`", stringify!($($field).+), "` is longer than 8 bytes")
+            )
+        };
+    };
+}
+#[cfg(not(inline_const))]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__assert_uint_field {
+    ($parent:path, $($field:tt).+) => {
+        _memoffset__assert!(
+            _memoffset__field_size!($parent, $($field).+) <= 8,
+            _memoffset__concat!(
+                "`",
+                _memoffset__stringify!($($field).+),
+                "` is longer than 8 bytes"
+            )
+        );
+    };
+}
+
+/// Computes the `(start, end)` byte range of a field that holds an unsigned integer of up to
+/// 8 bytes, as an array of bytes.
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__uint_field_range {
+    ($parent:path, $($field:tt).+) => {{
+        _memoffset__assert_uint_field!($parent, $($field).+);
+        // Only byte arrays coerce to a byte slice.
+        _memoffset__let_base_ptr!(base_ptr, $parent);
+        let _: *const [u8] = raw_field!(base_ptr, $parent, $($field).+);
+        _memoffset__field_range!($parent, $($field).+)
+    }};
+}
+
+/// Reads a big-endian unsigned integer from a byte-array field of a struct serialized in a byte
+/// buffer.
+///
+/// The field must be an array of at most 8 bytes, such as a `[u8; 3]` holding a 24-bit integer.
+/// On rustc 1.79 and newer, longer arrays are rejected at compile time.
+/// The field may be a nested path, such as `hdr.len`, which requires rustc 1.82 or newer.
+///
+/// ## Examples
+/// ```
+/// use memoffset::read_uint_field_be;
+///
+/// #[repr(C, packed)]
+/// struct Header {
+///     kind: u8,
+///     len: [u8; 3],
+/// }
+///
+/// let buf = [7, 0x01, 0x02, 0x03];
+/// assert_eq!(read_uint_field_be!(&buf, Header, len), 0x010203);
+/// ```
+///
+/// Fields that are not byte arrays, or are longer than 8 bytes, are rejected:
+///
+/// ```compile_fail
+/// use memoffset::read_uint_field_be;
+///
+/// #[repr(C)]
+/// struct Wide {
+///     id: [u8; 9],
+/// }
+///
+/// read_uint_field_be!(&[0; 9], Wide, id);
+/// ```
+///
+/// ## Panics
+/// Panics if the buffer is too short to hold the field.
+/// On rustc versions below 1.79, also panics if the field is longer than 8 bytes.
+#[macro_export(local_inner_macros)]
+macro_rules! read_uint_field_be {
+    ($buf:expr, $parent:path, $($field:tt).+ $(,)*) => {{
+        let buf: &[u8] = $buf;
+        let (start, end) = _memoffset__uint_field_range!($parent, $($field).+);
+        let range = $crate::span::expect_span(start..end, buf.len());
+        $crate::__priv::read_uint_be(&buf[range])
+    }};
+}
+
+/// Reads a little-endian unsigned integer from a byte-array field of a struct serialized in a
+/// byte buffer.
+///
+/// This is the little-endian counterpart of `read_uint_field_be!`.
+#[macro_export(local_inner_macros)]
+macro_rules! read_uint_field_le {
+    ($buf:expr, $parent:path, $($field:tt).+ $(,)*) => {{
+        let buf: &[u8] = $buf;
+        let (start, end) = _memoffset__uint_field_range!($parent, $($field).+);
+        let range = $crate::span::expect_span(start..end, buf.len());
+        $crate::__priv::read_uint_le(&buf[range])
+    }};
+}
+
+/// Writes a big-endian unsigned integer into a byte-array field of a struct serialized in a
+/// byte buffer.
+///
+/// The field must be an array of at most 8 bytes, as for `read_uint_field_be!`.
+///
+/// ## Examples
+/// ```
+/// use memoffset::write_uint_field_be;
+///
+/// #[repr(C, packed)]
+/// struct Header {
+///     kind: u8,
+///     len: [u8; 3],
+/// }
+///
+/// let mut buf = [7, 0, 0, 0];
+/// write_uint_field_be!(&mut buf, Header, len, 0x010203);
+/// assert_eq!(buf, [7, 0x01, 0x02, 0x03]);
+/// ```
+///
+/// ## Panics
+/// Panics if the buffer is too short to hold the field, or if the value does not fit in it.
+/// On rustc versions below 1.79, also panics if the field is longer than 8 bytes.
+#[macro_export(local_inner_macros)]
+macro_rules! write_uint_field_be {
+    ($buf:expr, $parent:path, $($field:tt).+, $value:expr $(,)*) => {{
+        let value: u64 = $value;
+        let buf: &mut [u8] = $buf;
+        let (start, end) = _memoffset__uint_field_range!($parent, $($field).+);
+        let range = $crate::span::expect_span(start..end, buf.len());
+        $crate::__priv::write_uint_be(&mut buf[range], value)
+    }};
+}
+
+/// Writes a little-endian unsigned integer into a byte-array field of a struct serialized in a
+/// byte buffer.
+///
+/// This is the little-endian counterpart of `write_uint_field_be!`.
+#[macro_export(local_inner_macros)]
+macro_rules! write_uint_field_le {
+    ($buf:expr, $parent:path, $($field:tt).+, $value:expr $(,)*) => {{
+        let value: u64 = $value;
+        let buf: &mut [u8] = $buf;
+        let (start, end) = _memoffset__uint_field_range!($parent, $($field).+);
+        let range = $crate::span::expect_span(start..end, buf.len());
+        $crate::__priv::write_uint_le(&mut buf[range], value)
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    #[repr(C, packed)]
+    struct Frame {
+        kind: u8,
+        len: [u8; 3],
+        mac: [u8; 6],
+        seq: [u8; 8],
+        tiny: [u8; 1],
+    }
+
+    #[test]
+    fn read() {
+        let buf = [
+            0xFF, // kind
+            0x12, 0x34, 0x56, // len
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, // mac
+            0xFF, 0xEE, 0xDD, 0xCC, 0xBB, 0xAA, 0x99, 0x88, // seq
+            0x42, // tiny
+        ];
+
+        assert_eq!(read_uint_field_be!(&buf, Frame, len), 0x12_3456);
+        assert_eq!(read_uint_field_le!(&buf, Frame, len), 0x56_3412);
+        assert_eq!(read_uint_field_be!(&buf, Frame, mac), 0x0102_0304_0506);
+        assert_eq!(read_uint_field_le!(&buf, Frame, mac), 0x0605_0403_0201);
+        assert_eq!(read_uint_field_be!(&buf, Frame, seq), 0xFFEE_DDCC_BBAA_9988);
+        assert_eq!(read_uint_field_le!(&buf, Frame, seq), 0x8899_AABB_CCDD_EEFF);
+        assert_eq!(read_uint_field_be!(&buf, Frame, tiny), 0x42);
+        assert_eq!(read_uint_field_le!(&buf[..], Frame, tiny), 0x42);
+    }
+
+    #[test]
+    fn write() {
+        let mut buf = [0u8; 19];
+
+        write_uint_field_be!(&mut buf, Frame, len, 0x12_3456);
+        write_uint_field_le!(&mut buf, Frame, mac, 0x0605_0403_0201);
+        write_uint_field_be!(&mut buf, Frame, seq, 0xFFEE_DDCC_BBAA_9988);
+        write_uint_field_le!(&mut buf, Frame, tiny, 0x42);
+
+        assert_eq!(
+            buf,
+            [
+                0x00, // kind
+                0x12, 0x34, 0x56, // len
+                0x01, 0x02, 0x03, 0x04, 0x05, 0x06, // mac
+                0xFF, 0xEE, 0xDD, 0xCC, 0xBB, 0xAA, 0x99, 0x88, // seq
+                0x42, // tiny
+            ]
+        );
+        assert_eq!(read_uint_field_le!(&buf, Frame, mac), 0x0605_0403_0201);
+    }
+
+    #[cfg(nested_offset_of)]
+    #[test]
+    fn nested() {
+        #[repr(C, packed)]
+        struct Packet {
+            id: u8,
+            hdr: Frame,
+            tail: ([u8; 2], [u8; 5]),
+        }
+
+        let mut buf = [0u8; 27];
+        write_uint_field_be!(&mut buf, Packet, hdr.len, 0x12_3456);
+        write_uint_field_le!(&mut buf, Packet, hdr.tiny, 0x42);
+        write_uint_field_be!(&mut buf, Packet, tail.1, 0x01_0203_0405,);
+
+        assert_eq!(buf[2..5], [0x12, 0x34, 0x56]);
+        assert_eq!(buf[19], 0x42);
+        assert_eq!(buf[22..], [0x01, 0x02, 0x03, 0x04, 0x05]);
+        assert_eq!(read_uint_field_le!(&buf, Packet, hdr.len), 0x56_3412);
+        assert_eq!(read_uint_field_be!(&buf, Packet, hdr.tiny), 0x42);
+        assert_eq!(read_uint_field_le!(&buf, Packet, tail.1), 0x05_0403_0201);
+        assert_eq!(read_uint_field_be!(&buf, Packet, tail.0), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn write_too_large() {
+        let mut buf = [0u8; 19];
+        write_uint_field_be!(&mut buf, Frame, len, 0x0100_0000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn short_buffer() {
+        let buf = [0u8; 9];
+        read_uint_field_be!(&buf, Frame, mac);
+    }
+}
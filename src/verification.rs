@@ -0,0 +1,141 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// Generates a module of [Kani](https://github.com/model-checking/kani) proof harnesses
+/// checking the projections of this crate on the given fields of a struct.
+///
+/// The generated module is only compiled under `cfg(kani)`, so normal builds ignore it.
+/// Its harness places the struct at a nondeterministic index of an uninitialized array, and
+/// proves for every listed field that:
+///  * `raw_field!` stays within the bounds of the struct,
+///  * `container_of!` maps the field pointer back to the pointer to the struct,
+///  * `span_of!` lies within `0..size_of::<T>()` and starts where `raw_field!` points.
+///
+/// The struct must be nameable from a child module, as the harnesses `use super::*`.
+/// On rustc 1.80 or newer, the crate using the macro should declare the `kani` cfg, e.g. with
+/// `unexpected_cfgs = { level = "warn", check-cfg = ['cfg(kani)'] }` under `[lints.rust]` in
+/// `Cargo.toml`, as Kani itself recommends.
+///
+/// ## Examples
+/// ```
+/// #[macro_use]
+/// extern crate memoffset;
+///
+/// #[repr(C)]
+/// pub struct Descriptor {
+///     addr: u64,
+///     len: u32,
+///     flags: u16,
+///     next: u16,
+/// }
+///
+/// verification_harnesses!(mod descriptor_proofs for Descriptor { addr, len, flags, next });
+/// # fn main() {}
+/// ```
+///
+/// Requires the `verification` cargo feature.
+#[cfg(all(feature = "verification", maybe_uninit))]
+#[macro_export(local_inner_macros)]
+macro_rules! verification_harnesses {
+    (mod $name:ident for $parent:path { $($field:tt),+ $(,)* } $(,)*) => {
+        _memoffset__verification_harnesses!(#[cfg(kani)] mod $name for $parent { $($field),+ });
+    };
+}
+
+/// Generates the module of `verification_harnesses!` under the given `cfg`, so that it can be
+/// compiled against a stand-in for `kani` in tests.
+#[cfg(all(feature = "verification", maybe_uninit))]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! _memoffset__verification_harnesses {
+    (#[$cfg:meta] mod $name:ident for $parent:path { $($field:tt),+ }) => {
+        #[$cfg]
+        mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #[kani::proof]
+            fn projections() {
+                const COUNT: usize = 4;
+                let array = $crate::__priv::mem::MaybeUninit::<[$parent; COUNT]>::uninit();
+                let index: usize = kani::any();
+                kani::assume(index < COUNT);
+                let base = (array.as_ptr() as *const $parent).wrapping_offset(index as isize);
+                let size = $crate::__priv::mem::size_of::<$parent>();
+                $(
+                    let field = raw_field!(base, $parent, $field);
+                    let start = field as usize - base as usize;
+                    _memoffset__assert!(
+                        start + $crate::__priv::size_of_pointee(field) <= size,
+                        _memoffset__concat!("field `", _memoffset__stringify!($field), "` is out of bounds")
+                    );
+                    _memoffset__assert!(
+                        container_of!(field, $parent, $field) == base,
+                        _memoffset__concat!("`container_of!` does not invert `raw_field!` for field `", _memoffset__stringify!($field), "`")
+                    );
+                    let span = span_of!($parent, $field);
+                    _memoffset__assert!(
+                        span.start == start && span.end <= size,
+                        _memoffset__concat!("span of field `", _memoffset__stringify!($field), "` is out of bounds")
+                    );
+                )+
+            }
+        }
+    };
+}
+
+#[cfg(all(test, feature = "verification", maybe_uninit))]
+mod tests {
+    /// A stand-in for the parts of `kani` the harnesses use, which runs them as regular tests.
+    mod kani {
+        pub use core::prelude::v1::test as proof;
+
+        pub fn any<T: Default>() -> T {
+            T::default()
+        }
+
+        pub fn assume(cond: bool) {
+            assert!(cond);
+        }
+    }
+
+    #[repr(C)]
+    pub struct Descriptor {
+        addr: u64,
+        len: u32,
+        flags: u16,
+        next: u16,
+    }
+
+    #[repr(C, packed)]
+    pub struct Packed {
+        tag: u8,
+        value: u32,
+    }
+
+    #[repr(C)]
+    pub struct Tup(u8, Descriptor);
+
+    verification_harnesses!(mod descriptor_proofs for Descriptor { addr, len, flags, next });
+
+    _memoffset__verification_harnesses!(#[cfg(test)] mod descriptor for Descriptor { addr, len, flags, next });
+    _memoffset__verification_harnesses!(#[cfg(test)] mod packed for Packed { tag, value });
+    _memoffset__verification_harnesses!(#[cfg(test)] mod tup for Tup { 0, 1 });
+}
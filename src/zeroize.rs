@@ -0,0 +1,166 @@
+// Copyright (c) 2017 Gilad Naaman
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// Overwrites the bytes of the specified fields of a struct with zeros, in place.
+///
+/// The zeros are written with volatile stores, so they are not optimized out even if the
+/// value is never read again.
+///
+/// ## Examples
+/// ```
+/// use memoffset::zeroize_fields;
+///
+/// #[repr(C)]
+/// struct Session {
+///     id: u32,
+///     key: [u8; 16],
+///     nonce: u64,
+/// }
+///
+/// let mut session = Session { id: 7, key: [0xAA; 16], nonce: 42 };
+/// unsafe { zeroize_fields!(&mut session, Session, [key, nonce]) };
+///
+/// assert_eq!(session.id, 7);
+/// assert_eq!(session.key, [0; 16]);
+/// assert_eq!(session.nonce, 0);
+/// ```
+///
+/// ## Safety
+/// The all-zeros bit pattern must be a valid value for every listed field.
+/// The previous values of the fields are overwritten without being dropped, so any resources
+/// they own are leaked.
+///
+/// Requires the `zeroize` cargo feature.
+#[cfg(feature = "zeroize")]
+#[macro_export(local_inner_macros)]
+macro_rules! zeroize_fields {
+    ($value:expr, $parent:path, [$($field:tt),+ $(,)*] $(,)*) => {{
+        let value: &mut $parent = $value;
+        let base = value as *mut $parent as *const $parent;
+        $(
+            let field = raw_field!(base, $parent, $field);
+            $crate::__priv::volatile_zero(field as *mut u8, $crate::__priv::size_of_pointee(field));
+        )+
+    }};
+}
+
+/// Overwrites all bytes in the given span of a struct with zeros, in place.
+///
+/// This is the padding-inclusive counterpart of `zeroize_fields!`: it accepts the same range
+/// expressions as `span_of!`, and also scrubs any padding and any other field inside the range.
+///
+/// ## Examples
+/// ```
+/// use memoffset::zeroize_span;
+///
+/// #[repr(C)]
+/// struct Session {
+///     id: u32,
+///     key: [u8; 16],
+///     nonce: u64,
+/// }
+///
+/// let mut session = Session { id: 7, key: [0xAA; 16], nonce: 42 };
+/// unsafe { zeroize_span!(&mut session, Session, key ..= nonce) };
+///
+/// assert_eq!(session.id, 7);
+/// assert_eq!(session.key, [0; 16]);
+/// assert_eq!(session.nonce, 0);
+/// ```
+///
+/// ## Safety
+/// The all-zeros bit pattern must be a valid value for every field inside the span.
+/// The previous values of those fields are overwritten without being dropped, so any resources
+/// they own are leaked.
+///
+/// Requires the `zeroize` cargo feature.
+#[cfg(feature = "zeroize")]
+#[macro_export(local_inner_macros)]
+macro_rules! zeroize_span {
+    ($value:expr, $parent:path, $($exp:tt)+) => {{
+        let value: &mut $parent = $value;
+        let span = span_of!($parent, $($exp)+);
+        let base = value as *mut $parent as *mut u8;
+        $crate::__priv::volatile_zero(base.offset(span.start as isize), span.end - span.start);
+    }};
+}
+
+#[cfg(all(test, feature = "zeroize"))]
+mod tests {
+    #![cfg_attr(allow_clippy, allow(clippy::ptr_offset_with_cast))]
+
+    #[repr(C)]
+    struct Secret {
+        id: u32,
+        key: [u8; 7],
+        nonce: u64,
+        public: u16,
+    }
+
+    fn secret() -> Secret {
+        Secret {
+            id: 0xDEAD_BEEF,
+            key: [0xAA; 7],
+            nonce: !0,
+            public: 0x1234,
+        }
+    }
+
+    #[test]
+    fn zeroize_selected_fields() {
+        let mut s = secret();
+        unsafe { zeroize_fields!(&mut s, Secret, [key, nonce]) };
+
+        assert_eq!(s.id, 0xDEAD_BEEF);
+        assert_eq!(s.key, [0; 7]);
+        assert_eq!(s.nonce, 0);
+        assert_eq!(s.public, 0x1234);
+    }
+
+    #[test]
+    fn zeroize_single_field_packed() {
+        #[repr(C, packed)]
+        struct Packed {
+            a: u8,
+            b: u32,
+            c: u8,
+        }
+
+        let mut p = Packed { a: 1, b: !0, c: 3 };
+        unsafe { zeroize_fields!(&mut p, Packed, [b]) };
+
+        assert_eq!({ p.a }, 1);
+        assert_eq!({ p.b }, 0);
+        assert_eq!({ p.c }, 3);
+    }
+
+    #[test]
+    fn zeroize_span_includes_padding() {
+        let mut s = secret();
+        unsafe { zeroize_span!(&mut s, Secret, key..=nonce) };
+
+        let bytes = &s as *const Secret as *const u8;
+        for i in span_of!(Secret, key..=nonce) {
+            assert_eq!(unsafe { *bytes.offset(i as isize) }, 0);
+        }
+        assert_eq!(s.id, 0xDEAD_BEEF);
+        assert_eq!(s.public, 0x1234);
+    }
+}
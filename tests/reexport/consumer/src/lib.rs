@@ -0,0 +1,32 @@
+//! Uses the macros of `memoffset` only through other crates' re-exports.
+
+/// Through a plain facade crate.
+#[cfg(test)]
+mod facade {
+    use memoffset_facade as m;
+
+    include!("../../exercise.rs");
+}
+
+/// Through a facade crate that itself renames its `memoffset` dependency.
+#[cfg(test)]
+mod renamed_facade {
+    use memoffset_renamed as m;
+
+    include!("../../exercise.rs");
+}
+
+/// Without the prelude, through a re-export of a re-export nested a few modules deep.
+#[cfg(test)]
+#[no_implicit_prelude]
+mod no_prelude {
+    mod outer {
+        pub mod inner {
+            pub use ::memoffset_renamed::*;
+        }
+    }
+
+    use self::outer::inner as m;
+
+    include!("../../no_prelude.rs");
+}
@@ -0,0 +1,514 @@
+// Exercises every public macro through a path `m`, which the including module binds to a
+// re-export or a renamed dependency of `memoffset`. Nothing here may name `memoffset` directly.
+
+use std::mem::MaybeUninit;
+
+#[repr(C)]
+pub struct Foo {
+    a: u32,
+    b: [u8; 4],
+    c: u64,
+}
+
+#[repr(C, align(64))]
+pub struct Lines {
+    head: u32,
+    tail: u32,
+}
+
+#[repr(C)]
+pub union Bits {
+    int: u32,
+    bytes: [u8; 4],
+}
+
+m::assert_prefix_layout!(Lines, Lines, { head, tail });
+m::assert_same_cacheline!(Lines, head, tail);
+m::assert_different_cachelines!(Foo, a, c, 8);
+m::assert_field_aligned_to!(Foo, c, 8);
+m::assert_offset!(Foo, b, 4);
+m::assert_offsets!(Foo { a: 0, c: 8 });
+m::target_layouts!(Foo {
+    all() => { size: 16, c: 8 },
+});
+
+m::roundtrip_tests!(mod foo_roundtrip for Foo { a, b, c });
+m::verification_harnesses!(mod foo_proofs for Foo { a, b, c });
+
+#[test]
+fn field_exists() {
+    m::assert_field_exists!(Foo, c);
+    m::assert_field_exists!(union Bits, int);
+    m::assert_field_exists!((u8, u32), 1);
+}
+
+#[test]
+fn offsets() {
+    assert_eq!(m::offset_of!(Foo, c), 8);
+    assert_eq!(m::offset_of!(Foo, b[2]), 6);
+    let i = 3;
+    assert_eq!(m::element_offset!(Foo, b, i), 7);
+    let foo = Foo {
+        a: 0,
+        b: [1, 2, 3, 4],
+        c: 0,
+    };
+    assert_eq!(
+        unsafe { *m::element_ptr!(&foo as *const Foo, Foo, b, i) },
+        4
+    );
+    assert_eq!((foo.a, foo.c), (0, 0));
+    assert_eq!(unsafe { m::offset_of_unchecked!(Foo, b) }, 4);
+    assert_eq!(m::offset_of_tuple!((u8, u32), 0), 0);
+    assert_eq!(m::offset_of_union!(Bits, bytes), 0);
+    assert_eq!(m::span_of_union!(Bits, bytes[1..]), 1..4);
+    assert_eq!(m::nth_field_offset!(Foo, c, 2), Some(40));
+    assert_eq!(m::nth_field_span!(Foo, b, 1), Some(20..24));
+}
+
+#[test]
+fn nested() {
+    #[repr(C)]
+    struct Outer {
+        tag: u64,
+        foo: Foo,
+    }
+
+    assert_eq!(m::offset_of!(Outer, foo.c), 16);
+    assert_eq!(m::span_of!(Outer, foo.b), 12..16);
+}
+
+mod wire {
+    #[repr(C)]
+    pub struct Header(pub u16, pub u16);
+}
+
+// Field names as emitted by bindgen for fields named after keywords.
+#[repr(C)]
+pub struct Request {
+    r#type: u32,
+    r#fn: u16,
+    r#loop: [u8; 4],
+    hdr: RequestHeader,
+}
+
+#[repr(C)]
+pub struct RequestHeader {
+    r#type: u8,
+    r#match: u32,
+}
+
+#[test]
+fn raw_identifiers() {
+    m::assert_field_exists!(Request, r#type);
+    assert_eq!(m::offset_of!(Request, r#type), 0);
+    assert_eq!(m::offset_of!(Request, r#fn), 4);
+    assert_eq!(m::offset_of!(Request, r#loop[2]), 8);
+    assert_eq!(m::offset_of!(Request, hdr.r#match), 16);
+    assert_eq!(m::span_of!(Request, r#type..r#loop), 0..6);
+    assert_eq!(m::span_of!(Request, r#fn..=r#loop), 4..10);
+    assert_eq!(m::span_of!(Request, ..r#fn), 0..4);
+    assert_eq!(m::span_of!(Request, r#loop[1..]), 7..10);
+    assert_eq!(m::span_of!(Request, hdr.r#type), 12..13);
+
+    let request = MaybeUninit::<Request>::uninit();
+    let base = request.as_ptr();
+    let fn_ptr = m::raw_field!(base, Request, r#fn);
+    let match_ptr = m::raw_field!(base, Request, hdr.r#match);
+    assert_eq!(fn_ptr as usize - base as usize, 4);
+    assert_eq!(m::container_of!(fn_ptr, Request, r#fn), base);
+    assert_eq!(m::container_of!(match_ptr, Request, hdr.r#match), base);
+}
+
+#[test]
+fn paths() {
+    assert_eq!(m::offset_of!(self::Foo, c), 8);
+    assert_eq!(m::offset_of!(self::wire::Header, 1), 2);
+    assert_eq!(m::span_of!(self::wire::Header, 0..=1), 0..4);
+}
+
+#[test]
+fn lifetimes() {
+    #[repr(C)]
+    struct View<'a> {
+        len: usize,
+        ptr: &'a u8,
+    }
+
+    let byte = 0u8;
+    let view = View { len: 1, ptr: &byte };
+    let base = &view as *const View<'_>;
+    assert_eq!(m::offset_of!(View<'_>, len), 0);
+    assert_eq!(
+        m::span_of!(View<'_>, ptr).start,
+        std::mem::size_of::<usize>()
+    );
+    assert_eq!(
+        m::raw_field!(base, View<'_>, ptr) as usize,
+        &view.ptr as *const &u8 as usize
+    );
+}
+
+#[test]
+fn search() {
+    let foos = [
+        Foo {
+            a: 1,
+            b: [0; 4],
+            c: 0,
+        },
+        Foo {
+            a: 4,
+            b: [0; 4],
+            c: 0,
+        },
+    ];
+    assert_eq!(m::search_slice_by_field!(&foos, Foo, a, &4), Ok(1));
+    assert!(m::is_sorted_by_field!(&foos, Foo, a));
+    let mut foos = foos;
+    foos[0].a = 5;
+    assert!(!m::is_sorted_by_field!(&foos, Foo, a));
+    m::sort_by_field!(&mut foos, Foo, a);
+    assert_eq!(foos[0].a, 4);
+    let bytes = [0u8; 32];
+    assert_eq!(unsafe { m::search_by_field!(&bytes, Foo, c, &1) }, Err(2));
+}
+
+#[test]
+fn uint() {
+    let mut buf = [0u8; 16];
+    m::write_uint_field_be!(&mut buf, Foo, b, 0x0102_0304);
+    m::write_uint_field_le!(&mut buf, Foo, b, m::read_uint_field_le!(&buf, Foo, b));
+    assert_eq!(m::read_uint_field_be!(&buf, Foo, b), 0x0102_0304);
+}
+
+#[test]
+fn spans() {
+    assert_eq!(m::span_of!(Foo, b..=c), 4..16);
+    assert_eq!(unsafe { m::span_of_unchecked!(Foo, a..c) }, 0..8);
+    assert_eq!(m::span_of!(Foo, b[1..3]), 5..7);
+}
+
+#[test]
+fn split() {
+    let mut buf = [0u8; 16];
+    let [a, c] = m::split_fields_mut!(&mut buf, Foo, [a, c]);
+    assert_eq!((a.len(), c.len()), (4, 8));
+    let (a, b, rest) = m::split_at_field_mut!(&mut buf, Foo, b).unwrap();
+    assert_eq!((a.len(), b.len(), rest.len()), (4, 4, 8));
+    assert!(m::split_at_field!(&buf[..15], Foo, b).is_none());
+}
+
+#[test]
+fn raw_fields() {
+    let foo = Foo {
+        a: 1,
+        b: [2; 4],
+        c: 3,
+    };
+    let base = &foo as *const Foo;
+    assert_eq!(m::raw_field!(base, Foo, c), &foo.c as *const u64);
+    assert_eq!(unsafe { m::raw_field_unchecked!(base, Foo, a) }, &foo.a as *const u32);
+    assert_eq!(m::container_of!(&foo.c as *const u64, Foo, c), base);
+
+    let tup = (1u8, 2u32);
+    let tup_base = &tup as *const (u8, u32);
+    assert_eq!(
+        m::raw_field_tuple!(tup_base, (u8, u32), 1),
+        &tup.1 as *const u32
+    );
+
+    let bits = Bits { int: 0 };
+    let bits_base = &bits as *const Bits;
+    assert_eq!(
+        m::raw_field_union!(bits_base, Bits, int) as usize,
+        bits_base as usize
+    );
+
+    let uninit = std::mem::MaybeUninit::<Foo>::uninit();
+    assert_eq!(
+        m::raw_field!(&uninit, MaybeUninit<Foo>, c) as usize - uninit.as_ptr() as usize,
+        8
+    );
+
+    #[repr(C)]
+    struct Entry {
+        key: u64,
+        foo: std::mem::ManuallyDrop<Foo>,
+    }
+    let entry = Entry {
+        key: 1,
+        foo: std::mem::ManuallyDrop::new(Foo {
+            a: 0,
+            b: [0; 4],
+            c: 2,
+        }),
+    };
+    let entry_base = &entry as *const Entry;
+    assert_eq!(
+        unsafe { *m::raw_field!(entry_base, Entry, foo as Foo, c) },
+        2
+    );
+    assert_eq!(m::offset_of!(Entry, foo as self::Foo, c), 16);
+    assert_eq!(entry.key, 1);
+
+    #[repr(C)]
+    struct Slot {
+        seq: u64,
+        value: std::cell::UnsafeCell<u32>,
+    }
+    let slot = Slot {
+        seq: 0,
+        value: std::cell::UnsafeCell::new(1),
+    };
+    let value = m::raw_cell_field!(&slot as *const Slot, Slot, value);
+    unsafe { value.write(2) };
+    assert_eq!((slot.seq, slot.value.into_inner()), (0, 2));
+}
+
+#[test]
+fn tails() {
+    #[repr(C)]
+    struct Packet {
+        len: u32,
+        data: [u8],
+    }
+
+    let buf = [0u32; 4];
+    let packet = std::ptr::slice_from_raw_parts(buf.as_ptr() as *const u8, 3) as *const Packet;
+    assert_eq!(m::offset_of_tail!(packet, Packet, data), 4);
+    assert_eq!(
+        unsafe { (&*m::raw_field_tail!(packet, Packet, data)).len() },
+        3
+    );
+}
+
+#[test]
+fn typed_offsets() {
+    assert_eq!(m::offset_of_as!(Foo, c, u32), 8u32);
+    assert_eq!(m::span_of_as!(Foo, a..=c, u16), 0u16..16);
+}
+
+#[test]
+fn placement() {
+    let region = [0u64; 4];
+    let base = region.as_ptr() as *const u8;
+    let c = m::placed_field!(base, 32, 8, Foo, c).unwrap();
+    assert_eq!(c as usize, base as usize + 16);
+}
+
+#[test]
+fn init() {
+    let foo = Foo {
+        a: 1,
+        b: [2; 4],
+        c: 3,
+    };
+    let mut uninit = MaybeUninit::<Foo>::uninit();
+    unsafe { m::partial_init_from!(&mut uninit, &foo, Foo, [a, b, c]) };
+    assert_eq!(m::uninit_field_bytes!(&uninit, Foo, b).len(), 4);
+    assert_eq!(m::uninit_field_bytes_mut!(&mut uninit, Foo, c).len(), 8);
+    let mut copy = unsafe { uninit.assume_init() };
+    assert_eq!(copy.c, 3);
+
+    let other = Foo {
+        a: 4,
+        b: [5; 4],
+        c: 6,
+    };
+    m::copy_field!(&mut copy, &other, Foo, c);
+    m::clone_field!(&mut copy, &other, Foo, b);
+    assert_eq!((copy.a, copy.b, copy.c), (1, [5; 4], 6));
+}
+
+#[test]
+fn init_slice() {
+    let mut foos: [MaybeUninit<Foo>; 3] = unsafe { MaybeUninit::uninit().assume_init() };
+    m::init_field_slice!(&mut foos, Foo, a, |i| i as u32);
+    m::fill_field_slice!(&mut foos, Foo, b, [1; 4]);
+    m::fill_field_slice!(&mut foos, Foo, c, 2);
+    assert_eq!(unsafe { &*foos[2].as_ptr() }.a, 2);
+}
+
+#[test]
+fn builder() {
+    m::uninit_builder! {
+        struct FooBuilder for Foo {
+            a: u32 => set_a,
+            b: [u8; 4] => set_b,
+            c: u64 => set_c,
+        }
+    }
+
+    let foo = Foo::uninit_builder().set_c(3).set_a(1).set_b([2; 4]).finish().unwrap();
+    assert_eq!((foo.a, foo.b, foo.c), (1, [2; 4], 3));
+    assert!(Foo::uninit_builder().set_a(1).finish().is_none());
+}
+
+#[test]
+fn self_ref() {
+    struct Node {
+        value: u32,
+        this: *const u32,
+    }
+
+    let node = m::init_self_ref!(Node { value: 1 }, this => value);
+    assert_eq!(node.this, &node.value as *const u32);
+}
+
+#[test]
+fn zeroize() {
+    let mut foo = Foo {
+        a: 1,
+        b: [2; 4],
+        c: 3,
+    };
+    unsafe { m::zeroize_fields!(&mut foo, Foo, [a]) };
+    unsafe { m::zeroize_span!(&mut foo, Foo, b..=c) };
+    assert_eq!((foo.a, foo.b, foo.c), (0, [0; 4], 0));
+}
+
+#[test]
+fn io() {
+    let foo = Foo {
+        a: 1,
+        b: [2; 4],
+        c: 3,
+    };
+    let slices = unsafe { m::io_slices!(&foo, Foo, [a, b[1..], c]) };
+    assert_eq!(slices[1].len(), 3);
+
+    let mut uninit = MaybeUninit::<Foo>::uninit();
+    let slices = m::io_slices_mut!(&mut uninit, Foo, [a, c]);
+    assert_eq!(slices[1].len(), 8);
+}
+
+#[test]
+fn repr_c() {
+    let layout = m::repr_c_layout!([u32, [u8; 4], u64]);
+    assert_eq!(layout.offsets, [0, 4, 8]);
+    let packed = m::repr_c_layout!(packed [u8, #[align(4)] u32]);
+    assert_eq!(packed.offsets, [0, 1]);
+}
+
+impl std::fmt::Debug for Foo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        m::fmt_with_offsets!(f, self, Foo, [a, c])
+    }
+}
+
+#[test]
+fn debug() {
+    let foo = Foo {
+        a: 1,
+        b: [2; 4],
+        c: 3,
+    };
+    assert_eq!(format!("{:?}", foo), "Foo { a @ 0..4: 1, c @ 8..16: 3 }");
+}
+
+#[test]
+fn layout() {
+    let layout = m::type_layout!(Foo { a, b, c });
+    assert_eq!(layout.size, 16);
+    assert_eq!(layout.fields[2].offset, 8);
+}
+
+m::field_lookup!(Foo { a, b, c });
+
+#[test]
+fn field_lookup() {
+    assert_eq!(Foo::field_offset("c"), Some(8));
+    assert_eq!(Foo::field_span("b"), Some(4..8));
+    assert_eq!(Foo::field_span("d"), None);
+}
+
+m::field_enum!(enum FooField for Foo { A => a, B => b, C => c });
+
+#[test]
+fn abi() {
+    let table = m::abi_table!([Foo { a, b, c }]);
+    assert_eq!(table.len, 1);
+}
+
+#[test]
+fn field_enum() {
+    assert_eq!(FooField::ALL.len(), 3);
+    assert_eq!(FooField::C.offset(), 8);
+    assert_eq!(FooField::B.span(), 4..8);
+    assert_eq!(FooField::A.name(), "a");
+}
+
+#[test]
+fn array_parents() {
+    assert_eq!(m::offset_of!([Foo; 4], [2].c), 2 * 16 + 8);
+    assert_eq!(m::span_of!([Foo; 4], [1]..=[2]), 16..48);
+}
+
+m::define_offsets!(mod foo_offsets for Foo { a, b as bytes, c });
+
+#[test]
+fn define_offsets() {
+    assert_eq!(foo_offsets::a::OFFSET, 0);
+    assert_eq!(foo_offsets::bytes::SPAN, 4..8);
+    assert_eq!(foo_offsets::c::SIZE, 8);
+}
+
+// Generated calls often end their arguments with a comma, so every macro accepts one.
+#[test]
+fn trailing_commas() {
+    let mut foo = Foo {
+        a: 1,
+        b: [2; 4],
+        c: 3,
+    };
+    let base = &foo as *const Foo;
+    let bits = Bits { int: 0 };
+    let tup = (1u8, 2u32);
+    let mut buf = [0u8; 16];
+    let region = [0u64; 4];
+
+    m::assert_field_exists!(Foo, c,);
+    m::assert_field_exists!(union Bits, int,);
+    assert_eq!(m::offset_of!(Foo, c,), 8);
+    assert_eq!(unsafe { m::offset_of_unchecked!(Foo, c,) }, 8);
+    assert_eq!(m::offset_of_tuple!((u8, u32), 1,), 4);
+    assert_eq!(m::offset_of_union!(Bits, bytes,), 0);
+    assert_eq!(m::span_of_union!(Bits, int,), 0..4);
+    assert_eq!(m::span_of_pair!(Foo, a..c,), (0, 8));
+    assert_eq!(m::span_of_len!(Foo, b..=c,), (4, 12));
+    assert_eq!(m::nth_field_offset!(Foo, c, 1,), Some(24));
+    assert_eq!(m::nth_field_span!(Foo, b, 1,), Some(20..24));
+    assert_eq!(m::span_of!(Foo, c,), 8..16);
+    assert_eq!(m::span_of!(Foo, a..c,), 0..8);
+    assert_eq!(m::span_of!(Foo, ..,), 0..16);
+    assert_eq!(m::span_of!(Foo, ..=b,), 0..8);
+    assert_eq!(m::span_of!(Foo, {c, a},), 0..16);
+    assert_eq!(m::spans_of!(Foo, [c, a],), [8..16, 0..4]);
+    assert_eq!(m::padding_between!(Foo, a, b,), 0);
+    assert_eq!(m::padding_after!(Foo, c,), 0);
+    assert_eq!(m::span_of!(Foo, b[1..],), 5..8);
+    assert_eq!(unsafe { m::span_of_unchecked!(Foo, b..,) }, 4..16);
+    assert_eq!(m::raw_field!(base, Foo, c,), &foo.c as *const u64);
+    assert_eq!(unsafe { m::raw_field_unchecked!(base, Foo, a,) }, &foo.a as *const u32);
+    assert_eq!(m::container_of!(&foo.c as *const u64, Foo, c,), base);
+    assert_eq!(
+        m::raw_field_tuple!(&tup as *const (u8, u32), (u8, u32), 1,),
+        &tup.1 as *const u32
+    );
+    assert_eq!(
+        m::raw_field_union!(&bits as *const Bits, Bits, int,) as usize,
+        &bits as *const Bits as usize
+    );
+    assert!(m::placed_field!(region.as_ptr() as *const u8, 32, 8, Foo, c,).is_ok());
+    m::write_uint_field_be!(&mut buf, Foo, b, 7,);
+    assert_eq!(m::read_uint_field_be!(&buf, Foo, b,), 7);
+    assert!(m::split_at_field!(&buf, Foo, b,).is_some());
+    assert!(m::split_at_field_mut!(&mut buf, Foo, b,).is_some());
+    assert_eq!(m::split_fields_mut!(&mut buf, Foo, [a, c],).len(), 2);
+    assert_eq!(m::repr_c_layout!([u32, u64],).size, 16);
+    assert_eq!(m::type_layout!(Foo { a, b, c },).fields.len(), 3);
+    unsafe { m::zeroize_fields!(&mut foo, Foo, [a],) };
+    unsafe { m::zeroize_span!(&mut foo, Foo, b..=c,) };
+    assert_eq!((foo.a, foo.c), (0, 0));
+}
@@ -0,0 +1,18 @@
+//! A facade that re-exports the macros of `memoffset` under its own name.
+
+pub use memoffset::{
+    abi_table, assert_different_cachelines, assert_field_aligned_to, assert_field_exists,
+    assert_offset, assert_offsets, assert_prefix_layout, assert_same_cacheline, clone_field,
+    container_of, copy_field, define_offsets, element_offset, element_ptr, export_abi_table,
+    field_enum, field_lookup, fill_field_slice, fmt_with_offsets, init_field_slice, init_self_ref,
+    io_slices, io_slices_mut, is_sorted_by_field, layout_snapshot, nth_field_offset,
+    nth_field_span, offset_of, offset_of_as, offset_of_tail, offset_of_tuple, offset_of_unchecked,
+    offset_of_union, padding_after, padding_between, partial_init_from, placed_field,
+    raw_cell_field, raw_field, raw_field_tail, raw_field_tuple, raw_field_unchecked,
+    raw_field_union, read_uint_field_be, read_uint_field_le, repr_c_layout, roundtrip_tests,
+    search_by_field, search_slice_by_field, sort_by_field, span_of, span_of_as, span_of_len,
+    span_of_pair, span_of_unchecked, span_of_union, spans_of, split_at_field, split_at_field_mut,
+    split_fields_mut, target_layouts, type_layout, uninit_builder, uninit_field_bytes,
+    uninit_field_bytes_mut, verification_harnesses, write_uint_field_be, write_uint_field_le,
+    zeroize_fields, zeroize_span,
+};
@@ -0,0 +1,282 @@
+// Exercises every public macro from a `#![no_implicit_prelude]` module, through a path `m`
+// that the including module binds to a nested re-export of `memoffset`. Anything an expansion
+// takes from the prelude (`Some`, `Ok`, `assert_eq!`, trait methods, ...) fails to resolve here,
+// so this file itself only uses builtin macros and `::core`/`::std` paths, and imports nothing
+// that an expansion could pick up by accident.
+
+use ::core::mem::MaybeUninit;
+
+#[repr(C)]
+pub struct Foo {
+    a: u32,
+    b: [u8; 4],
+    c: u64,
+}
+
+#[repr(C, align(64))]
+pub struct Lines {
+    head: u32,
+    tail: u32,
+}
+
+#[repr(C)]
+pub union Bits {
+    int: u32,
+    bytes: [u8; 4],
+}
+
+fn foo() -> Foo {
+    Foo {
+        a: 1,
+        b: [2; 4],
+        c: 3,
+    }
+}
+
+m::assert_prefix_layout!(Lines, Lines, { head, tail });
+m::assert_same_cacheline!(Lines, head, tail);
+m::assert_different_cachelines!(Foo, a, c, 8);
+m::assert_field_aligned_to!(Foo, c, 8);
+m::assert_offset!(Foo, b, 4);
+m::assert_offsets!(Foo { a: 0, c: 8 });
+m::target_layouts!(Foo {
+    all() => { size: 16, c: 8 },
+});
+
+m::roundtrip_tests!(mod foo_roundtrip for Foo { a, b, c });
+m::verification_harnesses!(mod foo_proofs for Foo { a, b, c });
+m::field_enum!(enum FooField for Foo { A => a, B => b, C => c });
+m::field_lookup!(Foo { a, b, c });
+m::define_offsets!(mod foo_offsets for Foo { a, b as bytes, c });
+
+#[test]
+fn offsets() {
+    m::assert_field_exists!(Foo, c);
+    m::assert_field_exists!(union Bits, int);
+    assert!(m::offset_of!(Foo, c) == 8);
+    assert!(m::offset_of!(Foo, b[2]) == 6);
+    assert!(unsafe { m::offset_of_unchecked!(Foo, b) } == 4);
+    assert!(m::offset_of_tuple!((u8, u32), 1) == 4);
+    assert!(m::offset_of_union!(Bits, bytes) == 0);
+    assert!(m::offset_of_as!(Foo, c, u32) == 8u32);
+    assert!(m::nth_field_offset!(Foo, c, 2) == ::core::option::Option::Some(40));
+    assert!(m::nth_field_span!(Foo, b, 1) == ::core::option::Option::Some(20..24));
+    assert!(m::nth_field_span!(Foo, b, !0) == ::core::option::Option::None);
+
+    let i = 3;
+    assert!(m::element_offset!(Foo, b, i) == 7);
+    assert!(m::offset_of!([Foo; 4], [i].b) == 52);
+    let foo = foo();
+    assert!(unsafe { *m::element_ptr!(&foo as *const Foo, Foo, b, i) } == 2);
+}
+
+#[test]
+fn spans() {
+    assert!(m::span_of!(Foo, b..=c) == (4..16));
+    assert!(m::span_of!(Foo, b[1..3]) == (5..7));
+    assert!(m::span_of!(Foo, b[..2]) == (4..6));
+    assert!(m::span_of!(Foo, b[1..]) == (5..8));
+    assert!(m::span_of!(Foo, {c, b[3]}) == (7..16));
+    assert!(m::spans_of!(Foo, [c, b[3]]) == [8..16, 7..8]);
+    assert!(m::padding_between!(Foo, a, c) == 4);
+    assert!(m::padding_after!(Foo, b) == 8);
+    assert!(unsafe { m::span_of_unchecked!(Foo, a..c) } == (0..8));
+    assert!(m::span_of_as!(Foo, a..=c, u16) == (0u16..16));
+    assert!(m::span_of!([Foo; 4], [1]..) == (16..64));
+    assert!(m::span_of_union!(Bits, bytes[..2]) == (0..2));
+    assert!(m::span_of_pair!(Foo, b..=c) == (4, 16));
+    assert!(m::span_of_len!(Foo, b[1..]) == (5, 3));
+}
+
+#[test]
+fn raw_fields() {
+    let foo = foo();
+    let base = &foo as *const Foo;
+    assert!(m::raw_field!(base, Foo, c) == &foo.c as *const u64);
+    assert!(unsafe { m::raw_field_unchecked!(base, Foo, a) } == &foo.a as *const u32);
+    assert!(m::container_of!(&foo.c as *const u64, Foo, c) == base);
+
+    let tup = (1u8, 2u32);
+    let tup_base = &tup as *const (u8, u32);
+    assert!(m::raw_field_tuple!(tup_base, (u8, u32), 1) == &tup.1 as *const u32);
+
+    let bits = Bits { int: 0 };
+    let bits_base = &bits as *const Bits;
+    assert!(m::raw_field_union!(bits_base, Bits, int) as usize == bits_base as usize);
+
+    #[repr(C)]
+    struct Slot {
+        seq: u64,
+        value: ::core::cell::Cell<u32>,
+    }
+    let slot = Slot {
+        seq: 0,
+        value: ::core::cell::Cell::new(1),
+    };
+    let value = m::raw_cell_field!(&slot as *const Slot, Slot, value);
+    unsafe { *value = 2 };
+    assert!(slot.seq == 0 && slot.value.get() == 2);
+}
+
+#[test]
+fn tails() {
+    #[repr(C)]
+    struct Packet {
+        len: u32,
+        data: [u8],
+    }
+
+    let buf = [0u32; 4];
+    let packet = ::core::ptr::slice_from_raw_parts(buf.as_ptr() as *const u8, 3) as *const Packet;
+    assert!(m::offset_of_tail!(packet, Packet, data) == 4);
+    assert!(unsafe { (&*m::raw_field_tail!(packet, Packet, data)).len() } == 3);
+}
+
+#[test]
+fn search() {
+    let mut foos = [foo(), foo()];
+    foos[1].a = 3;
+    assert!(m::search_slice_by_field!(&foos, Foo, a, &1) == ::core::result::Result::Ok(0));
+    assert!(m::search_slice_by_field!(&foos, Foo, a, &2) == ::core::result::Result::Err(1));
+    assert!(m::is_sorted_by_field!(&foos, Foo, a));
+    foos[0].a = 4;
+    assert!(!m::is_sorted_by_field!(&foos, Foo, a));
+    m::sort_by_field!(&mut foos, Foo, a);
+    assert!(foos[0].a == 3);
+    let bytes = [0u8; 32];
+    assert!(unsafe { m::search_by_field!(&bytes, Foo, c, &1) } == ::core::result::Result::Err(2));
+}
+
+#[test]
+fn uint() {
+    let mut buf = [0u8; 16];
+    m::write_uint_field_be!(&mut buf, Foo, b, 0x0102_0304);
+    m::write_uint_field_le!(&mut buf, Foo, b, m::read_uint_field_le!(&buf, Foo, b));
+    assert!(m::read_uint_field_be!(&buf, Foo, b) == 0x0102_0304);
+}
+
+#[test]
+fn split() {
+    let mut buf = [0u8; 16];
+    let [a, c] = m::split_fields_mut!(&mut buf, Foo, [a, c]);
+    assert!(a.len() == 4 && c.len() == 8);
+    let (a, b, rest) = m::split_at_field_mut!(&mut buf, Foo, b).unwrap();
+    assert!(a.len() == 4 && b.len() == 4 && rest.len() == 8);
+    assert!(m::split_at_field!(&buf, Foo, c).is_some());
+    assert!(m::split_at_field!(&buf[..15], Foo, b).is_none());
+}
+
+#[test]
+fn placement() {
+    let region = [0u64; 4];
+    let base = region.as_ptr() as *const u8;
+    let c = m::placed_field!(base, 32, 8, Foo, c).unwrap();
+    assert!(c as usize == base as usize + 16);
+}
+
+#[test]
+fn init() {
+    let foo = foo();
+    let mut uninit = MaybeUninit::<Foo>::uninit();
+    unsafe { m::partial_init_from!(&mut uninit, &foo, Foo, [a, b, c]) };
+    assert!(m::uninit_field_bytes!(&uninit, Foo, b).len() == 4);
+    assert!(m::uninit_field_bytes_mut!(&mut uninit, Foo, c).len() == 8);
+    let mut copy = unsafe { uninit.assume_init() };
+    assert!(copy.c == 3);
+    let mut other = self::foo();
+    other.b = [5; 4];
+    other.c = 6;
+    m::copy_field!(&mut copy, &other, Foo, c);
+    m::clone_field!(&mut copy, &other, Foo, b);
+    assert!(copy.b == [5; 4] && copy.c == 6);
+
+    let mut foos: [MaybeUninit<Foo>; 3] = unsafe { MaybeUninit::uninit().assume_init() };
+    m::init_field_slice!(&mut foos, Foo, a, |i| i as u32);
+    m::fill_field_slice!(&mut foos, Foo, b, [1; 4]);
+    m::fill_field_slice!(&mut foos, Foo, c, 2);
+    assert!(unsafe { &*foos[2].as_ptr() }.a == 2);
+}
+
+#[test]
+fn builder() {
+    m::uninit_builder! {
+        struct FooBuilder for Foo {
+            a: u32 => set_a,
+            b: [u8; 4] => set_b,
+            c: u64 => set_c,
+        }
+    }
+
+    let foo = Foo::uninit_builder().set_c(3).set_a(1).set_b([2; 4]).finish().unwrap();
+    assert!(foo.a == 1 && foo.b == [2; 4] && foo.c == 3);
+    assert!(Foo::uninit_builder().set_a(1).finish().is_none());
+}
+
+#[test]
+fn self_ref() {
+    struct Node {
+        value: u32,
+        this: *const u32,
+    }
+
+    let node = m::init_self_ref!(Node { value: 1 }, this => value);
+    assert!(node.this == &node.value as *const u32);
+}
+
+#[test]
+fn zeroize() {
+    let mut foo = foo();
+    unsafe { m::zeroize_fields!(&mut foo, Foo, [a]) };
+    unsafe { m::zeroize_span!(&mut foo, Foo, b..=c) };
+    assert!(foo.a == 0 && foo.b == [0; 4] && foo.c == 0);
+}
+
+#[test]
+fn io() {
+    let foo = foo();
+    let slices = unsafe { m::io_slices!(&foo, Foo, [a, b[1..], c]) };
+    assert!(slices[1].len() == 3);
+
+    let mut uninit = MaybeUninit::<Foo>::uninit();
+    let slices = m::io_slices_mut!(&mut uninit, Foo, [a, c]);
+    assert!(slices[1].len() == 8);
+}
+
+#[test]
+fn layouts() {
+    let layout = m::repr_c_layout!([u32, [u8; 4], u64]);
+    assert!(layout.offsets == [0, 4, 8]);
+
+    let layout = m::type_layout!(Foo { a, b, c });
+    assert!(layout.size == 16 && layout.fields[2].offset == 8);
+
+    let table = m::abi_table!([Foo { a, b, c }]);
+    assert!(table.len == 1);
+
+    assert!(Foo::field_offset("c") == ::core::option::Option::Some(8));
+    assert!(Foo::field_span("d") == ::core::option::Option::None);
+}
+
+#[test]
+fn field_enum() {
+    assert!(FooField::ALL.len() == 3);
+    assert!(FooField::C.offset() == 8);
+    assert!(FooField::B.span() == (4..8));
+}
+
+#[test]
+fn define_offsets() {
+    assert!(foo_offsets::c::OFFSET == 8);
+    assert!(foo_offsets::bytes::SPAN == (4..8));
+}
+
+impl ::core::fmt::Debug for Foo {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        m::fmt_with_offsets!(f, self, Foo, [a, c])
+    }
+}
+
+#[test]
+fn debug() {
+    assert!(::std::format!("{:?}", foo()) == "Foo { a @ 0..4: 1, c @ 8..16: 3 }");
+}